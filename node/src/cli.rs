@@ -1,5 +1,6 @@
 use structopt::StructOpt;
 use sc_cli::RunCmd;
+use sp_core::H256;
 
 #[derive(Debug, StructOpt)]
 pub struct Cli {
@@ -8,6 +9,11 @@ pub struct Cli {
 
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	/// The key the rewards of the blocks authored by this node are paid to, instead of the
+	/// session key.
+	#[structopt(long)]
+	pub payout_key: Option<H256>,
 }
 
 #[derive(Debug, StructOpt)]
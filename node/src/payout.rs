@@ -0,0 +1,59 @@
+//! Inherent data provider claiming the block reward for a payout key.
+
+use std::sync::Arc;
+use sp_blockchain::HeaderBackend;
+use sp_core::{crypto::{key_types::AURA, CryptoTypePublicPair, Public}, sr25519, H256, H512};
+use sp_inherents::{InherentData, InherentIdentifier, ProvideInherentData};
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+use utxo_runtime::opaque::Block;
+use pallet_utxo::{payout_payload, PayoutClaim, INHERENT_IDENTIFIER};
+
+/// Provides a [`PayoutClaim`] for `payout`, signed with the first AURA key of the keystore.
+pub struct PayoutInherentDataProvider<C> {
+	client: Arc<C>,
+	keystore: SyncCryptoStorePtr,
+	payout: H256,
+}
+
+impl<C> PayoutInherentDataProvider<C> {
+	pub fn new(client: Arc<C>, keystore: SyncCryptoStorePtr, payout: H256) -> Self {
+		Self { client, keystore, payout }
+	}
+}
+
+impl<C: HeaderBackend<Block> + Send + Sync> ProvideInherentData for PayoutInherentDataProvider<C> {
+	fn inherent_identifier(&self) -> &'static InherentIdentifier {
+		&INHERENT_IDENTIFIER
+	}
+
+	fn provide_inherent_data(
+		&self,
+		inherent_data: &mut InherentData,
+	) -> Result<(), sp_inherents::Error> {
+		// the claim is for the block built on top of the best one
+		let block = self.client.info().best_number as u64 + 1;
+
+		let keys = SyncCryptoStore::sr25519_public_keys(&*self.keystore, AURA);
+		let key = match keys.into_iter().next() {
+			Some(key) => key,
+			// without a session key the runtime pays the author's key anyway
+			None => return Ok(()),
+		};
+
+		let payload = payout_payload(&self.payout, block);
+		let key = CryptoTypePublicPair(sr25519::CRYPTO_ID, key.to_raw_vec());
+		let signature = SyncCryptoStore::sign_with(&*self.keystore, AURA, &key, &payload)
+			.map_err(|e| sp_inherents::Error::from(format!("can't sign the payout claim: {}", e)))?;
+
+		if signature.len() != 64 {
+			return Err("unexpected payout claim signature length".into());
+		}
+
+		let claim = PayoutClaim { payout: self.payout, signature: H512::from_slice(&signature) };
+		inherent_data.put_data(INHERENT_IDENTIFIER, &claim)
+	}
+
+	fn error_to_string(&self, _error: &[u8]) -> Option<String> {
+		None
+	}
+}
@@ -2,7 +2,7 @@
 
 use std::sync::Arc;
 use std::time::Duration;
-use sc_client_api::{ExecutorProvider, RemoteBackend};
+use sc_client_api::{Backend, ExecutorProvider, RemoteBackend};
 use utxo_runtime::{self, opaque::Block, RuntimeApi};
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sp_inherents::InherentDataProviders;
@@ -11,6 +11,8 @@ pub use sc_executor::NativeExecutor;
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
 use sc_finality_grandpa::SharedVoterState;
 use sc_keystore::LocalKeystore;
+use sp_core::H256;
+use crate::payout::PayoutInherentDataProvider;
 
 // Our native executor instance.
 native_executor_instance!(
@@ -100,7 +102,10 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 }
 
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+	mut config: Configuration,
+	payout_key: Option<H256>,
+) -> Result<TaskManager, ServiceError> {
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -152,12 +157,14 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let offchain_storage = backend.offchain_storage();
 
 		Box::new(move |deny_unsafe, _| {
 			let deps = crate::rpc::FullDeps {
 				client: client.clone(),
 				pool: pool.clone(),
 				deny_unsafe,
+				offchain_storage: offchain_storage.clone(),
 			};
 
 			crate::rpc::create_full(deps)
@@ -182,6 +189,16 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	)?;
 
 	if role.is_authority() {
+		if let Some(payout_key) = payout_key {
+			inherent_data_providers
+				.register_provider(PayoutInherentDataProvider::new(
+					client.clone(),
+					keystore_container.sync_keystore(),
+					payout_key,
+				))
+				.map_err(sp_consensus::Error::InherentData)?;
+		}
+
 		let proposer_factory = sc_basic_authorship::ProposerFactory::new(
 			task_manager.spawn_handle(),
 			client.clone(),
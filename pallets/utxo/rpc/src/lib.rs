@@ -0,0 +1,189 @@
+//! RPC interface for the UTXO pallet.
+//!
+//! [`UtxoApi`] answers queries from the runtime state, [`UtxoIndexApi`] from the owner index the
+//! pallet's offchain worker maintains in the node's offchain storage.
+//!
+//! Queries about past blocks are answered from the state of that block, so they only succeed
+//! for blocks whose state has not been pruned. Run the node with `--pruning archive` to be able
+//! to query the whole history.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Decode;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_utxo::trie_proof::check_utxo_proof;
+use sc_client_api::ProofProvider;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{offchain::OffchainStorage, Bytes, H256};
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
+
+pub use pallet_utxo_runtime_api::{TransactionOutput, UtxoApi as UtxoRuntimeApi, Value};
+
+/// Error code for calls into the runtime that failed.
+const RUNTIME_ERROR: i64 = 1;
+/// Error code for queries about blocks the node doesn't know about.
+const UNKNOWN_BLOCK: i64 = 2;
+/// Error code for offchain index entries that can't be decoded.
+const CORRUPT_INDEX: i64 = 3;
+
+/// The most UTXOs a single `utxo_utxosPaged` call returns.
+const MAX_PAGE_SIZE: u32 = 1_000;
+
+#[rpc]
+pub trait UtxoApi<BlockNumber> {
+	/// Total value owned by `pubkey` as of block number `block`. Requires an archive node for
+	/// blocks whose state would otherwise have been pruned.
+	#[rpc(name = "utxo_balanceAt")]
+	fn balance_at(&self, pubkey: H256, block: BlockNumber) -> Result<Value>;
+
+	/// Up to `limit` UTXOs as of block number `block`, at most `MAX_PAGE_SIZE`, after the cursor
+	/// `start_key`, and the cursor of the next page if any are left. Read every page at the same
+	/// block for them to add up to the UTXO set.
+	#[rpc(name = "utxo_utxosPaged")]
+	fn utxos_paged(
+		&self,
+		start_key: Option<Bytes>,
+		limit: u32,
+		block: BlockNumber,
+	) -> Result<(Vec<(H256, TransactionOutput)>, Option<Bytes>)>;
+
+	/// The output unspent at `outpoint` as of block number `block` and the trie nodes proving it
+	/// against the state root of that block, `None` if `outpoint` isn't unspent.
+	#[rpc(name = "utxo_trieProof")]
+	fn trie_proof(
+		&self,
+		outpoint: H256,
+		block: BlockNumber,
+	) -> Result<Option<(TransactionOutput, Vec<Bytes>)>>;
+}
+
+/// Implements the [`UtxoApi`] RPC trait on top of the runtime API.
+pub struct Utxo<C, Block> {
+	client: Arc<C>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> Utxo<C, Block> {
+	/// Create a new `Utxo` RPC handler backed by the given client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block> Utxo<C, Block> where
+	Block: BlockT,
+	C: HeaderBackend<Block>,
+{
+	fn block_id(&self, number: NumberFor<Block>) -> Result<BlockId<Block>> {
+		let hash = self.client.hash(number)
+			.map_err(runtime_error)?
+			.ok_or_else(|| RpcError {
+				code: ErrorCode::ServerError(UNKNOWN_BLOCK),
+				message: format!("Unknown block number {:?}", number),
+				data: None,
+			})?;
+
+		Ok(BlockId::hash(hash))
+	}
+}
+
+impl<C, Block> UtxoApi<NumberFor<Block>> for Utxo<C, Block> where
+	Block: BlockT<Hash = H256>,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + ProofProvider<Block>,
+	C: Send + Sync + 'static,
+	C::Api: UtxoRuntimeApi<Block, NumberFor<Block>>,
+{
+	fn balance_at(&self, pubkey: H256, block: NumberFor<Block>) -> Result<Value> {
+		let at = self.block_id(block)?;
+
+		self.client.runtime_api().balance_of(&at, pubkey).map_err(runtime_error)
+	}
+
+	fn utxos_paged(
+		&self,
+		start_key: Option<Bytes>,
+		limit: u32,
+		block: NumberFor<Block>,
+	) -> Result<(Vec<(H256, TransactionOutput)>, Option<Bytes>)> {
+		let at = self.block_id(block)?;
+		let start_key = start_key.map(|key| key.to_vec());
+
+		let (page, cursor) = self.client
+			.runtime_api()
+			.utxos_paged(&at, start_key, limit.min(MAX_PAGE_SIZE))
+			.map_err(runtime_error)?;
+
+		Ok((page, cursor.map(Bytes::from)))
+	}
+
+	fn trie_proof(
+		&self,
+		outpoint: H256,
+		block: NumberFor<Block>,
+	) -> Result<Option<(TransactionOutput, Vec<Bytes>)>> {
+		let at = self.block_id(block)?;
+		let root = match self.client.header(at).map_err(runtime_error)? {
+			Some(header) => *header.state_root(),
+			None => return Ok(None),
+		};
+
+		let key = self.client
+			.runtime_api()
+			.utxo_storage_key(&at, outpoint)
+			.map_err(runtime_error)?;
+		let proof = self.client
+			.read_proof(&at, &mut std::iter::once(&key[..]))
+			.map_err(runtime_error)?;
+
+		// the proof of an outpoint that isn't unspent only proves its absence
+		let nodes = proof.clone().iter_nodes().map(Bytes::from).collect();
+		Ok(check_utxo_proof(root, &outpoint, proof).map(|utxo| (utxo, nodes)))
+	}
+}
+
+fn runtime_error<E: std::fmt::Debug>(e: E) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Unable to query the UTXO state. Is the node running in archive mode?".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+#[rpc]
+pub trait UtxoIndexApi {
+	/// Outpoints owned by `pubkey` on the best chain, as seen by the offchain worker. Empty when
+	/// the node doesn't run offchain workers.
+	#[rpc(name = "utxo_ownedBy")]
+	fn owned_by(&self, pubkey: H256) -> Result<Vec<H256>>;
+}
+
+/// Implements the [`UtxoIndexApi`] RPC trait on top of the node's offchain storage.
+pub struct UtxoIndex<S> {
+	storage: S,
+}
+
+impl<S> UtxoIndex<S> {
+	/// Create a new `UtxoIndex` RPC handler reading from the given offchain storage.
+	pub fn new(storage: S) -> Self {
+		Self { storage }
+	}
+}
+
+impl<S> UtxoIndexApi for UtxoIndex<S> where
+	S: OffchainStorage + 'static,
+{
+	fn owned_by(&self, pubkey: H256) -> Result<Vec<H256>> {
+		let key = pallet_utxo::offchain::owned_key(&pubkey);
+
+		match self.storage.get(sp_offchain::STORAGE_PREFIX, &key) {
+			None => Ok(Vec::new()),
+			Some(encoded) => Vec::<H256>::decode(&mut &encoded[..]).map_err(|e| RpcError {
+				code: ErrorCode::ServerError(CORRUPT_INDEX),
+				message: "Unable to decode the offchain UTXO index".into(),
+				data: Some(format!("{:?}", e).into()),
+			}),
+		}
+	}
+}
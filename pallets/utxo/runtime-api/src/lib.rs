@@ -0,0 +1,77 @@
+//! Runtime API definition for the UTXO pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_core::H256;
+use sp_std::vec::Vec;
+
+pub use pallet_utxo::{
+	merkle::UtxoProof, AppliedStatus, DryRunError, DryRunResult, RewardInfo, Transaction,
+	TransactionOutput, Value,
+};
+
+sp_api::decl_runtime_apis! {
+	#[api_version(2)]
+	pub trait UtxoApi<BlockNumber> where
+		BlockNumber: Codec,
+	{
+		/// Total value owned by `pubkey` in the state of the block the call is made at.
+		fn balance_of(pubkey: H256) -> Value;
+
+		/// Merkle proof that `outpoint` is unspent, `None` if it isn't part of the UTXO set.
+		fn utxo_proof(outpoint: H256) -> Option<UtxoProof>;
+
+		/// The raw storage key of the UTXO set entry of `outpoint`, whose trie proof against the
+		/// state root of the block shows that `outpoint` is unspent.
+		fn utxo_storage_key(outpoint: H256) -> Vec<u8>;
+
+		/// Validate `tx` and report the outpoints it would spend, the outputs it would create and
+		/// its fee without applying it. Fails with the reason validation failed.
+		#[changed_in(2)]
+		fn dry_run(tx: Transaction) -> Result<DryRunResult, Vec<u8>>;
+
+		/// Validate `tx` and report the outpoints it would spend, the outputs it would create and
+		/// its fee without applying it. Fails with the reason validation failed and the index of
+		/// the input it failed at, if it is about a single input.
+		fn dry_run(tx: Transaction) -> Result<DryRunResult, DryRunError>;
+
+		/// Confirmations `outpoint` has as of block `at`, `None` if it isn't unspent.
+		fn confirmations(outpoint: H256, at: BlockNumber) -> Option<u32>;
+
+		/// The keys of the outputs `tx` creates, its change output last if it sets `change_to`.
+		fn outpoints_for(tx: Transaction) -> Vec<H256>;
+
+		/// Whether `tx` was applied and how many of its outputs were spent since, from the
+		/// outputs it creates other than its change output.
+		fn is_applied(tx: Transaction) -> AppliedStatus;
+
+		/// Up to `limit` entries of the UTXO set after the cursor `start_key`, from the first
+		/// entry without one, and the cursor of the next page if any entries are left. Pages
+		/// only add up to the UTXO set when they are all read at the same block hash.
+		fn utxos_paged(
+			start_key: Option<Vec<u8>>,
+			limit: u32,
+		) -> (Vec<(H256, TransactionOutput)>, Option<Vec<u8>>);
+
+		/// The txids of the transactions applied in `block`, in order. Empty for blocks older than
+		/// `TxIndexRetention`.
+		fn transactions_at(block: BlockNumber) -> Vec<H256>;
+
+		/// The outpoints created in the block the call is made at, in order, the reward outputs
+		/// last. The list is reset at the start of every block, so it is only complete at the
+		/// state of the block itself, ask an archive node about older blocks.
+		fn created_in_current_block() -> Vec<H256>;
+
+		/// The fee quoted for a spend with `inputs` inputs and `outputs` outputs, from its weight.
+		fn estimate_fee(inputs: u32, outputs: u32) -> Value;
+
+		/// The fee a transaction of weight `tx_weight` should pay to be included within
+		/// `target_blocks`, from the fees recent blocks paid per unit of weight.
+		fn estimate_fee_within(target_blocks: u32, tx_weight: u64) -> Value;
+
+		/// The fees collected in the block the call is made at so far, the subsidy it issues and
+		/// the outpoint of the last reward paid to a block author.
+		fn reward_info() -> RewardInfo;
+	}
+}
@@ -1,26 +1,163 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarks for pallet-utxo
 
 use super::*;
 
 use frame_system::RawOrigin;
 use frame_benchmarking::{benchmarks, whitelisted_caller, impl_benchmark_test_suite};
+use sp_core::crypto::KeyTypeId;
 use sp_std::{vec, vec::Vec, boxed::Box};
 
 #[allow(unused)]
-use crate::Module as Template;
+use crate::Module as UtxoModule;
+
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"utxo");
+const MAX_INPUTS: u32 = 100;
+const MAX_OUTPUTS: u32 = 100;
+const INPUT_VALUE: Value = 100;
+const WITNESS_BYTES: usize = 64;
+const MAX_DEDUPE_INPUTS: u32 = 64;
 
 benchmarks! {
-	do_something {
-		let s in 0 .. 100;
+	spend {
+		let i in 1 .. MAX_INPUTS;
+		let o in 1 .. MAX_OUTPUTS.min(T::MaxOutputsPerBlock::get());
+		let w in 0 .. T::MaxWitnessBytes::get();
+
+		let owner = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+		let pubkey = H256::from(owner.0);
+
+		let mut tx = Transaction::default();
+		for index in 0 .. i {
+			let outpoint = BlakeTwo256::hash_of(&(b"bench", index));
+			let utxo = TransactionOutput { value: INPUT_VALUE, pubkey, ..Default::default() };
+			Module::<T>::insert_utxo(outpoint, utxo);
+			tx.inputs.push(TransactionInput { outpoint, sigscript: H512::zero(), witness: Vec::new() });
+		}
+		tx.inputs[0].witness = vec![0; w as usize];
+
+		for index in 0 .. o {
+			// the zero key can't be paid
+			let pubkey = H256::from_low_u64_be(index as u64 + 1);
+			tx.outputs.push(TransactionOutput { value: 1, pubkey, ..Default::default() });
+		}
+
+		let payload = UtxoModule::<T>::signing_payload(&tx);
+		let signature = sp_io::crypto::sr25519_sign(KEY_TYPE, &owner, &payload)
+			.expect("the key was generated in the keystore; qed");
+		for input in tx.inputs.iter_mut() {
+			input.sigscript = H512::from(signature.0);
+		}
+
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), s)
+	}: _(RawOrigin::Signed(caller), tx)
 	verify {
-		assert_eq!(Something::<T>::get(), Some(s));
+		assert_eq!(RewardTotal::get(), INPUT_VALUE * i as Value - o as Value);
+	}
+
+	// decoding a transaction with `b` bytes of memo and hashing its signing payload for the
+	// txid and every output key, the parts of `spend` growing with its encoded length
+	spend_bytes {
+		let b in 0 .. T::MaxTxBytes::get();
+
+		let mut tx = signed_inputs_tx(1);
+		tx.outputs[0].memo = vec![0; b as usize];
+		let encoded = tx.encode();
+	}: {
+		let tx = Transaction::decode(&mut &encoded[..])
+			.expect("the transaction was encoded above; qed");
+		crate::txid(&tx);
+		Module::<T>::output_keys(&tx);
+	}
+
+	// the signing payload of a `MAX_INPUTS` input transaction, against `sighash_by_copy`
+	sighash_bytes {
+		let tx = signed_inputs_tx(MAX_INPUTS);
+	}: {
+		crate::sighash_bytes(&tx);
+	}
+
+	// what `sighash_bytes` replaced: copying the transaction to clear it before encoding it
+	sighash_by_copy {
+		let tx = signed_inputs_tx(MAX_INPUTS);
+	}: {
+		let mut copy = tx.clone();
+		for input in copy.inputs.iter_mut() {
+			input.sigscript = H512::zero();
+			input.witness.clear();
+		}
+		copy.encode();
+	}
+
+	// finding a duplicate among `MAX_DEDUPE_INPUTS` inputs, against `dedupe_by_map`
+	dedupe_by_sorting {
+		let tx = signed_inputs_tx(MAX_DEDUPE_INPUTS);
+	}: {
+		duplicate_outpoint(&tx.inputs);
 	}
+
+	// what `duplicate_outpoint` replaced: collecting the whole inputs into a map
+	dedupe_by_map {
+		let tx = signed_inputs_tx(MAX_DEDUPE_INPUTS);
+	}: {
+		let set: BTreeMap<_, ()> = tx.inputs.iter().map(|input| (input, ())).collect();
+		assert_eq!(set.len(), tx.inputs.len());
+	}
+
+	// turning away a transaction spending `MAX_INPUTS` outpoints the UTXO set doesn't hold,
+	// reading the outputs and first input from storage, against `missing_inputs_by_filter`
+	missing_inputs_by_store {
+		let (_, tx) = bogus_inputs_tx::<T>();
+	}: {
+		let reason = Module::<T>::validate_transaction(&tx).err();
+		assert_eq!(reason, Some(Error::<T>::MissingInput.into()));
+	}
+
+	// the same with the outpoints of the UTXO set in a bloom filter, reading none of them
+	missing_inputs_by_filter {
+		let (filter, tx) = bogus_inputs_tx::<T>();
+	}: {
+		let reason = Module::<T>::validate_transaction_filtered(&filter, &tx).err();
+		assert_eq!(reason, Some(Error::<T>::MissingInput.into()));
+	}
+}
+
+// A transaction with `inputs` inputs carrying signatures and witnesses, to be stripped of them
+fn signed_inputs_tx(inputs: u32) -> Transaction {
+	let mut tx = Transaction::default();
+	for index in 0 .. inputs {
+		let outpoint = BlakeTwo256::hash_of(&(b"bench", index));
+		let witness = vec![0; WITNESS_BYTES];
+		tx.inputs.push(TransactionInput { outpoint, sigscript: H512::repeat_byte(1), witness });
+	}
+	tx.outputs.push(TransactionOutput { value: 1, ..Default::default() });
+
+	tx
+}
+
+// `MAX_INPUTS` stored outputs in a filter, and a transaction spending as many outpoints besides
+// them with `MAX_OUTPUTS` outputs
+fn bogus_inputs_tx<T: Config>() -> (OutpointFilter, Transaction) {
+	let mut outpoints = Vec::new();
+	for index in 0 .. MAX_INPUTS {
+		let outpoint = BlakeTwo256::hash_of(&(b"stored", index));
+		let pubkey = H256::repeat_byte(1);
+		let utxo = TransactionOutput { value: INPUT_VALUE, pubkey, ..Default::default() };
+		Module::<T>::insert_utxo(outpoint, utxo);
+		outpoints.push(outpoint);
+	}
+	let filter = OutpointFilter::from_outpoints(&outpoints, 0);
+
+	let mut tx = signed_inputs_tx(MAX_INPUTS);
+	for index in 1 .. MAX_OUTPUTS {
+		let pubkey = H256::from_low_u64_be(index as u64);
+		tx.outputs.push(TransactionOutput { value: 1, pubkey, ..Default::default() });
+	}
+
+	(filter, tx)
 }
 
 impl_benchmark_test_suite!(
-	Template,
-	crate::mock::new_test_ext(),
+	UtxoModule,
+	crate::mock::new_benchmark_ext(),
 	crate::mock::Test,
 );
@@ -0,0 +1,99 @@
+//! A bloom filter of the outpoints of the UTXO set, for nodes validating many transactions to turn
+//! away those spending outpoints the set surely doesn't hold without reading storage.
+//!
+//! A bloom filter has no false negatives: an outpoint it doesn't hold isn't part of the set it
+//! was filled from. It does have false positives, the lookups of those fall through to the set
+//! itself. Outputs are never taken out again, so a filter gets less selective as the outputs it
+//! holds are spent and is best rebuilt from time to time.
+
+use codec::Encode;
+use sp_core::H256;
+use sp_std::{vec, vec::Vec};
+
+use crate::{provider::UtxoProvider, TransactionOutput};
+
+/// The bits a filter spends per outpoint it is sized for, about 1% false positives along with
+/// `HASHES`.
+pub const BITS_PER_OUTPOINT: usize = 10;
+
+/// The number of bits every outpoint sets.
+pub const HASHES: u64 = 7;
+
+/// The outpoints a set of outputs may hold, see the module docs.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct OutpointFilter {
+	bits: Vec<u64>,
+	seed: u64,
+}
+
+impl OutpointFilter {
+	/// An empty filter sized for `outpoints` outpoints. Outpoints are hashed along with `seed`, a
+	/// seed of the node's own choosing keeps others from crafting outpoints its filter holds.
+	pub fn with_capacity(outpoints: usize, seed: u64) -> Self {
+		let words = outpoints.saturating_mul(BITS_PER_OUTPOINT) / 64 + 1;
+
+		Self { bits: vec![0; words], seed }
+	}
+
+	/// A filter holding every outpoint of `outpoints`, sized for as many as there are.
+	pub fn from_outpoints(outpoints: &[H256], seed: u64) -> Self {
+		let mut filter = Self::with_capacity(outpoints.len(), seed);
+		for outpoint in outpoints {
+			filter.insert(outpoint);
+		}
+
+		filter
+	}
+
+	/// Add `outpoint`, e.g. for an output created since the filter was built.
+	pub fn insert(&mut self, outpoint: &H256) {
+		for bit in self.bit_indices(outpoint) {
+			self.bits[bit / 64] |= 1u64 << (bit % 64);
+		}
+	}
+
+	/// `false` if `outpoint` surely isn't part of the filtered set.
+	pub fn may_contain(&self, outpoint: &H256) -> bool {
+		self.bit_indices(outpoint).all(|bit| self.bits[bit / 64] & (1u64 << (bit % 64)) != 0)
+	}
+
+	// the bits `outpoint` sets, from two hashes of it by double hashing
+	fn bit_indices(&self, outpoint: &H256) -> impl Iterator<Item = usize> {
+		let hash = sp_io::hashing::twox_128(&(self.seed, outpoint).encode());
+		let mut halves = [0u8; 8];
+		halves.copy_from_slice(&hash[..8]);
+		let first = u64::from_le_bytes(halves);
+		halves.copy_from_slice(&hash[8..]);
+		// odd, so the indices don't repeat before every bit was visited
+		let second = u64::from_le_bytes(halves) | 1;
+		let len = self.bits.len() as u64 * 64;
+
+		(0..HASHES).map(move |i| (first.wrapping_add(i.wrapping_mul(second)) % len) as usize)
+	}
+}
+
+/// The outputs of `utxos`, looked up only for outpoints `filter` may contain.
+pub struct FilteredUtxos<'a, P> {
+	filter: &'a OutpointFilter,
+	utxos: P,
+}
+
+impl<'a, P: UtxoProvider> FilteredUtxos<'a, P> {
+	pub fn new(filter: &'a OutpointFilter, utxos: P) -> Self {
+		Self { filter, utxos }
+	}
+}
+
+impl<'a, P: UtxoProvider> UtxoProvider for FilteredUtxos<'a, P> {
+	fn utxo(&self, outpoint: &H256) -> Option<TransactionOutput> {
+		if !self.filter.may_contain(outpoint) {
+			return None;
+		}
+
+		self.utxos.utxo(outpoint)
+	}
+
+	fn contains(&self, outpoint: &H256) -> bool {
+		self.filter.may_contain(outpoint) && self.utxos.contains(outpoint)
+	}
+}
@@ -0,0 +1,111 @@
+//! Helpers for building and signing transactions off-chain.
+
+use sp_core::{sr25519, Pair, H256, H512};
+
+use crate::{
+	signing_payload, AssetId, Transaction, TransactionInput, TransactionOutput, Value,
+	NATIVE_ASSET,
+};
+
+/// Assembles a [`Transaction`] and signs its inputs over the same payload the pallet verifies.
+///
+/// Signatures cover every input and output, so they have to be made once the transaction is
+/// complete: changing it after `sign_with` invalidates them. They are only valid on the chain
+/// whose genesis hash the builder is made for.
+#[derive(Clone, Debug)]
+pub struct TxBuilder {
+	tx: Transaction,
+	genesis_hash: H256,
+}
+
+impl TxBuilder {
+	pub fn new(genesis_hash: H256) -> Self {
+		Self { tx: Transaction::default(), genesis_hash }
+	}
+
+	/// Spend the output stored under `outpoint`.
+	pub fn add_input(&mut self, outpoint: H256) -> &mut Self {
+		self.tx.inputs.push(TransactionInput { outpoint, sigscript: H512::zero(), witness: Vec::new() });
+		self
+	}
+
+	/// Pay `value` of the native asset to `pubkey`.
+	pub fn add_output(&mut self, pubkey: H256, value: Value) -> &mut Self {
+		self.add_asset_output(pubkey, NATIVE_ASSET, value)
+	}
+
+	/// Pay `value` of `asset_id` to `pubkey`.
+	pub fn add_asset_output(&mut self, pubkey: H256, asset_id: AssetId, value: Value) -> &mut Self {
+		self.push_output(TransactionOutput { value, pubkey, asset_id, ..Default::default() })
+	}
+
+	/// Add `output` as is, e.g. to pass on a unique output.
+	pub fn push_output(&mut self, output: TransactionOutput) -> &mut Self {
+		self.tx.outputs.push(output);
+		self
+	}
+
+	/// Send the leftover value to `pubkey` instead of paying it all as fee.
+	pub fn change_to(&mut self, pubkey: H256) -> &mut Self {
+		self.tx.change_to = Some(pubkey);
+		self
+	}
+
+	/// Make the transaction invalid after block `block`.
+	pub fn valid_until(&mut self, block: u64) -> &mut Self {
+		self.tx.valid_until = Some(block);
+		self
+	}
+
+	/// Destroy `value` of the native asset, paying it to nobody.
+	pub fn burn(&mut self, value: Value) -> &mut Self {
+		self.tx.burn = Some(value);
+		self
+	}
+
+	/// Sign the input at `index` with `pair`, which must own the output it spends.
+	pub fn sign_input(&mut self, index: usize, pair: &sr25519::Pair) -> &mut Self {
+		let signature = pair.sign(&signing_payload(&self.tx, &self.genesis_hash));
+		self.tx.inputs[index].sigscript = H512::from(signature.0);
+		self
+	}
+
+	/// Sign every input with `pair` and return the signed transaction.
+	pub fn sign_with(&mut self, pair: &sr25519::Pair) -> Transaction {
+		let signature = H512::from(pair.sign(&signing_payload(&self.tx, &self.genesis_hash)).0);
+
+		for input in self.tx.inputs.iter_mut() {
+			input.sigscript = signature;
+		}
+
+		self.build()
+	}
+
+	/// Sign once with `pair` for every input spending an output of its key, instead of once per
+	/// input, and return the signed transaction. Inputs of other keys still need `sign_input`.
+	pub fn aggregate_with(&mut self, pair: &sr25519::Pair) -> Transaction {
+		let signature = H512::from(pair.sign(&signing_payload(&self.tx, &self.genesis_hash)).0);
+		self.tx.aggregate_sig = Some((H256::from(pair.public().0), signature));
+
+		self.build()
+	}
+
+	/// The transaction as assembled so far.
+	pub fn build(&self) -> Transaction {
+		self.tx.clone()
+	}
+
+	/// The fee the transaction pays given the native values of the outputs its inputs spend, in
+	/// input order. `None` if the native outputs exceed the inputs.
+	pub fn fee(&self, input_values: &[Value]) -> Option<Value> {
+		let total_input = input_values
+			.iter()
+			.try_fold(0, |total: Value, value| total.checked_add(*value))?;
+		let total_output = self.tx.outputs
+			.iter()
+			.filter(|output| output.asset_id == NATIVE_ASSET)
+			.try_fold(0, |total: Value, output| total.checked_add(output.value))?;
+
+		total_input.checked_sub(total_output)
+	}
+}
@@ -0,0 +1,137 @@
+//! Coin selection for wallets building transactions.
+//!
+//! Given the UTXOs a wallet controls, picks the ones to spend for a target value. Coins of equal
+//! value are ordered by a seeded hash of their outpoint, so a selection only depends on the set of
+//! available coins and the seed, not on the order the wallet happens to list them in.
+
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+
+use crate::Value;
+
+/// Upper bound on the number of nodes the branch and bound search visits.
+const MAX_BNB_TRIES: usize = 100_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+	/// Spend the largest coins first, keeping the number of inputs low.
+	LargestFirst,
+	/// Spend the smallest coins first, consolidating dust.
+	SmallestFirst,
+	/// Search for a set of coins adding up to exactly the target so no change output is needed,
+	/// falling back to largest-first when there is none.
+	BranchAndBound,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Selection {
+	/// The outpoints to spend.
+	pub outpoints: Vec<H256>,
+	/// The total value of the selected coins.
+	pub total: Value,
+	/// What is left over after paying the target.
+	pub change: Value,
+}
+
+/// Select coins out of `available` `(outpoint, value)` pairs to cover `target`. Returns `None` if
+/// the coins don't add up to the target.
+pub fn select_coins(available: &[(H256, Value)], target: Value, strategy: Strategy) -> Option<Selection> {
+	select_coins_seeded(available, target, strategy, 0)
+}
+
+/// Like [`select_coins`], with an explicit seed for ordering coins of equal value.
+pub fn select_coins_seeded(
+	available: &[(H256, Value)],
+	target: Value,
+	strategy: Strategy,
+	seed: u64,
+) -> Option<Selection> {
+	let tie_break = |outpoint: &H256| BlakeTwo256::hash_of(&(seed, outpoint));
+
+	// largest first, ties broken by the seeded hash
+	let mut coins = available.to_vec();
+	coins.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| tie_break(&a.0).cmp(&tie_break(&b.0))));
+
+	match strategy {
+		Strategy::LargestFirst => accumulate(coins.iter(), target),
+		Strategy::SmallestFirst => accumulate(coins.iter().rev(), target),
+		Strategy::BranchAndBound => branch_and_bound(&coins, target)
+			.or_else(|| accumulate(coins.iter(), target)),
+	}
+}
+
+fn accumulate<'a>(coins: impl Iterator<Item = &'a (H256, Value)>, target: Value) -> Option<Selection> {
+	let mut outpoints = Vec::new();
+	let mut total: Value = 0;
+
+	for (outpoint, value) in coins {
+		if total >= target {
+			break;
+		}
+
+		total = total.checked_add(*value)?;
+		outpoints.push(*outpoint);
+	}
+
+	if total < target {
+		return None;
+	}
+
+	Some(Selection { outpoints, total, change: total - target })
+}
+
+// depth first search over include/exclude decisions on coins sorted largest first
+fn branch_and_bound(coins: &[(H256, Value)], target: Value) -> Option<Selection> {
+	// remaining[i] is the value of all coins from i onwards, to prune branches that can't reach
+	// the target anymore
+	let mut remaining = vec![0; coins.len() + 1];
+	for i in (0..coins.len()).rev() {
+		remaining[i] = remaining[i + 1].saturating_add(coins[i].1);
+	}
+
+	let mut selected = Vec::new();
+	let mut tries = 0;
+
+	if search(coins, &remaining, target, 0, 0, &mut selected, &mut tries) {
+		Some(Selection {
+			outpoints: selected.iter().map(|&i| coins[i].0).collect(),
+			total: target,
+			change: 0,
+		})
+	} else {
+		None
+	}
+}
+
+fn search(
+	coins: &[(H256, Value)],
+	remaining: &[Value],
+	target: Value,
+	index: usize,
+	total: Value,
+	selected: &mut Vec<usize>,
+	tries: &mut usize,
+) -> bool {
+	if total == target {
+		return true;
+	}
+
+	*tries += 1;
+	if index == coins.len()
+		|| *tries > MAX_BNB_TRIES
+		|| total.saturating_add(remaining[index]) < target
+	{
+		return false;
+	}
+
+	let with = total.saturating_add(coins[index].1);
+	if with <= target {
+		selected.push(index);
+		if search(coins, remaining, target, index + 1, with, selected, tries) {
+			return true;
+		}
+		selected.pop();
+	}
+
+	search(coins, remaining, target, index + 1, total, selected, tries)
+}
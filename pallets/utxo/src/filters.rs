@@ -0,0 +1,190 @@
+//! Compact filters of the outputs a block created and the outputs it spent, for light clients to
+//! tell from a header alone whether the block may concern the keys and outputs they watch.
+//!
+//! A filter is a Golomb-coded set as in BIP158: every item is hashed, keyed by the parent hash of
+//! the block, into `0 .. n * M` for `n` items, and the sorted hashes are stored as Golomb-Rice
+//! coded deltas of `P` bits of remainder. An item of the block always matches, an item besides
+//! them matches with a probability of about `1 / M`. The items are the pubkeys paid by the
+//! outputs created in the block and the outpoints of the outputs it spent.
+
+use codec::{Decode, Encode};
+use sp_core::H256;
+use sp_std::vec::Vec;
+
+#[cfg(feature = "std")]
+use sp_runtime::{generic::Digest, traits::Header as HeaderT};
+
+#[cfg(feature = "std")]
+use crate::BLOCK_FILTER_ID;
+
+/// The bits of remainder of every coded delta.
+pub const P: u8 = 19;
+
+/// The inverse of the false positive rate, the one BIP158 picked for `P`.
+pub const M: u64 = 784_931;
+
+/// A filter of the items of a block, see the module docs.
+#[derive(PartialEq, Eq, Clone, Default, Encode, Decode, Debug)]
+pub struct BlockFilter {
+	/// The number of distinct items.
+	pub n: u32,
+	/// The coded deltas of their sorted hashes.
+	pub data: Vec<u8>,
+}
+
+impl BlockFilter {
+	/// The filter of `items`, keyed by `key`, the parent hash of the block they are from.
+	pub fn build(key: &[u8], items: &[H256]) -> Self {
+		let mut distinct = items.to_vec();
+		distinct.sort();
+		distinct.dedup();
+
+		let range = distinct.len() as u64 * M;
+		let mut hashes: Vec<_> = distinct
+			.iter()
+			.map(|item| hash_to_range(key, item, range))
+			.collect();
+		// equal hashes of distinct items code as a zero delta, and match alike
+		hashes.sort_unstable();
+
+		let mut writer = BitWriter::default();
+		let mut last = 0;
+		for hash in hashes {
+			let delta = hash - last;
+			for _ in 0 .. delta >> P {
+				writer.write_bit(true);
+			}
+			writer.write_bit(false);
+			writer.write_bits(delta, P);
+			last = hash;
+		}
+
+		Self { n: distinct.len() as u32, data: writer.bytes }
+	}
+
+	/// Whether `item` may be among the items the filter was built from with `key`.
+	pub fn matches(&self, key: &[u8], item: &H256) -> bool {
+		self.matches_any(key, &[*item])
+	}
+
+	/// Whether any of `items` may be among the items the filter was built from with `key`.
+	pub fn matches_any(&self, key: &[u8], items: &[H256]) -> bool {
+		let range = self.n as u64 * M;
+		let mut targets: Vec<_> = items
+			.iter()
+			.map(|item| hash_to_range(key, item, range))
+			.collect();
+		targets.sort_unstable();
+
+		let mut targets = targets.into_iter().peekable();
+		let mut reader = BitReader { bytes: &self.data, position: 0 };
+		let mut value = 0;
+		for _ in 0 .. self.n {
+			value += match reader.read_delta() {
+				Some(delta) => delta,
+				// a truncated filter can't rule anything out
+				None => return true,
+			};
+
+			while let Some(target) = targets.peek() {
+				if *target == value {
+					return true;
+				}
+				if *target > value {
+					break;
+				}
+				targets.next();
+			}
+			if targets.peek().is_none() {
+				return false;
+			}
+		}
+
+		false
+	}
+}
+
+// `item` hashed with `key` and mapped evenly onto `0 .. range`
+fn hash_to_range(key: &[u8], item: &H256, range: u64) -> u64 {
+	let hash = u64::from_le_bytes(sp_io::hashing::twox_64(&(key, item).encode()));
+
+	((hash as u128 * range as u128) >> 64) as u64
+}
+
+// bits appended most significant first
+#[derive(Default)]
+struct BitWriter {
+	bytes: Vec<u8>,
+	// the bits of the last byte in use, 0 for a full one
+	used: u8,
+}
+
+impl BitWriter {
+	fn write_bit(&mut self, bit: bool) {
+		if self.used == 0 {
+			self.bytes.push(0);
+		}
+		if bit {
+			if let Some(last) = self.bytes.last_mut() {
+				*last |= 0x80 >> self.used;
+			}
+		}
+		self.used = (self.used + 1) % 8;
+	}
+
+	// the `bits` low bits of `value`
+	fn write_bits(&mut self, value: u64, bits: u8) {
+		for bit in (0 .. bits).rev() {
+			self.write_bit((value >> bit) & 1 == 1);
+		}
+	}
+}
+
+struct BitReader<'a> {
+	bytes: &'a [u8],
+	position: usize,
+}
+
+impl<'a> BitReader<'a> {
+	fn read_bit(&mut self) -> Option<bool> {
+		let byte = self.bytes.get(self.position / 8)?;
+		let bit = byte & (0x80 >> (self.position % 8)) != 0;
+		self.position += 1;
+
+		Some(bit)
+	}
+
+	fn read_bits(&mut self, bits: u8) -> Option<u64> {
+		(0 .. bits).try_fold(0, |value, _| Some(value << 1 | self.read_bit()? as u64))
+	}
+
+	// a Golomb-Rice coded delta, `None` once the bytes run out
+	fn read_delta(&mut self) -> Option<u64> {
+		let mut quotient = 0u64;
+		while self.read_bit()? {
+			quotient += 1;
+		}
+
+		Some(quotient << P | self.read_bits(P)?)
+	}
+}
+
+/// The filter logged to `digest`, if there is one.
+#[cfg(feature = "std")]
+pub fn block_filter<Hash>(digest: &Digest<Hash>) -> Option<BlockFilter> {
+	digest.logs.iter().find_map(|log| {
+		let (id, filter) = <([u8; 4], BlockFilter)>::decode(&mut log.as_other()?).ok()?;
+
+		Some(filter).filter(|_| id == BLOCK_FILTER_ID)
+	})
+}
+
+/// Whether the block of `header` may have paid or spent any of `watched`, pubkeys and outpoints
+/// alike. A header without a filter may concern anything.
+#[cfg(feature = "std")]
+pub fn header_may_concern<Header: HeaderT>(header: &Header, watched: &[H256]) -> bool {
+	match block_filter(header.digest()) {
+		Some(filter) => filter.matches_any(header.parent_hash().as_ref(), watched),
+		None => true,
+	}
+}
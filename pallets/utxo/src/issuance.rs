@@ -0,0 +1,132 @@
+//! Block subsidies: the new value paid to block authors on top of the fees of a block.
+
+use frame_support::traits::Get;
+use sp_runtime::traits::{AtLeast32BitUnsigned, One, SaturatedConversion, Saturating};
+use sp_std::marker::PhantomData;
+
+use crate::Value;
+
+/// Bitcoin's initial subsidy, the default of [`HalvingIssuance`].
+pub const INITIAL_ISSUANCE: Value = 50;
+
+/// Bitcoin's halving interval in blocks, the default of [`HalvingIssuance`].
+pub const HALVING_EVERY_BLOCKS: u32 = 210_000;
+
+frame_support::parameter_types! {
+	pub const BitcoinInitialIssuance: Value = INITIAL_ISSUANCE;
+	pub const BitcoinHalvingInterval: u32 = HALVING_EVERY_BLOCKS;
+}
+
+/// Computes the subsidy of a block.
+pub trait Issuance<BlockNumber, Balance> {
+	/// The new value created in block `block`.
+	fn issuance(block: BlockNumber) -> Balance;
+
+	/// The first block after `block` with a different subsidy, if the schedule knows it.
+	fn next_change(_block: BlockNumber) -> Option<BlockNumber> {
+		None
+	}
+}
+
+/// No subsidy, block authors only earn fees.
+impl<BlockNumber, Balance: Default> Issuance<BlockNumber, Balance> for () {
+	fn issuance(_block: BlockNumber) -> Balance {
+		Balance::default()
+	}
+}
+
+/// A subsidy starting at `Initial` and halved every `Interval` blocks until it reaches zero. The
+/// defaults follow Bitcoin's schedule.
+pub struct HalvingIssuance<Initial = BitcoinInitialIssuance, Interval = BitcoinHalvingInterval>(
+	PhantomData<(Initial, Interval)>,
+);
+
+/// Bitcoin's schedule: `INITIAL_ISSUANCE`, halved every `HALVING_EVERY_BLOCKS` blocks.
+pub type BitcoinIssuance = HalvingIssuance<BitcoinInitialIssuance, BitcoinHalvingInterval>;
+
+impl<Initial: Get<Value>, Interval: Get<u32>> HalvingIssuance<Initial, Interval> {
+	fn interval() -> u64 {
+		Interval::get().max(1) as u64
+	}
+}
+
+impl<BlockNumber, Initial, Interval> Issuance<BlockNumber, Value>
+	for HalvingIssuance<Initial, Interval>
+where
+	BlockNumber: AtLeast32BitUnsigned,
+	Initial: Get<Value>,
+	Interval: Get<u32>,
+{
+	fn issuance(block: BlockNumber) -> Value {
+		let halvings = block.saturated_into::<u64>() / Self::interval();
+
+		Initial::get().checked_shr(halvings.saturated_into()).unwrap_or(0)
+	}
+
+	fn next_change(block: BlockNumber) -> Option<BlockNumber> {
+		// nothing is left to halve
+		if Self::issuance(block.clone()) == 0 {
+			return None;
+		}
+
+		let interval = Self::interval();
+		let halvings = block.saturated_into::<u64>() / interval;
+		let next = halvings.saturating_add(1).saturating_mul(interval);
+
+		Some(next.saturated_into())
+	}
+}
+
+/// A subsidy shrinking by `DecayPerBlock` every block, starting at `Start` and never dropping
+/// below `Floor`.
+pub struct LinearDecayIssuance<Start, DecayPerBlock, Floor>(
+	PhantomData<(Start, DecayPerBlock, Floor)>,
+);
+
+impl<BlockNumber, Start, DecayPerBlock, Floor> Issuance<BlockNumber, Value>
+	for LinearDecayIssuance<Start, DecayPerBlock, Floor>
+where
+	BlockNumber: AtLeast32BitUnsigned,
+	Start: Get<Value>,
+	DecayPerBlock: Get<Value>,
+	Floor: Get<Value>,
+{
+	fn issuance(block: BlockNumber) -> Value {
+		let decayed = DecayPerBlock::get().saturating_mul(block.saturated_into::<Value>());
+
+		Start::get().saturating_sub(decayed).max(Floor::get())
+	}
+
+	fn next_change(block: BlockNumber) -> Option<BlockNumber> {
+		if DecayPerBlock::get() == 0 || Self::issuance(block.clone()) <= Floor::get() {
+			return None;
+		}
+
+		Some(block.saturating_add(BlockNumber::one()))
+	}
+}
+
+/// Monero's schedule: the halvings of `Halvings`, but never less than `Tail`, so authors keep
+/// being paid once the fees alone would not be enough.
+pub struct TailEmissionIssuance<Tail, Halvings = HalvingIssuance>(PhantomData<(Tail, Halvings)>);
+
+impl<BlockNumber, Tail, Halvings> Issuance<BlockNumber, Value>
+	for TailEmissionIssuance<Tail, Halvings>
+where
+	BlockNumber: Clone,
+	Tail: Get<Value>,
+	Halvings: Issuance<BlockNumber, Value>,
+{
+	fn issuance(block: BlockNumber) -> Value {
+		Halvings::issuance(block).max(Tail::get())
+	}
+
+	fn next_change(block: BlockNumber) -> Option<BlockNumber> {
+		// once at the tail the subsidy stays there
+		if Halvings::issuance(block.clone()) <= Tail::get() {
+			return None;
+		}
+
+		Halvings::next_change(block)
+	}
+}
@@ -1,30 +1,198 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod bloom;
+pub mod filters;
+pub mod issuance;
+pub mod merkle;
+pub mod migrations;
+pub mod offchain;
+pub mod provider;
+pub mod signature;
+
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod coin_select;
+#[cfg(feature = "std")]
+pub mod partial;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod trie_proof;
+pub mod weights;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+#[cfg(test)]
+mod mock;
+
+/// The target of the pallet's log messages.
+pub const LOG_TARGET: &str = "runtime::utxo";
+
+// Log under `LOG_TARGET`. Messages only reach a logger in native execution or once the runtime
+// logger is set up, and are filtered by level before their arguments are formatted
+macro_rules! log {
+	($level:tt, $pattern:expr $(, $values:expr)* $(,)?) => {
+		frame_support::debug::$level!(target: crate::LOG_TARGET, $pattern $(, $values)*)
+	};
+}
+
+#[cfg(test)]
+mod tests;
+
 use codec::{Decode, Encode};
 use frame_support::{
 	decl_event, decl_error, decl_module, decl_storage, ensure,
-	dispatch::{DispatchResult, Vec},
-	traits::{FindAuthor},
+	dispatch::{DispatchErrorWithPostInfo, DispatchResult, DispatchResultWithPostInfo, Vec},
+	storage::{with_transaction, StoragePrefixedMap},
+	traits::{FindAuthor, Get},
+	weights::{DispatchClass, Weight},
 };
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 use sp_core::{
-	crypto::Public as _,
 	H256,
 	H512,
-	sr25519::{Public, Signature},
 };
-use sp_consensus_aura::sr25519::AuthorityId as AuraId;
-use sp_std::collections::btree_map::BTreeMap;
+use sp_inherents::{InherentData, InherentIdentifier, MakeFatalError, ProvideInherent};
+use frame_system::{ensure_none, ensure_root, ensure_signed};
+use sp_std::{collections::btree_map::BTreeMap, vec};
+use issuance::Issuance;
+use signature::SignatureVerifier;
+pub use weights::WeightInfo;
+pub use provider::{OnChainUtxos, UtxoProvider};
+use bloom::{FilteredUtxos, OutpointFilter};
 use sp_runtime::{
-	traits::{BlakeTwo256, Hash, SaturatedConversion},
-	transaction_validity::{TransactionLongevity, ValidTransaction},
+	FixedPointNumber, FixedU128, Perbill, TransactionOutcome,
+	helpers_128bit::multiply_by_rational,
+	generic::DigestItem,
+	traits::{
+		BlakeTwo256, Convert, Hash, One, SaturatedConversion, Saturating, ValidateUnsigned, Zero,
+	},
+	transaction_validity::{
+		InvalidTransaction, TransactionLongevity, TransactionSource, TransactionValidity,
+		ValidTransaction,
+	},
 };
 
 /// Configure the pallet by specifying the parameters and types on which it depends.
 pub trait Config: frame_system::Config {
 	/// Because this pallet emits events, it depends on the runtime's definition of an event.
 	type Event: From<Event> + Into<<Self as frame_system::Config>::Event>;
-	type FindAuthor: FindAuthor<AuraId>;
+	/// The key of a block author, as found by `FindAuthor` for whatever consensus engine the
+	/// runtime uses.
+	type AuthorityId: AsRef<[u8]> + Clone;
+	/// Finds the author of the current block in its pre-runtime digests.
+	type FindAuthor: FindAuthor<Self::AuthorityId>;
+
+	/// The portion of the collected fees that is paid to the treasury instead of the block author.
+	type TreasuryShare: Get<Perbill>;
+
+	/// The key owning the outputs paid to the treasury. It can't be the zero key, which nobody can
+	/// spend.
+	type TreasuryPubkey: Get<H256>;
+
+	/// Maps a signed origin's account to the pubkey owning its UTXOs.
+	type AccountPubkey: Convert<Self::AccountId, H256>;
+
+	/// The fee `transfer` leaves for the block author.
+	type MinimumFee: Get<Value>;
+
+	/// The maximum number of UTXOs `transfer` may spend to cover the requested value.
+	type MaxTxInputs: Get<u32>;
+
+	/// The signature scheme of the keys owning outputs.
+	type SignatureVerifier: SignatureVerifier;
+
+	/// The maximum length of the witness of a single input.
+	type MaxWitnessBytes: Get<u32>;
+
+	/// The maximum number of outputs extrinsics may create in a single block.
+	type MaxOutputsPerBlock: Get<u32>;
+
+	/// The fee paid by transactions that have their leftover value sent to `change_to`.
+	type AutoChangeFee: Get<Value>;
+
+	/// The subsidy paid to block authors on top of fees.
+	type Issuance: Issuance<Self::BlockNumber, Value>;
+
+	/// The cap on `TotalSupply`, subsidies and `mint` stop short of it.
+	type MaxSupply: Get<Value>;
+
+	/// Weight information for the extrinsics of this pallet.
+	type WeightInfo: WeightInfo;
+
+	/// The lowest the `MinFeeRate` floor goes, in fee per encoded byte of the transaction.
+	type BaseFeeRate: Get<Value>;
+
+	/// Blocks filled beyond this share of the maximum block weight raise `MinFeeRate`, emptier
+	/// ones lower it.
+	type TargetBlockFullness: Get<Perbill>;
+
+	/// Whether a failing `spend` records why it failed in a `TransactionRejected` event. Meant
+	/// for debugging, the events take up block space without changing any state.
+	type EmitRejections: Get<bool>;
+
+	/// The number of blocks reward outputs have to wait before they can be spent.
+	type CoinbaseMaturity: Get<Self::BlockNumber>;
+
+	/// Whether outputs have to wait for the block after the one creating them to be spent, so the
+	/// same coins can't change hands over and over within a block.
+	type SameBlockSpendForbidden: Get<bool>;
+
+	/// How long unsigned spends stay valid in the pool, in blocks.
+	type PoolLongevity: Get<TransactionLongevity>;
+
+	/// Whether fees are destroyed instead of paid to block authors, who then only earn the
+	/// subsidy.
+	type BurnFees: Get<bool>;
+
+	/// The most entries transactions may grow the UTXO set to. Transactions that don't grow it
+	/// are accepted even when it is full.
+	type MaxUtxoSetSize: Get<u32>;
+
+	/// Whether outputs may only pay keys in `ApprovedKeys`, for ledgers where only vetted keys
+	/// may hold value. Outputs of revoked keys can still be spent so their value can leave.
+	type RestrictedMode: Get<bool>;
+
+	/// The most transactions spending outputs of the same key within a `SpendWindow`.
+	type MaxSpendsPerWindow: Get<u32>;
+
+	/// The length of the windows `MaxSpendsPerWindow` counts spends in, in blocks.
+	type SpendWindow: Get<Self::BlockNumber>;
+
+	/// How many blocks `TxsInBlock` keeps the txids of a block for.
+	type TxIndexRetention: Get<Self::BlockNumber>;
+
+	/// The fee `estimate_fee` quotes per unit of weight, in units of value.
+	type WeightPrice: Get<FixedU128>;
+
+	/// The longest a transaction may be encoded, in bytes.
+	type MaxTxBytes: Get<u32>;
+
+	/// The share of the weight and of the fee rate floor of a spend that `consolidate` is charged,
+	/// so that merging dust pays for itself.
+	type ConsolidationFeeMultiplier: Get<Perbill>;
+
+	/// Whether outputs are removed at the end of the last block they can be spent in, their native
+	/// value going to the block reward. Otherwise expired outputs stay in the set unspendable.
+	type SweepExpired: Get<bool>;
+
+	/// How many of the last blocks `RecentFeeStats` keeps the fees and weight of, for
+	/// `estimate_fee_within`.
+	type FeeHistoryLength: Get<u32>;
+
+	/// The longest `memo` an output may carry, in bytes.
+	type MaxMemoBytes: Get<u32>;
+
+	/// The native value every output a transaction creates holds in `Deposits` until it is spent,
+	/// returned to the transaction spending it. Reward and minted outputs hold none.
+	type OutputDeposit: Get<Value>;
+
+	/// The key owning the rewards of a block author that neither claimed a payout key nor set a
+	/// payout preference. `AuthorityKeyOwner` has the authority key own them itself.
+	type AuthorityToOwner: Convert<Self::AuthorityId, H256>;
 }
 
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -35,10 +203,202 @@ pub struct TransactionInput {
 
 	// proof that the tx owner is authorised to spent the referred UTXO
 	pub sigscript: H512,
+
+	// additional data unlocking the referred UTXO, bounded by `MaxWitnessBytes`
+	pub witness: Vec<u8>,
 }
 
 pub type Value = u128;
 
+/// Identifies the token an output holds.
+pub type AssetId = u32;
+
+/// The chain's own token, the only one fees and rewards are paid in.
+pub const NATIVE_ASSET: AssetId = 0;
+
+/// Identifies a non-fungible output across the transactions passing it on.
+pub type UniqueId = H256;
+
+/// The outpoints a transaction spends, the `(outpoint, output)` pairs it creates and its fee.
+pub type DryRunResult = (Vec<H256>, Vec<(H256, TransactionOutput)>, Value);
+
+/// What validating a transaction derived, so applying it doesn't derive it again.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ValidatedTransaction {
+	/// The fee the transaction leaves for the block author.
+	pub reward: Value,
+	/// The keys the outputs are stored under, in output order.
+	pub output_keys: Vec<H256>,
+	/// The key and value of the change output, if the transaction has one.
+	pub change: Option<(H256, Value)>,
+}
+
+/// Why `dry_run` rejected a transaction.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Debug)]
+pub struct DryRunError {
+	/// The index of the failing input, if the failure is about a single input.
+	pub input_index: Option<u32>,
+	/// The reason validation failed.
+	pub reason: Vec<u8>,
+}
+
+/// The reward the block the call is made at pays its author so far, for validator dashboards.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Default, Clone, Encode, Decode, Debug)]
+pub struct RewardInfo {
+	/// The fees collected in the block so far.
+	pub pending_fees: Value,
+	/// The subsidy the block issues on top of them, before the `MaxSupply` cap.
+	pub next_subsidy: Value,
+	/// The outpoint of the last reward paid to a block author, `None` before the first one.
+	pub last_reward_outpoint: Option<H256>,
+}
+
+/// Whether the outputs of a transaction are in the UTXO set, see `Module::is_applied`.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Copy, Encode, Decode, Debug)]
+pub enum AppliedStatus {
+	/// None of its outputs are unspent: it was never applied, or all of them were spent since.
+	NotApplied,
+	/// It was applied and all of its outputs are still unspent.
+	FullyUnspent,
+	/// It was applied and some of its outputs were spent since.
+	PartiallySpent,
+}
+
+/// Block level aggregates, reported at the end of every block.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Default, Clone, Encode, Decode, Debug)]
+pub struct BlockSummary {
+	/// Transactions applied through `spend` and `transfer`.
+	pub txs: u32,
+	/// The fees those transactions paid.
+	pub total_fees: Value,
+	/// Outputs created in the block, the reward outputs included.
+	pub utxos_created: u32,
+	/// Outputs spent in the block.
+	pub utxos_spent: u32,
+}
+
+/// The layouts the storage of the pallet went through, see `migrations`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Encode, Decode, Debug)]
+pub enum Releases {
+	/// Before `StorageVersion` was introduced, outputs only carry `value` and `pubkey`.
+	V0,
+	/// Outputs carry `asset_id` and `unique`, `OwnedUtxos` and `TotalSupply` track the UTXO set.
+	V1,
+	/// Outputs carry `refund_to`.
+	V2,
+	/// `UtxoCount` tracks the size of the UTXO set.
+	V3,
+	/// `UtxoStore` keys are hashed with `blake2_128_concat` instead of `identity`.
+	V4,
+	/// Outputs carry `covenant`.
+	V5,
+	/// Outputs carry `expiry`.
+	V6,
+	/// Outputs carry `memo`.
+	V7,
+	/// Outputs carry `unlocks_at`.
+	V8,
+	/// Outputs carry `vesting`.
+	V9,
+	/// Outputs carry `fallback`.
+	V10,
+	/// Outputs carry `cosigner`.
+	V11,
+	/// `UtxoCreatedAt`, `CoinbaseUtxos` and `FrozenUtxos` keys are hashed with `blake2_128_concat`.
+	V12,
+}
+
+impl Default for Releases {
+	fn default() -> Self {
+		Releases::V0
+	}
+}
+
+/// The raw storage key of the `UtxoStore` entry following `key`, if there is one.
+pub(crate) fn next_utxo_key(key: &[u8]) -> Option<Vec<u8>> {
+	let prefix = UtxoStore::final_prefix();
+	sp_io::storage::next_key(key).filter(|next| next.starts_with(&prefix))
+}
+
+/// The raw storage key of the `UtxoStore` entry of `outpoint`, the key its trie proof covers.
+pub fn utxo_storage_key(outpoint: &H256) -> Vec<u8> {
+	UtxoStore::hashed_key_for(outpoint)
+}
+
+/// Identifies the inherent through which the author of a block names the key its reward is paid
+/// to.
+pub const INHERENT_IDENTIFIER: InherentIdentifier = *b"utxopay0";
+
+/// The key the author of a block wants its reward paid to, so that funds don't pile up on its hot
+/// session key. Carried by the inherent in the SCALE encoding.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Debug)]
+pub struct PayoutClaim {
+	/// The key the reward is paid to.
+	pub payout: H256,
+	/// The signature of the author's session key over `payout_payload`.
+	pub signature: H512,
+}
+
+/// Authority keys own the rewards of their blocks themselves: their raw public key if it is 32
+/// bytes long, the hash of the authority id otherwise.
+pub struct AuthorityKeyOwner;
+
+impl<AuthorityId: AsRef<[u8]>> Convert<AuthorityId, H256> for AuthorityKeyOwner {
+	fn convert(author: AuthorityId) -> H256 {
+		let raw = author.as_ref();
+		match raw.len() {
+			32 => H256::from_slice(raw),
+			_ => BlakeTwo256::hash(raw),
+		}
+	}
+}
+
+/// The message the session key of the author of block `block` signs to have its reward paid to
+/// `payout`. The block number keeps a claim from being replayed in another block.
+pub fn payout_payload(payout: &H256, block: u64) -> Vec<u8> {
+	(b"payout", payout, block).encode()
+}
+
+/// The message an authority key signs with `set_payout_key` to have its rewards paid to `payout`
+/// from then on. `nonce` is the authority's `PayoutNonce`, so that the signature of an older
+/// preference can't be replayed.
+pub fn payout_preference_payload(payout: &H256, nonce: u64) -> Vec<u8> {
+	(b"payout preference", payout, nonce).encode()
+}
+
+/// The message the owner of the output under `outpoint` signs for `prove_ownership`, against
+/// `block_hash`, the parent of the block including the proof. Tied to a block so proofs can't be
+/// replayed later on, after the output changed hands.
+pub fn ownership_payload<Hash: Encode>(outpoint: &H256, block_hash: &Hash) -> Vec<u8> {
+	(b"ownership", outpoint, block_hash).encode()
+}
+
+/// The message the owner of the output under `outpoint` signs for `reserve`, to keep it for the
+/// channel close paying the outputs with `covenant_hash` `closing` until block `until`, on the
+/// chain with genesis hash `genesis_hash`.
+pub fn reservation_payload<Hash: Encode>(
+	outpoint: &H256,
+	until: u64,
+	closing: &H256,
+	genesis_hash: &Hash,
+) -> Vec<u8> {
+	(b"reservation", outpoint, until, closing, genesis_hash).encode()
+}
+
+/// Identifies the digest item carrying the UTXO set commitment. The item is a
+/// `DigestItem::Other` holding the SCALE encoded `(UTXO_COMMITMENT_ID, UtxoCommitment)` pair.
+pub const UTXO_COMMITMENT_ID: [u8; 4] = *b"utxo";
+
+/// Identifies the digest item carrying the filter of the block for light clients, see
+/// [`filters`]. The item is a `DigestItem::Other` holding the SCALE encoded
+/// `(BLOCK_FILTER_ID, BlockFilter)` pair.
+pub const BLOCK_FILTER_ID: [u8; 4] = *b"ufil";
+
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[derive(PartialEq, Eq, PartialOrd, Ord, Default, Clone, Encode, Decode, Hash, Debug)]
 pub struct TransactionOutput {
@@ -47,6 +407,98 @@ pub struct TransactionOutput {
 
 	// the key of the onwer of the transaction output
 	pub pubkey: H256,
+
+	// the token the value is denominated in, native when left out of a chain spec
+	#[cfg_attr(feature = "std", serde(default))]
+	pub asset_id: AssetId,
+
+	// set for non-fungible outputs, which every spending transaction has to pass on whole
+	#[cfg_attr(feature = "std", serde(default))]
+	pub unique: Option<UniqueId>,
+
+	// where the value goes if the owner rejects the payment with `reject_payment`
+	#[cfg_attr(feature = "std", serde(default))]
+	pub refund_to: Option<H256>,
+
+	// the `covenant_hash` of the outputs every transaction spending this one has to create
+	#[cfg_attr(feature = "std", serde(default))]
+	pub covenant: Option<H256>,
+
+	// the last block the output can be spent in, it expires after that
+	#[cfg_attr(feature = "std", serde(default))]
+	pub expiry: Option<u64>,
+
+	// a note for the owner, bounded by `MaxMemoBytes`, that wallets encrypt to its key. Stored
+	// and signed with the output but never looked at by validation
+	#[cfg_attr(feature = "std", serde(default))]
+	pub memo: Vec<u8>,
+
+	// the first block the output can be spent in, it is locked before that
+	#[cfg_attr(feature = "std", serde(default))]
+	pub unlocks_at: Option<u64>,
+
+	// the block range over which the value unlocks linearly, see `Vesting`
+	#[cfg_attr(feature = "std", serde(default))]
+	pub vesting: Option<Vesting>,
+
+	// a second key that may spend the output once the owner has been inactive for long enough
+	#[cfg_attr(feature = "std", serde(default))]
+	pub fallback: Option<Fallback>,
+
+	// a second key that has to sign every spend along with the owner, a 2-of-2 for escrows. Both
+	// signatures go in the witness of the input, see `COSIGNED_WITNESS_BYTES`
+	#[cfg_attr(feature = "std", serde(default))]
+	pub cosigner: Option<H256>,
+}
+
+/// The length of the witness of an input spending an output with a `cosigner`: the signatures of
+/// the owner and of the cosigner over the signing payload, one after the other in either order.
+pub const COSIGNED_WITNESS_BYTES: usize = 128;
+
+/// A key that can spend an output in place of its owner from block `after` on, so that value
+/// isn't lost with a key nobody uses anymore. The owner can still spend it after that block.
+///
+/// Like every other field of an output it is committed to by the outpoint, so it can't be
+/// swapped out for another key without the output becoming another one.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Encode, Decode, Hash, Debug)]
+pub struct Fallback {
+	pub pubkey: H256,
+	pub after: u64,
+}
+
+/// A linear vesting schedule: the share of the value of an output spendable at block `b` is
+/// `(b - start) / (end - start)`, clamped to between nothing and everything.
+///
+/// A transaction spending a vesting output has to leave the unvested value with the owner, in
+/// outputs of the same asset vesting until the same `end`. They start vesting at the block of
+/// the spend, so what is left keeps unlocking at the pace of the original.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Encode, Decode, Hash, Debug)]
+pub struct Vesting {
+	pub start: u64,
+	pub end: u64,
+}
+
+impl Vesting {
+	/// The part of `value` vested at block `now`, rounded down.
+	pub fn vested(&self, value: Value, now: u64) -> Value {
+		if now <= self.start {
+			return 0;
+		}
+		if now >= self.end {
+			return value;
+		}
+
+		let (elapsed, duration) = ((now - self.start) as Value, (self.end - self.start) as Value);
+		// never more than `value`, it can't overflow
+		multiply_by_rational(value, elapsed, duration).unwrap_or(value)
+	}
+
+	/// The schedule the unvested value of a spend at block `now` moves on to.
+	pub fn remainder(&self, now: u64) -> Vesting {
+		Vesting { start: self.start.max(now), end: self.end }
+	}
 }
 
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -54,28 +506,352 @@ pub struct TransactionOutput {
 pub struct Transaction {
 	pub inputs: Vec<TransactionInput>,
 	pub outputs: Vec<TransactionOutput>,
+
+	// receives whatever native value the outputs leave over beyond `AutoChangeFee`, instead of
+	// it all going to the block author
+	#[cfg_attr(feature = "std", serde(default))]
+	pub change_to: Option<H256>,
+
+	// the last block the transaction may be applied in, so payments can expire
+	#[cfg_attr(feature = "std", serde(default))]
+	pub valid_until: Option<u64>,
+
+	// native value the transaction destroys on purpose, taken out of the supply instead of being
+	// paid to a key. Outputs can't pay the zero key, this is how value is burned
+	#[cfg_attr(feature = "std", serde(default))]
+	pub burn: Option<Value>,
+
+	// a key and its signature over the signing payload, standing in for the sigscripts of all
+	// inputs spending outputs of that key but for cosigned ones
+	#[cfg_attr(feature = "std", serde(default))]
+	pub aggregate_sig: Option<(H256, H512)>,
+}
+
+/// The encoded transaction with all sigscripts zeroed, all witnesses emptied and no aggregate
+/// signature. Input signatures cover it behind the domain of `signing_payload`, the txid and
+/// output keys hash it as is.
+pub fn get_simple_tx(tx: &Transaction) -> Vec<u8> {
+	sighash_bytes(tx)
+}
+
+/// Tags the messages input signatures are made over, ahead of the genesis hash of the chain.
+pub const SIGHASH_TAG: &[u8] = b"utxo-spend-v1";
+
+/// The payload input signatures of `tx` are made over on the chain with genesis hash
+/// `genesis_hash`: `SIGHASH_TAG` and the genesis hash ahead of `get_simple_tx`. The domain keeps
+/// a signature from being replayed on another chain running this pallet, from a testnet sharing
+/// genesis outputs with a mainnet for instance.
+pub fn signing_payload<Hash: Encode>(tx: &Transaction, genesis_hash: &Hash) -> Vec<u8> {
+	let mut payload = (SIGHASH_TAG, genesis_hash).encode();
+	payload.extend(sighash_bytes(tx));
+
+	payload
+}
+
+/// The bytes every input signature covers, the encoding of `tx` with all sigscripts zeroed, all
+/// witnesses emptied and no aggregate signature. Encoded field by field, without copying the
+/// transaction.
+pub fn sighash_bytes(tx: &Transaction) -> Vec<u8> {
+	let mut bytes = Vec::with_capacity(tx.size_hint());
+
+	codec::Compact(tx.inputs.len() as u32).encode_to(&mut bytes);
+	for input in tx.inputs.iter() {
+		input.outpoint.encode_to(&mut bytes);
+		H512::zero().encode_to(&mut bytes);
+		// the length of an empty witness
+		codec::Compact(0u32).encode_to(&mut bytes);
+	}
+
+	tx.outputs.encode_to(&mut bytes);
+	tx.change_to.encode_to(&mut bytes);
+	tx.valid_until.encode_to(&mut bytes);
+	tx.burn.encode_to(&mut bytes);
+	None::<(H256, H512)>.encode_to(&mut bytes);
+
+	bytes
+}
+
+/// The canonical id of `tx`, the hash of `get_simple_tx`: the transaction without signatures
+/// and witnesses, which don't change it, and without the domain `signing_payload` adds, so it is
+/// the same on every chain.
+pub fn txid(tx: &Transaction) -> H256 {
+	BlakeTwo256::hash(&get_simple_tx(tx))
+}
+
+/// The total length of the witnesses of `tx`.
+pub fn witness_len(tx: &Transaction) -> usize {
+	tx.inputs.iter().fold(0, |len, input| len.saturating_add(input.witness.len()))
+}
+
+/// The weight `W` declares for a `spend` of `tx`: a part per input, per output and per witness
+/// byte, one growing with the encoded length of the whole transaction, and the second signature
+/// of every input whose witness holds two.
+pub fn spend_weight<W: WeightInfo>(tx: &Transaction) -> Weight {
+	let cosigned = tx.inputs
+		.iter()
+		.filter(|input| input.witness.len() == COSIGNED_WITNESS_BYTES)
+		.count();
+
+	W::spend(tx.inputs.len() as u32, tx.outputs.len() as u32, witness_len(tx) as u32)
+		.saturating_add(W::spend_bytes(tx.encoded_size() as u32))
+		.saturating_add(W::cosigned(cosigned as u32))
+}
+
+/// The encoded size of the smallest transaction with `inputs` inputs and `outputs` outputs:
+/// none of them carries a witness or sets an optional field. Computed from the counts alone, so
+/// any of them can be asked for without building the transaction.
+pub fn minimal_tx_size(inputs: u32, outputs: u32) -> u64 {
+	let len = |n: u32| <codec::Compact<u32> as codec::CompactLen<u32>>::compact_len(&n) as u64;
+	// both lists empty
+	let rest = (Transaction::default().encoded_size() as u64).saturating_sub(2 * len(0));
+	let input = TransactionInput::default().encoded_size() as u64;
+	let output = TransactionOutput::default().encoded_size() as u64;
+
+	rest.saturating_add(len(inputs))
+		.saturating_add(len(outputs))
+		.saturating_add(input.saturating_mul(inputs as u64))
+		.saturating_add(output.saturating_mul(outputs as u64))
+}
+
+/// The `spend_weight` of the smallest transaction with `inputs` inputs and `outputs` outputs.
+pub fn minimal_spend_weight<W: WeightInfo>(inputs: u32, outputs: u32) -> Weight {
+	W::spend(inputs, outputs, 0)
+		.saturating_add(W::spend_bytes(minimal_tx_size(inputs, outputs).saturated_into()))
+}
+
+// the storage `insert_utxo` updates for one output: read the commitment, the count and whether
+// the key is taken, write those two and the output with its six index entries
+const INSERT_UTXO_READS: Weight = 3;
+const INSERT_UTXO_WRITES: Weight = 9;
+// and what `remove_utxo` updates: the output, the commitment, the count and six index entries
+const REMOVE_UTXO_READS: Weight = 3;
+const REMOVE_UTXO_WRITES: Weight = 9;
+
+// the weight `W` gives one signature check, half of the two a cosigned input may take
+fn signature_weight<W: WeightInfo>() -> Weight {
+	W::cosigned(1) / 2
+}
+
+/// The outpoint of the output the genesis `treasury_premine` pays to the treasury.
+pub fn treasury_premine_outpoint() -> H256 {
+	BlakeTwo256::hash_of(b"treasury premine")
+}
+
+/// An outpoint more than one of `inputs` spend, if there is one. Only the outpoints are
+/// compared, whatever the signatures and witnesses of the inputs.
+pub fn duplicate_outpoint(inputs: &[TransactionInput]) -> Option<H256> {
+	// nothing to sort in the common case
+	match inputs {
+		[] | [_] => return None,
+		[first, second] => return Some(first.outpoint).filter(|_| first.outpoint == second.outpoint),
+		_ => {},
+	}
+
+	let mut outpoints: Vec<&H256> = inputs.iter().map(|input| &input.outpoint).collect();
+	outpoints.sort_unstable();
+
+	outpoints.windows(2).find(|pair| pair[0] == pair[1]).map(|pair| *pair[0])
+}
+
+/// The commitment an output locked to a covenant carries: the hash of exactly the outputs a
+/// transaction spending it has to create, in order.
+pub fn covenant_hash(outputs: &[TransactionOutput]) -> H256 {
+	BlakeTwo256::hash_of(&outputs)
+}
+
+/// The contribution of a single unspent output to the `UtxoCommitment`, and its leaf in the
+/// merkle tree over the UTXO set.
+pub fn commitment_leaf(outpoint: &H256, utxo: &TransactionOutput) -> H256 {
+	BlakeTwo256::hash_of(&(outpoint, utxo))
 }
 
 // The pallet's runtime storage items.
 // https://substrate.dev/docs/en/knowledgebase/runtime/storage
 decl_storage! {
 	trait Store for Module<T: Config> as UtxoModule {
-		// seed data from genesis
-		UtxoStore build(|config: &GenesisConfig| {
-			config.genesis_utxos
-				.iter()
-				.cloned()
-				.map(|u| (BlakeTwo256::hash_of(&u), u))
-				.collect::<Vec<_>>()
-		}): map hasher(identity) H256 => Option<TransactionOutput>;
+		// the set of unspent outputs keyed by outpoint; seeded from genesis. Outpoints aren't
+		// always hashes of the transaction creating them, so they are hashed again to keep the
+		// trie balanced
+		UtxoStore: map hasher(blake2_128_concat) H256 => Option<TransactionOutput>;
+
+		// index of the outpoints owned by each pubkey, kept in sync with UtxoStore
+		pub OwnedUtxos: double_map hasher(blake2_128_concat) H256, hasher(blake2_128_concat) H256 => ();
+
+		// the block each unspent output was created in
+		pub UtxoCreatedAt get(fn utxo_created_at):
+			map hasher(blake2_128_concat) H256 => Option<T::BlockNumber>;
+
+		// the unspent reward outputs, which only become spendable after `CoinbaseMaturity` blocks
+		pub CoinbaseUtxos: map hasher(blake2_128_concat) H256 => ();
+
+		// the number of entries in UtxoStore
+		pub UtxoCount get(fn utxo_count): u32;
+
+		// rolling commitment to the utxo set: the XOR of the leaf hashes of all unspent outputs
+		pub UtxoCommitment get(fn utxo_commitment): H256;
+
+		// outpoints created and (outpoint, owner) pairs spent in the current block, reset at the
+		// start of every block
+		pub CreatedThisBlock get(fn created_in_current_block): Vec<H256>;
+		pub SpentThisBlock: Vec<(H256, H256)>;
+
+		// the pubkeys paid by the outputs created in the current block, for its filter. Reset at
+		// the start of every block
+		pub PaidThisBlock: Vec<H256>;
+
+		// transactions applied and the fees they paid in the current block, reset at the start of
+		// every block
+		pub TxsThisBlock: u32;
+		pub FeesThisBlock: Value;
+
+		// transactions spending outputs of each key in the current `SpendWindow`, reset at the
+		// start of every window
+		pub SpendCounts get(fn spend_count): map hasher(blake2_128_concat) H256 => u32;
+		// the number of keys in `SpendCounts`, for the weight of clearing it
+		pub SpendCountEntries: u32;
+
+		// the txids of the transactions applied in each of the last `TxIndexRetention` blocks, in
+		// order. Every transaction creates an output, so `MaxOutputsPerBlock` bounds each entry
+		pub TxsInBlock get(fn transactions_at):
+			map hasher(twox_64_concat) T::BlockNumber => Vec<H256>;
+
+		// the outpoints of the outputs whose `expiry` is each block, swept at its end while
+		// `SweepExpired` is set. Entries of outputs spent in the meantime are skipped
+		ExpiringAt: map hasher(twox_64_concat) u64 => Vec<H256>;
 
 		// the total reward that will be distributed to the miner when processing each block
 		pub RewardTotal get(fn reward_total): Value;
+
+		// the outpoint of the last output paying a block author its reward
+		pub LastRewardOutpoint get(fn last_reward_outpoint): Option<H256>;
+
+		// the native value of all outputs, which only grows through subsidies and `mint`
+		pub TotalSupply get(fn total_supply): Value;
+
+		// the payout key claimed by the author of the current block, taken when it is finalized
+		BlockPayout: Option<PayoutClaim>;
+
+		// the key the rewards of each authority key go to when its blocks carry no payout claim
+		pub PayoutPreference get(fn payout_preference):
+			map hasher(blake2_128_concat) H256 => Option<H256>;
+
+		// the nonce the next payout preference of each authority key has to sign
+		pub PayoutNonce get(fn payout_nonce): map hasher(blake2_128_concat) H256 => u64;
+
+		// the `OutputDeposit` each unspent output holds, at the rate of the block it was created in.
+		// Counted in `TotalSupply` but in no output
+		pub Deposits get(fn deposit_of): map hasher(blake2_128_concat) H256 => Value;
+
+		// the fees destroyed so far while `BurnFees` is set
+		pub FeesBurned get(fn fees_burned): Value;
+
+		// the value transactions destroyed so far through their `burn`
+		pub ValueBurned get(fn value_burned): Value;
+
+		// number of `mint` calls so far, keeps the keys of identical mints apart
+		MintNonce: u64;
+
+		// the key allowed to issue each non-native asset
+		pub AssetIssuer get(fn asset_issuer): map hasher(twox_64_concat) AssetId => Option<H256>;
+
+		// outpoints governance froze pending a decision, with the block they were frozen at
+		pub FrozenUtxos get(fn frozen_at):
+			map hasher(blake2_128_concat) H256 => Option<T::BlockNumber>;
+
+		// outpoints reserved for a channel close, with the last block of the reservation and the
+		// `covenant_hash` of the outputs the close pays. Lapsed entries stay until the output is
+		// spent or reserved again
+		pub Reservations get(fn reservation_of):
+			map hasher(blake2_128_concat) H256 => Option<(T::BlockNumber, H256)>;
+
+		// the keys outputs may pay while `RestrictedMode` is set
+		pub ApprovedKeys: map hasher(blake2_128_concat) H256 => ();
+
+		// the outpoint currently holding each unique output
+		pub UniqueOutpoint get(fn unique_outpoint): map hasher(blake2_128_concat) UniqueId => Option<H256>;
+
+		// the fees paid in and the weight used by each of the last `FeeHistoryLength` blocks,
+		// oldest first
+		pub RecentFeeStats get(fn recent_fee_stats): Vec<(Value, Weight)>;
+
+		// the fee per byte unsigned spends have to pay to enter the pool, follows block fullness
+		pub MinFeeRate get(fn min_fee_rate) build(|_| T::BaseFeeRate::get()): Value;
+
+		// the layout the storage is in, new chains start out at the latest one
+		pub StorageVersion get(fn storage_version) build(|_| Releases::V12): Releases;
 	}
 
 	add_extra_genesis {
 		// create a config property that will be pre-populated from the genesis file
 		config(genesis_utxos): Vec<TransactionOutput>;
+		// when set, the native genesis utxos must add up to exactly this value
+		config(expected_total_supply): Option<Value>;
+		// the initial issuer of each non-native asset
+		config(asset_issuers): Vec<(AssetId, H256)>;
+		// utxos stored under the given outpoints, as carried over from another chain
+		config(keyed_utxos): Vec<(H256, TransactionOutput)>;
+		// the keys outputs may pay from the start while `RestrictedMode` is set
+		config(approved_keys): Vec<H256>;
+		// the reward the author of the first block collects on top of its subsidy
+		config(initial_reward_total): Value;
+		// the value of an output paid to `TreasuryPubkey` under `treasury_premine_outpoint`
+		config(treasury_premine): Option<Value>;
+
+		build(|config: &GenesisConfig| {
+			assert!(config.treasury_premine != Some(0), "the treasury premine must be nonzero");
+
+			let total = config.genesis_utxos
+				.iter()
+				.chain(config.keyed_utxos.iter().map(|(_, utxo)| utxo))
+				.filter(|utxo| utxo.asset_id == NATIVE_ASSET)
+				.map(|utxo| utxo.value)
+				.chain(config.treasury_premine)
+				.chain(Some(config.initial_reward_total))
+				.try_fold(0, |total: Value, value| total.checked_add(value))
+				.expect("total genesis supply overflows");
+			assert!(total <= T::MaxSupply::get(), "the genesis supply exceeds `MaxSupply`");
+
+			if let Some(expected) = config.expected_total_supply {
+				assert!(
+					total == expected,
+					"genesis utxos add up to {} but the declared total supply is {}",
+					total,
+					expected,
+				);
+			}
+
+			let keyed = config.keyed_utxos.iter().map(|(_, utxo)| utxo);
+			for utxo in config.genesis_utxos.iter().chain(keyed) {
+				assert!(!utxo.pubkey.is_zero(), "genesis utxo paying the zero key");
+			}
+			for utxo in config.genesis_utxos.iter().cloned() {
+				// genesis outpoints are keyed by the output alone, a duplicate would replace it
+				let outpoint = BlakeTwo256::hash_of(&utxo);
+				assert!(!<UtxoStore>::contains_key(outpoint), "duplicate genesis utxo {:?}", outpoint);
+				<Module<T>>::insert_utxo(outpoint, utxo);
+			}
+			for (outpoint, utxo) in config.keyed_utxos.iter().cloned() {
+				assert!(!<UtxoStore>::contains_key(outpoint), "duplicate genesis utxo {:?}", outpoint);
+				<Module<T>>::insert_utxo(outpoint, utxo);
+			}
+			if let Some(value) = config.treasury_premine {
+				assert!(!T::TreasuryPubkey::get().is_zero(), "treasury premine paying the zero key");
+				let outpoint = treasury_premine_outpoint();
+				assert!(!<UtxoStore>::contains_key(outpoint), "duplicate genesis utxo {:?}", outpoint);
+				let utxo = TransactionOutput { value, pubkey: T::TreasuryPubkey::get(), ..Default::default() };
+				<Module<T>>::insert_utxo(outpoint, utxo);
+			}
+			RewardTotal::put(config.initial_reward_total);
+			TotalSupply::put(total);
+
+			for (asset_id, issuer) in config.asset_issuers.iter() {
+				assert!(*asset_id != NATIVE_ASSET, "the native asset can't have an issuer");
+				AssetIssuer::insert(asset_id, issuer);
+			}
+			for key in config.approved_keys.iter() {
+				ApprovedKeys::insert(key, ());
+			}
+		});
 	}
 }
 
@@ -84,16 +860,190 @@ decl_storage! {
 // https://substrate.dev/docs/en/knowledgebase/runtime/events
 decl_event! {
 	pub enum Event {
-		TransactionSuccess(Transaction),
+		/// A transaction was applied [txid, fee, transaction]
+		TransactionSuccess(H256, Value, Transaction),
+		/// A transaction created an output carrying a memo [outpoint, memo]
+		OutputMemo(H256, Vec<u8>),
+		/// A transaction passed to `spend` was rejected for the given reason
+		TransactionRejected(Vec<u8>),
+		/// The leftover value of a transaction was returned to its `change_to` [outpoint, value]
+		ChangeReturned(H256, Value),
 		RewardsIssued(Value, H256),
+		TreasuryRewarded(Value, H256),
 		RewardsWasted,
+		/// Aggregates of the block being finalized
+		BlockSummary(BlockSummary),
+		/// The fees the transactions of the block being finalized paid, as its reward is paid out
+		/// [total_fees, txs]
+		BlockFees(Value, u32),
+		/// Governance created outputs [outpoints, native_value]
+		Minted(Vec<H256>, Value),
+		/// The block subsidy was cut to stay within `MaxSupply` [requested, actual]
+		SubsidyClamped(Value, Value),
+		/// The fees and subsidy of the block don't fit in a value, the subsidy was cut to fit
+		/// [requested, actual]
+		RewardOverflowClamped(Value, Value),
+		/// The block subsidy differs from that of the previous block, every block of a decaying
+		/// schedule and once per halving of a halving one [old, new, block]
+		SubsidyChanged(Value, Value, u64),
+		/// Governance froze an outpoint [outpoint]
+		UtxoFrozen(H256),
+		/// Governance unfroze an outpoint [outpoint]
+		UtxoUnfrozen(H256),
+		/// Governance allowed outputs to pay a key [pubkey]
+		KeyApproved(H256),
+		/// Governance stopped outputs from paying a key [pubkey]
+		KeyRevoked(H256),
+		/// An issuer created an output holding an amount of an asset [asset_id, value, outpoint]
+		AssetIssued(AssetId, Value, H256),
+		/// The issuer of an asset changed [asset_id, issuer]
+		AssetIssuerSet(AssetId, H256),
+		/// A unique output was created [unique_id, outpoint]
+		UniqueMinted(UniqueId, H256),
+		/// A unique output was passed on [unique_id, old_outpoint, new_outpoint]
+		UniqueTransferred(UniqueId, H256, H256),
+		/// A unique output was destroyed [unique_id, outpoint]
+		UniqueBurned(UniqueId, H256),
+		/// The owner of an output sent its value back to its `refund_to` [outpoint, refund_outpoint]
+		PaymentRejected(H256, H256),
+		/// The payout claim of the block author isn't signed by its session key, the reward went
+		/// to the session key instead [payout]
+		PayoutClaimRejected(H256),
+		/// An authority key had its future rewards paid to another key [authority, payout]
+		PayoutPreferenceSet(H256, H256),
+		/// The owner of an output proved to control it, leaving it unspent [outpoint, pubkey]
+		OwnershipProven(H256, H256),
+		/// Outputs expired at the end of the block, their native value and deposits went to the
+		/// block reward [outputs, native_value]
+		OutputsExpired(u32, Value),
+		/// An output was reserved for a channel close until a block [outpoint, until, closing]
+		UtxoReserved(H256, u64, H256),
+		/// A transaction destroyed native value through its `burn` [txid, value]
+		ValueBurned(H256, Value),
 	}
 }
 
 // Errors inform users that something went wrong.
 decl_error! {
 	pub enum Error for Module<T: Config> {
-
+		/// The sender doesn't own enough value to cover the transfer and its fee
+		InsufficientFunds,
+		/// Covering the transfer would take more than `MaxTxInputs` inputs
+		TooManyInputs,
+		/// An input's witness is longer than `MaxWitnessBytes`
+		WitnessTooLarge,
+		/// The block would hold more than `MaxOutputsPerBlock` new outputs
+		TooManyOutputs,
+		/// The transaction would grow the UTXO set beyond `MaxUtxoSetSize` entries
+		UtxoSetFull,
+		/// Outputs must hold a nonzero value
+		ZeroValueOutput,
+		/// The total supply would exceed `MaxSupply`
+		ExceedsMaxSupply,
+		/// The asset has no issuer, or is the native asset
+		UnknownAsset,
+		/// The caller's key isn't the issuer of the asset
+		NotAssetIssuer,
+		/// Issuing nothing would create an empty output
+		ZeroIssuance,
+		/// A unique id already exists
+		UniqueExists,
+		/// An output carries a unique id none of the inputs pass on
+		UniqueCreated,
+		/// A unique id is passed on to more than one output
+		UniqueDuplicated,
+		/// A unique id of an input isn't passed on, it has to be burned explicitly
+		UniqueOmitted,
+		/// The outpoint doesn't hold a unique output
+		NotUnique,
+		/// The caller's key doesn't own the output
+		NotOwner,
+		/// There is no unspent output under the outpoint
+		UnknownOutpoint,
+		/// An input spends a frozen outpoint
+		UtxoFrozen,
+		/// The outpoint isn't frozen
+		NotFrozen,
+		/// An output pays or refunds to a key that isn't in `ApprovedKeys` while `RestrictedMode`
+		/// is set
+		RecipientNotApproved,
+		/// The key isn't in `ApprovedKeys`
+		NotApproved,
+		/// An input spends a reward output younger than `CoinbaseMaturity`
+		ImmatureCoinbase,
+		/// An input spends an output of the current block while `SameBlockSpendForbidden` is set
+		SameBlockSpend,
+		/// A key whose outputs the transaction spends already spent `MaxSpendsPerWindow` times in
+		/// the current `SpendWindow`
+		RateLimited,
+		/// An input is locked to a covenant the outputs of the transaction don't match
+		CovenantViolated,
+		/// The transaction is encoded in more than `MaxTxBytes` bytes
+		TransactionTooLarge,
+		/// An output carries a memo longer than `MaxMemoBytes`
+		MemoTooLarge,
+		/// The outputs of the transaction exist already, it was applied before
+		AlreadyApplied,
+		/// An input spends an output past its `expiry`, or an output expires before the current
+		/// block
+		UtxoExpired,
+		/// An input spends or a rejection refunds an output before its `unlocks_at` block, or a
+		/// rejection refunds an output that didn't fully vest yet
+		UtxoLocked,
+		/// A vesting schedule has no tranches
+		EmptySchedule,
+		/// A `split` into no outputs
+		EmptySplit,
+		/// The outputs of a `split` would hold no more than the minimum fee it takes to spend them
+		SplitIntoDust,
+		/// The aggregate signature of a transaction covers none of its inputs
+		UnusedAggregate,
+		/// An output vests over an empty block range
+		InvalidVesting,
+		/// The transaction doesn't leave the unvested value of an input vesting with its owner
+		VestingRemainderMissing,
+		/// The payout preference isn't signed by the authority key over its current nonce
+		InvalidPayoutSignature,
+		/// The ownership proof isn't signed by the owner of the output over `ownership_payload`
+		InvalidOwnershipProof,
+		/// A consolidation spends outputs of more than one key or pays another key
+		NotSelfConsolidation,
+		/// A consolidation doesn't have fewer outputs than inputs
+		ConsolidationNotShrinking,
+		/// An input spends an outpoint that isn't in the utxo set, or no longer is
+		MissingInput,
+		/// The current block is past the `valid_until` of the transaction
+		TransactionExpired,
+		/// The output has no `refund_to` to send its value back to
+		NotRefundable,
+		/// The key owns no output `sweep` could spend
+		NothingToSweep,
+		/// `sweep` needs exactly one signature per swept output
+		SignatureCountMismatch,
+		/// Adding up the values of outputs overflows
+		ValueOverflow,
+		/// The outputs of a transaction hold more of an asset than its inputs
+		OutputsExceedInputs,
+		/// An output holds an asset none of the inputs of the transaction hold
+		AssetImbalance,
+		/// The outputs of a transaction hold less of a non-native asset than its inputs, fees are
+		/// only paid in the native asset
+		NonNativeFee,
+		/// What a transaction leaves over doesn't cover the deposits of its outputs
+		DepositNotCovered,
+		/// The output is reserved for a channel close paying other outputs
+		UtxoReserved,
+		/// The output already is reserved for a channel close
+		AlreadyReserved,
+		/// A reservation has to last beyond the current block
+		ReservationLapsed,
+		/// The reservation isn't signed by the owner of the output over `reservation_payload`
+		InvalidReservationSignature,
+		/// An output, its refund or the change pays the zero key, which nobody can spend. Value is
+		/// burned through the `burn` of a transaction instead
+		UnspendableRecipient,
+		/// The output has a cosigner, only a spend both keys sign can move it
+		CosignerRequired,
 	}
 }
 
@@ -104,140 +1054,2105 @@ decl_module! {
 	pub struct Module<T: Config> for enum Call where origin: T::Origin {
 		fn deposit_event() = default;
 
-		#[weight = 10_000]
-		pub fn spend(_origin, tx: Transaction) -> DispatchResult {
-			// 1. check that the transaction is valid
-			let reward = Self::validate_transaction(&tx)?;
+		// clear the transient per-block storage. `RewardTotal` is left alone: it carries the fees
+		// of the previous block until `on_finalize` pays them out, or on to a later block when
+		// there was no author to pay
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			CreatedThisBlock::kill();
+			SpentThisBlock::kill();
+			PaidThisBlock::kill();
+			TxsThisBlock::kill();
+			FeesThisBlock::kill();
 
-			Self::update_storage(&tx, reward)?;
+			let old = T::Issuance::issuance(n.saturating_sub(One::one()));
+			let new = T::Issuance::issuance(n);
+			if new != old {
+				Self::deposit_event(Event::SubsidyChanged(old, new, n.saturated_into()));
+			}
 
-			// 3. emit success event
-			Self::deposit_event(Event::TransactionSuccess(tx));
+			// windows start at multiples of their length
+			let window_start = (n % T::SpendWindow::get().max(One::one())).is_zero();
+			// every key that spent in the window is a write of its own
+			let cleared = if window_start {
+				let entries = SpendCountEntries::take() as Weight;
+				SpendCounts::remove_all();
+				T::DbWeight::get().reads_writes(1, 1 + entries)
+			} else {
+				0
+			};
 
-			Ok(())
-		}
+			let retention = T::TxIndexRetention::get();
+			if n >= retention {
+				TxsInBlock::<T>::remove(n - retention);
+			}
 
-		// function executed at the end of each block
-		fn on_finalize() {
-			let digest = <frame_system::Module<T>>::digest();
-			let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+			// the outputs expiring in this block are swept in `on_finalize`, removing one takes
+			// up to 8 writes
+			let sweep = if T::SweepExpired::get() {
+				let block = n.saturated_into::<u64>();
+				let expiring = ExpiringAt::decode_len(block).unwrap_or(0) as Weight;
+				T::DbWeight::get().reads_writes(1 + expiring, 1 + 8 * expiring)
+			} else {
+				0
+			};
 
-			match T::FindAuthor::find_author(pre_runtime_digests) {
-				// Block author did not provide key to claim reward
-				None => Self::deposit_event(Event::RewardsWasted),
-				// Block author did provide key, so issue thir reward
-				Some(author) => Self::disperse_reward(&author),
-			}
+			T::DbWeight::get().writes(6).saturating_add(cleared).saturating_add(sweep)
 		}
-	}
-}
 
-// Add additional helper function that can be accessible in anywhere we import Config
-impl<T: Config> Module<T> {
-	pub fn get_simple_tx(tx: &Transaction) -> Vec<u8> {
-		let mut tx = tx.clone();
+		fn on_runtime_upgrade() -> Weight {
+			migrations::migrate::<T>()
+		}
 
-		for input in tx.inputs.iter_mut() {
-			input.sigscript = H512::zero();
+		fn integrity_test() {
+			assert!(!T::TreasuryPubkey::get().is_zero(), "the treasury can't be the zero key");
 		}
 
-		tx.encode()
-	}
+		#[weight = spend_weight::<T::WeightInfo>(tx)]
+		pub fn spend(_origin, tx: Transaction) -> DispatchResultWithPostInfo {
+			// a failing spend must not leave partial writes behind, but the rejection event is
+			// deposited outside of the rolled back changes so it survives
+			with_transaction(|| match Self::apply_spend(&tx) {
+				Ok(()) => TransactionOutcome::Commit(Ok(())),
+				Err(e) => TransactionOutcome::Rollback(Err(e)),
+			}).map_err(|e| {
+				if T::EmitRejections::get() {
+					let reason: &'static str = e.into();
+					Self::deposit_event(Event::TransactionRejected(reason.as_bytes().to_vec()));
+				}
 
-	/// 1. Inputs and Outputs are not empty
-	/// 2. Each Input exists and is used exactly once
-	/// 3. Each Output is defined exactly once and has nonzero value
-	/// 4. Total Output value must not exceed total Input value
-	/// 5. New Outputs do not collide with existing ones
-	/// 6. Replay attacks are not possible
-	/// 7. Provided Input signatures are valid
-	/// 	- The Input UTXO is indeed signed by the owner
-	///   - Transactions are tamperproof
-	pub fn validate_transaction(tx: &Transaction) -> Result<Value, &'static str> {
-		ensure!(!tx.inputs.is_empty(), "no inputs");
-		ensure!(!tx.outputs.is_empty(), "no outputs");
+				// inputs are looked up before any signature is verified, a spend failing there
+				// only pays for the lookups
+				let actual_weight = Self::spent_outputs(&OnChainUtxos, &tx).err().map(|_| {
+					T::WeightInfo::spend(0, tx.outputs.len() as u32, witness_len(&tx) as u32)
+						.saturating_add(T::DbWeight::get().reads(tx.inputs.len() as Weight))
+				});
 
-		// use btree map to dedupe same inputs
-		let input_set: BTreeMap<_, ()> = tx.inputs.iter().map(|input| (input, ())).collect();
-		ensure!(input_set.len() == tx.inputs.len(), "Each input must be used once");
-
-		let output_set: BTreeMap<_, ()> = tx.outputs.iter().map(|output| (output, ())).collect();
-		ensure!(output_set.len() == tx.outputs.len(), "Each output must be defined only once");
-
-		let simple_transaction = Self::get_simple_tx(&tx);
-		let mut total_input: Value = 0;
-		let mut total_output: Value = 0;
-
-		for input in tx.inputs.iter() {
-			if let Some(input_utxo) = UtxoStore::get(&input.outpoint) {
-				// check sigs
-				ensure!(
-					sp_io::crypto::sr25519_verify(
-						&Signature::from_raw(*input.sigscript.as_fixed_bytes()),
-						&simple_transaction,
-						&Public::from_h256(input_utxo.pubkey)
-					),
-					"Signature must be valid"
-				);
+				DispatchErrorWithPostInfo { post_info: actual_weight.into(), error: e }
+			})?;
 
-				total_input = total_input.checked_add(input_utxo.value).ok_or("input value overflow")?;
-			} else {
-				// TODO
-			}
+			Ok(().into())
 		}
 
-		let mut output_index: u64 = 0;
-		for output in tx.outputs.iter() {
-			ensure!(output.value > 0, "output valud must be nonzero");
-			let hash = BlakeTwo256::hash_of(&(&tx.encode(), output_index));
-			output_index = output_index.checked_add(1).ok_or("output index overflow")?;
-			ensure!(!UtxoStore::contains_key(hash), "output already exists");
+		/// Pay `value` to `dest` out of the UTXOs owned by the caller's key, sending any change
+		/// back to it. Inputs are authorized by the signed origin instead of per-input signatures.
+		/// Only outputs `sweep` would move are spent, up to `MaxTxInputs` of them.
+		#[weight = minimal_spend_weight::<T::WeightInfo>(T::MaxTxInputs::get(), 2)]
+		pub fn transfer(origin, dest: H256, value: Value) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let owner = T::AccountPubkey::convert(who);
 
-			total_output = total_output.checked_add(output.value).ok_or("output value overflow")?;
-		}
+			let tx = Self::build_transfer(&owner, dest, value)?;
+			let validated = Self::validate_authorized_transaction(&OnChainUtxos, &tx, Some(&owner))?;
 
-		ensure!(total_input >= total_output, "output value must not exceed the input value");
-		let reward = total_input.checked_sub(total_output).ok_or("output index overflow")?;
+			Self::update_storage(&tx, &validated)?;
+			Self::note_applied(tx, validated.reward);
 
-		Ok(reward)
-	}
+			Ok(())
+		}
 
-	fn update_storage(tx: &Transaction, reward: Value) -> DispatchResult {
-		let new_total = RewardTotal::get()
-			.checked_add(reward)
-			.ok_or("reward overflow")?;
+		/// Merge outputs of a single key into fewer outputs paying the same key, to clean up dust.
+		/// `tx` is checked like a `spend`, but is charged `ConsolidationFeeMultiplier` of its
+		/// weight and of the fee rate floor.
+		#[weight = T::ConsolidationFeeMultiplier::get() * spend_weight::<T::WeightInfo>(tx)]
+		pub fn consolidate(origin, tx: Transaction) -> DispatchResultWithPostInfo {
+			Self::check_consolidation(&tx)?;
 
-		RewardTotal::put(new_total);
+			Self::spend(origin, tx)
+		}
 
-		// 1. Remove all input utxos from the UtxoStore
-		for input in &tx.inputs {
-			UtxoStore::remove(input.outpoint);
+		/// Move all native value of `from` to a single output owned by `to`, less the minimum fee.
+		/// Frozen, unique, expired, locked, vesting, cosigned and immature reward outputs are left
+		/// alone.
+		/// `signatures` holds one signature per output swept over the `signing_payload` of
+		/// `build_sweep`, in the order of its inputs.
+		#[weight = T::WeightInfo::spend(signatures.len() as u32, 1, 0)]
+		pub fn sweep(origin, from: H256, to: H256, signatures: Vec<H512>) -> DispatchResultWithPostInfo {
+			let tx = Self::signed_sweep(&from, to, &signatures)?;
+
+			Self::spend(origin, tx)
 		}
 
-		// 2. Create a new utxo
-		let mut index: u64 = 0;
-		for output in &tx.outputs {
-			// Make sure the key is unique by using the entire tx and a unique index
-			let key = BlakeTwo256::hash_of(&(&tx.encode(), index));
-			index = index.checked_add(1).ok_or("output index overflow")?;
-			UtxoStore::insert(key, output);
+		/// Move the output under `outpoint` to `new_pubkey` as a plain output, less the minimum fee
+		/// if it holds the native asset. Cosigned outputs can't be rekeyed. `sigscript` is the
+		/// signature of the current owner over the `signing_payload` of `build_rekey`.
+		#[weight = T::WeightInfo::spend(1, 1, 0)]
+		pub fn rekey(origin, outpoint: H256, sigscript: H512, new_pubkey: H256) -> DispatchResultWithPostInfo {
+			let mut tx = Self::build_rekey(&outpoint, new_pubkey)?;
+			tx.inputs[0].sigscript = sigscript;
+
+			Self::spend(origin, tx)
 		}
-		Ok(())
-	}
 
-	fn disperse_reward(author: &AuraId) {
-		let reward = RewardTotal::take();
-		let utxo = TransactionOutput{
-			value: reward,
-			pubkey: H256::from_slice(author.as_slice()),
-		};
+		/// Spend `input` into one output for `recipient` per tranche of `schedule`, each locked
+		/// until its block. Whatever the tranches leave of the input is the fee. The input is
+		/// signed over the `signing_payload` of `build_vest`.
+		#[weight = T::WeightInfo::spend(1, schedule.len() as u32, input.witness.len() as u32)]
+		pub fn vest(
+			origin,
+			input: TransactionInput,
+			recipient: H256,
+			schedule: Vec<(T::BlockNumber, Value)>,
+		) -> DispatchResultWithPostInfo {
+			let tx = Self::signed_vest(input, recipient, &schedule)?;
 
-		let current_block = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
-		let hash = BlakeTwo256::hash_of(&(&utxo, current_block));
+			Self::spend(origin, tx)
+		}
 
-		// Store the Utxo
-		UtxoStore::insert(hash, utxo);
+		/// Spend the native output under `outpoint` into `n` outputs of equal value for
+		/// `recipient`, for wallets preparing parallel spends. `sigscript` is the signature of the
+		/// owner over the `signing_payload` of `build_split`.
+		#[weight = T::WeightInfo::spend(1, *n, 0)]
+		pub fn split(
+			origin,
+			outpoint: H256,
+			sigscript: H512,
+			n: u32,
+			recipient: H256,
+		) -> DispatchResultWithPostInfo {
+			let mut tx = Self::build_split(&outpoint, n, recipient)?;
+			tx.inputs[0].sigscript = sigscript;
+
+			Self::spend(origin, tx)
+		}
+
+		// the calls below have no benchmark, their weights count each storage read and write on
+		// their path, an event as a write, and the signature check of `WeightInfo` where they
+		// verify one
+
+		/// Create an output holding `amount` of `asset_id` for `owner`. Only the registered issuer
+		/// of the asset may call this.
+		#[weight = T::DbWeight::get().reads_writes(7 + INSERT_UTXO_READS, 1 + INSERT_UTXO_WRITES)]
+		pub fn issue_asset(origin, asset_id: AssetId, amount: Value, owner: H256) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let issuer = AssetIssuer::get(asset_id).ok_or(Error::<T>::UnknownAsset)?;
+			ensure!(T::AccountPubkey::convert(who) == issuer, Error::<T>::NotAssetIssuer);
+			ensure!(amount > 0, Error::<T>::ZeroIssuance);
+
+			let utxo = TransactionOutput { value: amount, pubkey: owner, asset_id, ..Default::default() };
+			Self::check_output(&utxo)?;
+			Self::ensure_room_for_minted(1)?;
+			let block = <frame_system::Module<T>>::block_number();
+			let index = <frame_system::Module<T>>::extrinsic_index();
+			let hash = BlakeTwo256::hash_of(&(b"issue", &utxo, block, index));
+			ensure!(!UtxoStore::contains_key(hash), "output already exists");
+
+			Self::insert_utxo(hash, utxo);
+			Self::deposit_event(Event::AssetIssued(asset_id, amount, hash));
+
+			Ok(())
+		}
+
+		/// Create a unique output `id` of `asset_id` for `owner`, holding a single unit of the
+		/// asset. Only the registered issuer of the asset may call this.
+		#[weight = T::DbWeight::get().reads_writes(5 + INSERT_UTXO_READS, 1 + INSERT_UTXO_WRITES)]
+		pub fn mint_unique(origin, asset_id: AssetId, id: UniqueId, owner: H256) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let issuer = AssetIssuer::get(asset_id).ok_or(Error::<T>::UnknownAsset)?;
+			ensure!(T::AccountPubkey::convert(who) == issuer, Error::<T>::NotAssetIssuer);
+			ensure!(!UniqueOutpoint::contains_key(id), Error::<T>::UniqueExists);
+
+			let utxo = TransactionOutput {
+				value: 1,
+				pubkey: owner,
+				asset_id,
+				unique: Some(id),
+				..Default::default()
+			};
+			Self::check_output(&utxo)?;
+			Self::ensure_room_for_minted(1)?;
+			let hash = BlakeTwo256::hash_of(&(b"unique", &utxo));
+
+			Self::insert_utxo(hash, utxo);
+			Self::deposit_event(Event::UniqueMinted(id, hash));
+
+			Ok(())
+		}
+
+		/// Destroy the unique output stored under `outpoint`, which the caller's key must own.
+		#[weight = T::DbWeight::get().reads_writes(6 + REMOVE_UTXO_READS, 3 + REMOVE_UTXO_WRITES)]
+		pub fn burn_unique(origin, outpoint: H256) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let utxo = UtxoStore::get(&outpoint).ok_or(Error::<T>::NotUnique)?;
+			let id = utxo.unique.ok_or(Error::<T>::NotUnique)?;
+			ensure!(T::AccountPubkey::convert(who) == utxo.pubkey, Error::<T>::NotOwner);
+			ensure!(!FrozenUtxos::<T>::contains_key(outpoint), Error::<T>::UtxoFrozen);
+			ensure!(Self::active_reservation(&outpoint).is_none(), Error::<T>::UtxoReserved);
+
+			Self::remove_utxo(&outpoint);
+			// nobody is left to return the deposit to
+			let deposit = Deposits::take(outpoint);
+			RewardTotal::mutate(|total| *total = total.saturating_add(deposit));
+			Self::deposit_event(Event::UniqueBurned(id, outpoint));
+
+			Ok(())
+		}
+
+		/// Send the value of the output under `outpoint`, which the caller's key must own, back to
+		/// the `refund_to` of the output.
+		#[weight = T::DbWeight::get().reads_writes(
+			9 + REMOVE_UTXO_READS + INSERT_UTXO_READS,
+			3 + REMOVE_UTXO_WRITES + INSERT_UTXO_WRITES,
+		)]
+		pub fn reject_payment(origin, outpoint: H256) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let utxo = UtxoStore::get(&outpoint).ok_or(Error::<T>::UnknownOutpoint)?;
+			let refund_to = utxo.refund_to.ok_or(Error::<T>::NotRefundable)?;
+			ensure!(T::AccountPubkey::convert(who) == utxo.pubkey, Error::<T>::NotOwner);
+			ensure!(!FrozenUtxos::<T>::contains_key(outpoint), Error::<T>::UtxoFrozen);
+			ensure!(Self::active_reservation(&outpoint).is_none(), Error::<T>::UtxoReserved);
+			// an expired output is left for the sweep, its refund would never be swept
+			ensure!(Self::is_unexpired(&utxo), Error::<T>::UtxoExpired);
+			// nor can a refund move value before the recipient could
+			ensure!(Self::is_unlocked(&utxo), Error::<T>::UtxoLocked);
+			let now = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+			ensure!(
+				utxo.vesting.map_or(true, |vesting| vesting.vested(utxo.value, now) == utxo.value),
+				Error::<T>::UtxoLocked
+			);
+			ensure!(Self::may_receive(&refund_to), Error::<T>::RecipientNotApproved);
+			// the owner alone can't undo an escrow
+			ensure!(utxo.cosigner.is_none(), Error::<T>::CosignerRequired);
+			Self::ensure_block_has_room(1)?;
+
+			// the conditions the sender put on the payment are for the recipient, the refund only
+			// keeps what the value is
+			let refund = TransactionOutput {
+				value: utxo.value,
+				pubkey: refund_to,
+				asset_id: utxo.asset_id,
+				unique: utxo.unique,
+				..Default::default()
+			};
+			let key = BlakeTwo256::hash_of(&(b"refund", outpoint));
+			ensure!(!UtxoStore::contains_key(key), "output already exists");
+
+			Self::remove_utxo(&outpoint);
+			Self::insert_utxo(key, refund);
+			// the refund holds the deposit of the rejected output on
+			let deposit = Deposits::take(outpoint);
+			if deposit > 0 {
+				Deposits::insert(key, deposit);
+			}
+			Self::deposit_event(Event::PaymentRejected(outpoint, key));
+
+			Ok(())
+		}
+
+		/// Create `outputs` out of thin air, for bridge top-ups and supply corrections decided by
+		/// governance. They pass the checks the outputs of a transaction do, but hold no deposit.
+		#[weight = T::DbWeight::get().reads_writes(
+			5 + (3 + INSERT_UTXO_READS).saturating_mul(outputs.len() as Weight),
+			3 + INSERT_UTXO_WRITES.saturating_mul(outputs.len() as Weight),
+		)]
+		pub fn mint(origin, outputs: Vec<TransactionOutput>) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(outputs.iter().all(|output| output.value > 0), Error::<T>::ZeroValueOutput);
+			// unique outputs have their own minting rules
+			ensure!(outputs.iter().all(|output| output.unique.is_none()), Error::<T>::UniqueCreated);
+			for output in outputs.iter() {
+				Self::check_output(output)?;
+			}
+			Self::ensure_room_for_minted(outputs.len())?;
+
+			let minted = Self::sum_values(outputs
+				.iter()
+				.filter(|output| output.asset_id == NATIVE_ASSET)
+				.map(|output| output.value))?;
+			let total_supply = TotalSupply::get().checked_add(minted).ok_or("total supply overflow")?;
+			ensure!(total_supply <= T::MaxSupply::get(), Error::<T>::ExceedsMaxSupply);
+
+			let block = <frame_system::Module<T>>::block_number();
+			let nonce = MintNonce::mutate(|nonce| {
+				*nonce += 1;
+				*nonce
+			});
+
+			let mut keys = Vec::with_capacity(outputs.len());
+			for (index, output) in outputs.iter().enumerate() {
+				let key = BlakeTwo256::hash_of(&(&outputs, block, nonce, index as u64));
+				ensure!(!UtxoStore::contains_key(key), "output already exists");
+
+				Self::insert_utxo(key, output.clone());
+				keys.push(key);
+			}
+
+			TotalSupply::put(total_supply);
+			Self::deposit_event(Event::Minted(keys, minted));
+
+			Ok(())
+		}
+
+		/// Keep the output under `outpoint` from being spent until it is unfrozen.
+		#[weight = T::DbWeight::get().reads_writes(2, 2)]
+		pub fn freeze(origin, outpoint: H256) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(UtxoStore::contains_key(outpoint), Error::<T>::UnknownOutpoint);
+
+			FrozenUtxos::<T>::insert(outpoint, <frame_system::Module<T>>::block_number());
+			Self::deposit_event(Event::UtxoFrozen(outpoint));
+
+			Ok(())
+		}
+
+		/// Allow the output under `outpoint` to be spent again.
+		#[weight = T::DbWeight::get().reads_writes(1, 2)]
+		pub fn unfreeze(origin, outpoint: H256) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(FrozenUtxos::<T>::contains_key(outpoint), Error::<T>::NotFrozen);
+
+			FrozenUtxos::<T>::remove(outpoint);
+			Self::deposit_event(Event::UtxoUnfrozen(outpoint));
+
+			Ok(())
+		}
+
+		/// Allow outputs to pay `pubkey` while `RestrictedMode` is set.
+		#[weight = T::DbWeight::get().writes(2)]
+		pub fn approve_key(origin, pubkey: H256) -> DispatchResult {
+			ensure_root(origin)?;
+
+			ApprovedKeys::insert(pubkey, ());
+			Self::deposit_event(Event::KeyApproved(pubkey));
+
+			Ok(())
+		}
+
+		/// Stop outputs from paying `pubkey` while `RestrictedMode` is set. The outputs it already
+		/// owns can still be spent.
+		#[weight = T::DbWeight::get().reads_writes(1, 2)]
+		pub fn revoke_key(origin, pubkey: H256) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(ApprovedKeys::contains_key(pubkey), Error::<T>::NotApproved);
+
+			ApprovedKeys::remove(pubkey);
+			Self::deposit_event(Event::KeyRevoked(pubkey));
+
+			Ok(())
+		}
+
+		/// Register `issuer` as the key allowed to issue `asset_id`.
+		#[weight = T::DbWeight::get().writes(2)]
+		pub fn set_asset_issuer(origin, asset_id: AssetId, issuer: H256) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(asset_id != NATIVE_ASSET, Error::<T>::UnknownAsset);
+
+			AssetIssuer::insert(asset_id, issuer);
+			Self::deposit_event(Event::AssetIssuerSet(asset_id, issuer));
+
+			Ok(())
+		}
+
+		/// Have the reward of the current block paid to `claim.payout` instead of the session key
+		/// of its author. Only included as an inherent, the claim is checked against the author
+		/// when the block is finalized.
+		#[weight = (T::DbWeight::get().reads_writes(1, 1), DispatchClass::Mandatory)]
+		pub fn set_payout(origin, claim: PayoutClaim) -> DispatchResult {
+			ensure_none(origin)?;
+			ensure!(!BlockPayout::exists(), "payout already set in this block");
+
+			BlockPayout::put(claim);
+
+			Ok(())
+		}
+
+		/// Have every later reward of `authority` paid to `payout`, unless a block claims another
+		/// key through `set_payout`. `signature` is that of `authority` over
+		/// `payout_preference_payload` with its current `PayoutNonce`.
+		#[weight = signature_weight::<T::WeightInfo>()
+			.saturating_add(T::DbWeight::get().reads_writes(1, 3))]
+		pub fn set_payout_key(
+			_origin,
+			authority: H256,
+			payout: H256,
+			signature: H512,
+		) -> DispatchResult {
+			Self::check_payout_preference(&authority, &payout, &signature)?;
+
+			PayoutNonce::mutate(authority, |nonce| *nonce = nonce.saturating_add(1));
+			PayoutPreference::insert(authority, payout);
+			Self::deposit_event(Event::PayoutPreferenceSet(authority, payout));
+
+			Ok(())
+		}
+
+		/// Prove control over the output under `outpoint` without spending it, for attestations.
+		/// `sigscript` is the signature of its owner over `ownership_payload` against the parent
+		/// of the block the proof goes into.
+		#[weight = signature_weight::<T::WeightInfo>()
+			.saturating_add(T::DbWeight::get().reads_writes(2, 1))]
+		pub fn prove_ownership(origin, outpoint: H256, sigscript: H512) -> DispatchResult {
+			ensure_signed(origin)?;
+			let utxo = UtxoStore::get(&outpoint).ok_or(Error::<T>::UnknownOutpoint)?;
+
+			let payload = ownership_payload(&outpoint, &<frame_system::Module<T>>::parent_hash());
+			ensure!(
+				T::SignatureVerifier::verify(&sigscript, &payload, &utxo.pubkey),
+				Error::<T>::InvalidOwnershipProof
+			);
+			Self::deposit_event(Event::OwnershipProven(outpoint, utxo.pubkey));
+
+			Ok(())
+		}
+
+		/// Reserve the output under `outpoint` for the close of a payment channel: up to block
+		/// `until` it is only spent by a transaction paying the outputs whose `covenant_hash` is
+		/// `closing`, normal spending resumes after it. `sigscript` is the signature of its owner
+		/// over `reservation_payload`.
+		#[weight = signature_weight::<T::WeightInfo>()
+			.saturating_add(T::DbWeight::get().reads_writes(4, 2))]
+		pub fn reserve(
+			origin,
+			outpoint: H256,
+			sigscript: H512,
+			until: T::BlockNumber,
+			closing: H256,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let utxo = UtxoStore::get(&outpoint).ok_or(Error::<T>::UnknownOutpoint)?;
+			// a lapsed reservation can't be replayed
+			let now = <frame_system::Module<T>>::block_number();
+			ensure!(until > now, Error::<T>::ReservationLapsed);
+			ensure!(Self::active_reservation(&outpoint).is_none(), Error::<T>::AlreadyReserved);
+
+			let until_block = until.saturated_into::<u64>();
+			let genesis_hash = Self::genesis_hash();
+			let payload = reservation_payload(&outpoint, until_block, &closing, &genesis_hash);
+			ensure!(
+				T::SignatureVerifier::verify(&sigscript, &payload, &utxo.pubkey),
+				Error::<T>::InvalidReservationSignature
+			);
+
+			Reservations::<T>::insert(outpoint, (until, closing));
+			Self::deposit_event(Event::UtxoReserved(outpoint, until_block, closing));
+
+			Ok(())
+		}
+
+		// function executed at the end of each block
+		fn on_finalize() {
+			if T::SweepExpired::get() {
+				Self::sweep_expired(<frame_system::Module<T>>::block_number().saturated_into());
+			}
+
+			Self::deposit_event(Event::BlockFees(FeesThisBlock::get(), TxsThisBlock::get()));
+
+			let digest = <frame_system::Module<T>>::digest();
+			let pre_runtime_digests = digest.logs.iter().filter_map(|d| d.as_pre_runtime());
+
+			match T::FindAuthor::find_author(pre_runtime_digests) {
+				// Block author did not provide key to claim reward
+				None => Self::deposit_event(Event::RewardsWasted),
+				// Block author did provide key, so issue thir reward
+				Some(author) => Self::disperse_reward(&Self::payout_key(&author)),
+			}
+
+			Self::update_min_fee_rate();
+			Self::record_fee_stats();
+
+			Self::deposit_event(Event::BlockSummary(BlockSummary {
+				txs: TxsThisBlock::get(),
+				total_fees: FeesThisBlock::get(),
+				utxos_created: CreatedThisBlock::decode_len().unwrap_or(0) as u32,
+				utxos_spent: SpentThisBlock::decode_len().unwrap_or(0) as u32,
+			}));
+
+			// commit to the utxo set as it stands at the end of this block
+			let commitment = (UTXO_COMMITMENT_ID, UtxoCommitment::get()).encode();
+			<frame_system::Module<T>>::deposit_log(DigestItem::Other(commitment));
+
+			// and the filter of what it paid and spent, keyed by its parent, the hash of this one
+			// isn't known yet
+			let items = PaidThisBlock::get()
+				.into_iter()
+				.chain(SpentThisBlock::get().into_iter().map(|(outpoint, _)| outpoint))
+				.collect::<Vec<_>>();
+			let parent = <frame_system::Module<T>>::parent_hash();
+			let filter = filters::BlockFilter::build(parent.as_ref(), &items);
+			let filter = (BLOCK_FILTER_ID, filter).encode();
+			<frame_system::Module<T>>::deposit_log(DigestItem::Other(filter));
+		}
+
+		fn offchain_worker(block: T::BlockNumber) {
+			Self::index_owned_utxos(block);
+		}
+	}
+}
+
+// Add additional helper function that can be accessible in anywhere we import Config
+impl<T: Config> Module<T> {
+	pub fn get_simple_tx(tx: &Transaction) -> Vec<u8> {
+		get_simple_tx(tx)
+	}
+
+	/// The hash of the genesis block, known from block 1 on.
+	pub fn genesis_hash() -> T::Hash {
+		<frame_system::Module<T>>::block_hash(T::BlockNumber::zero())
+	}
+
+	/// The payload the input signatures of `tx` are made over on this chain.
+	pub fn signing_payload(tx: &Transaction) -> Vec<u8> {
+		signing_payload(tx, &Self::genesis_hash())
+	}
+
+	/// 1. Inputs and Outputs are not empty
+	/// 2. Each Input exists and is used exactly once
+	/// 3. Each Output has nonzero value, equal Outputs are stored apart by their index
+	/// 4. Total Output value must not exceed total Input value, for every asset
+	/// 	- Only the native asset can pay a fee, every other asset is conserved exactly
+	/// 5. New Outputs do not collide with existing ones
+	/// 	- Every unique id of an Input is passed on to exactly one Output, of the same asset
+	/// 6. Replay attacks are not possible
+	/// 	- A replayed transaction finds the outputs of the original and fails with
+	/// 	  `AlreadyApplied`, or with `MissingInput` on the inputs the original spent once those
+	/// 	  outputs are spent too
+	/// 7. Provided Input signatures are valid
+	/// 	- The Input UTXO is indeed signed by the owner
+	///   - Transactions are tamperproof
+	/// 	- Checked once every Input passed its other checks, all together so that
+	/// 	  `SignatureVerifier::verify_all` can batch them
+	pub fn validate_transaction(tx: &Transaction) -> Result<ValidatedTransaction, &'static str> {
+		Self::validate_transaction_against(&OnChainUtxos, tx)
+	}
+
+	/// `validate_transaction`, turning away the outpoints `filter` surely doesn't hold before
+	/// reading them from storage. For nodes keeping a filter of the UTXO set in memory.
+	pub fn validate_transaction_filtered(
+		filter: &OutpointFilter,
+		tx: &Transaction,
+	) -> Result<ValidatedTransaction, &'static str> {
+		Self::validate_transaction_against(&FilteredUtxos::new(filter, OnChainUtxos), tx)
+	}
+
+	/// `validate_transaction` with the outputs spent and created by `tx` looked up in `utxos`
+	/// instead of `UtxoStore`. The rest of the state, frozen outputs and spend counts among
+	/// others, is still read from storage.
+	pub fn validate_transaction_against(
+		utxos: &impl UtxoProvider,
+		tx: &Transaction,
+	) -> Result<ValidatedTransaction, &'static str> {
+		Self::validate_authorized_transaction(utxos, tx, None)
+	}
+
+	// inputs owned by `authorized` are accepted without a signature, for spends on behalf of an
+	// already authenticated origin
+	fn validate_authorized_transaction(
+		utxos: &impl UtxoProvider,
+		tx: &Transaction,
+		authorized: Option<&H256>,
+	) -> Result<ValidatedTransaction, &'static str> {
+		match Self::check_transaction(utxos, tx, authorized) {
+			Ok(validated) => {
+				log!(
+					debug,
+					"transaction is valid, reward {} change {:?}",
+					validated.reward,
+					validated.change,
+				);
+				Ok(validated)
+			},
+			Err(reason) => {
+				log!(debug, "transaction rejected: {}", reason);
+				Err(reason)
+			},
+		}
+	}
+
+	fn check_transaction(
+		utxos: &impl UtxoProvider,
+		tx: &Transaction,
+		authorized: Option<&H256>,
+	) -> Result<ValidatedTransaction, &'static str> {
+		ensure!(!tx.inputs.is_empty(), "no inputs");
+		ensure!(!tx.outputs.is_empty(), "no outputs");
+		ensure!(tx.encoded_size() <= T::MaxTxBytes::get() as usize, Error::<T>::TransactionTooLarge);
+
+		// a resubmitted transaction finds its own outputs, as long as they are unspent
+		let output_keys = Self::output_keys(tx);
+		ensure!(
+			!output_keys.iter().any(|key| utxos.contains(key)),
+			Error::<T>::AlreadyApplied
+		);
+		ensure!(!Self::is_expired(tx), Error::<T>::TransactionExpired);
+
+		Self::check_no_duplicate_inputs(&tx.inputs)?;
+
+		// equal outputs are fine, their keys differ by output index
+
+		let spent = Self::spent_outputs(utxos, tx)?;
+
+		let payload = Self::signing_payload(&tx);
+		// the asset of every unique input, and whether an output passed it on yet
+		let mut uniques = BTreeMap::<UniqueId, (AssetId, bool)>::new();
+
+		for (input, input_utxo) in tx.inputs.iter().zip(spent.iter()) {
+			Self::check_input(input, input_utxo)?;
+
+			if let Some(id) = input_utxo.unique {
+				uniques.insert(id, (input_utxo.asset_id, false));
+			}
+		}
+
+		if let Some((pubkey, _)) = tx.aggregate_sig {
+			ensure!(
+				spent.iter().any(|utxo| utxo.cosigner.is_none() && utxo.pubkey == pubkey),
+				Error::<T>::UnusedAggregate
+			);
+		}
+
+		// the signatures are verified together, for verifiers that can batch them. Inputs a
+		// fallback key may sign as well can't tell which key to batch, they are checked apart
+		// along with the cosigned ones. An authorized origin never stands in for a cosigner, and
+		// the aggregate signature is verified once for all the inputs it covers
+		let (apart, owned): (Vec<_>, Vec<_>) = tx.inputs
+			.iter()
+			.zip(spent.iter())
+			.filter(|(_, input_utxo)| {
+				input_utxo.cosigner.is_some() || authorized != Some(&input_utxo.pubkey)
+			})
+			.filter(|(_, input_utxo)| !Self::is_aggregated(tx, input_utxo))
+			.partition(|(_, input_utxo)| {
+				input_utxo.cosigner.is_some() || Self::active_fallback(input_utxo).is_some()
+			});
+		let signatures = owned
+			.iter()
+			.map(|(input, input_utxo)| (input.sigscript, input_utxo.pubkey))
+			.chain(tx.aggregate_sig.map(|(pubkey, signature)| (signature, pubkey)))
+			.collect::<Vec<_>>();
+		ensure!(
+			T::SignatureVerifier::verify_all(&signatures, &payload)
+				&& apart.iter().all(|(input, input_utxo)| {
+					Self::is_signed(input, input_utxo, &payload)
+				}),
+			"Signature must be valid"
+		);
+
+		ensure!(
+			tx.change_to.map_or(true, |pubkey| !pubkey.is_zero()),
+			Error::<T>::UnspendableRecipient
+		);
+		for output in tx.outputs.iter() {
+			ensure!(output.value > 0, "output valud must be nonzero");
+			Self::check_output(output)?;
+
+			if let Some(id) = output.unique {
+				let (asset_id, passed_on) = uniques.get_mut(&id).ok_or(Error::<T>::UniqueCreated)?;
+				ensure!(*asset_id == output.asset_id, Error::<T>::UniqueCreated);
+				ensure!(!*passed_on, Error::<T>::UniqueDuplicated);
+				*passed_on = true;
+			}
+		}
+
+		ensure!(uniques.values().all(|(_, passed_on)| *passed_on), Error::<T>::UniqueOmitted);
+
+		// inputs locked to a covenant only go to the outputs they committed to
+		if spent.iter().any(|utxo| utxo.covenant.is_some()) {
+			let outputs = covenant_hash(&tx.outputs);
+			ensure!(
+				spent.iter().all(|utxo| utxo.covenant.map_or(true, |covenant| covenant == outputs)),
+				Error::<T>::CovenantViolated
+			);
+		}
+
+		// reserved inputs only go to the outputs of the channel close they are reserved for
+		let closings = tx.inputs
+			.iter()
+			.filter_map(|input| Self::active_reservation(&input.outpoint))
+			.collect::<Vec<_>>();
+		if !closings.is_empty() {
+			let outputs = covenant_hash(&tx.outputs);
+			ensure!(closings.iter().all(|closing| *closing == outputs), Error::<T>::UtxoReserved);
+		}
+
+		Self::check_vesting(&spent, &tx.outputs)?;
+
+		// a transaction counts against every key it spends outputs of
+		let owners: BTreeMap<_, ()> = spent.iter().map(|utxo| (utxo.pubkey, ())).collect();
+		ensure!(
+			owners.keys().all(|owner| SpendCounts::get(owner) < T::MaxSpendsPerWindow::get()),
+			Error::<T>::RateLimited
+		);
+
+		// the deposits of the spent outputs come back, every output created holds one, the change
+		// output as well
+		let refunded = Self::refunded_deposits(&tx.inputs);
+		let leftover = Self::check_asset_balances(&spent, &tx.outputs, refunded)?
+			.checked_sub(Self::held_deposits(tx.outputs.len()))
+			.ok_or(Error::<T>::DepositNotCovered)?
+			.checked_sub(tx.burn.unwrap_or(0))
+			.ok_or(Error::<T>::OutputsExceedInputs)?;
+
+		let fee = T::AutoChangeFee::get();
+		let deposit = T::OutputDeposit::get();
+		let change = tx.change_to
+			.filter(|_| leftover > fee.saturating_add(deposit))
+			.map(|_| leftover - fee - deposit);
+		let change = match tx.change_to.zip(change) {
+			Some((pubkey, value)) => {
+				let key = Self::change_key(tx);
+				ensure!(!utxos.contains(&key), "output already exists");
+				ensure!(Self::may_receive(&pubkey), Error::<T>::RecipientNotApproved);
+				Some((key, value))
+			},
+			None => None,
+		};
+
+		let reward = leftover - change.map_or(0, |(_, value)| value + deposit);
+		Ok(ValidatedTransaction { reward, output_keys, change })
+	}
+
+	// the checks every new output has to pass on its own, whether a transaction creates it or it
+	// is minted
+	fn check_output(output: &TransactionOutput) -> Result<(), Error<T>> {
+		ensure!(!output.pubkey.is_zero(), Error::<T>::UnspendableRecipient);
+		ensure!(
+			output.refund_to.map_or(true, |refund_to| !refund_to.is_zero()),
+			Error::<T>::UnspendableRecipient
+		);
+		ensure!(Self::is_unexpired(output), Error::<T>::UtxoExpired);
+		ensure!(output.memo.len() <= T::MaxMemoBytes::get() as usize, Error::<T>::MemoTooLarge);
+		ensure!(
+			output.vesting.map_or(true, |vesting| vesting.start < vesting.end),
+			Error::<T>::InvalidVesting
+		);
+		ensure!(Self::may_receive(&output.pubkey), Error::<T>::RecipientNotApproved);
+		// a rejection pays the refund key, it has to be one outputs may pay as well
+		ensure!(
+			output.refund_to.map_or(true, |refund_to| Self::may_receive(&refund_to)),
+			Error::<T>::RecipientNotApproved
+		);
+
+		Ok(())
+	}
+
+	// the room `created` outputs spending nothing take, in the block and in the UTXO set
+	fn ensure_room_for_minted(created: usize) -> DispatchResult {
+		Self::ensure_block_has_room(created)?;
+		Self::ensure_utxo_set_has_room(0, created)
+	}
+
+	// whether outputs may pay `pubkey`, only the approved keys may be paid in restricted mode
+	fn may_receive(pubkey: &H256) -> bool {
+		!T::RestrictedMode::get() || ApprovedKeys::contains_key(pubkey)
+	}
+
+	// the checks every input has to pass on its own, but for its signature
+	fn check_input(
+		input: &TransactionInput,
+		input_utxo: &TransactionOutput,
+	) -> Result<(), &'static str> {
+		ensure!(
+			input.witness.len() <= T::MaxWitnessBytes::get() as usize,
+			Error::<T>::WitnessTooLarge
+		);
+		ensure!(!FrozenUtxos::<T>::contains_key(input.outpoint), Error::<T>::UtxoFrozen);
+		ensure!(Self::is_mature(&input.outpoint), Error::<T>::ImmatureCoinbase);
+		ensure!(Self::is_settled(&input.outpoint), Error::<T>::SameBlockSpend);
+		ensure!(Self::is_unexpired(input_utxo), Error::<T>::UtxoExpired);
+		ensure!(Self::is_unlocked(input_utxo), Error::<T>::UtxoLocked);
+
+		Ok(())
+	}
+
+	// the index of the first input of `tx` that is missing or fails its own checks, with the
+	// reason. Validation stops at that input, so it is the one a failure about an input is about.
+	// Like validation, it checks the signatures only once every input passed its other checks
+	fn failing_input(tx: &Transaction) -> Option<(u32, &'static str)> {
+		let missing = tx.inputs.iter().position(|input| !UtxoStore::contains_key(input.outpoint));
+		if let Some(index) = missing {
+			return Some((index as u32, Error::<T>::MissingInput.into()));
+		}
+
+		let inputs = tx.inputs
+			.iter()
+			.filter_map(|input| UtxoStore::get(input.outpoint).map(|utxo| (input, utxo)))
+			.collect::<Vec<_>>();
+		let unchecked = inputs.iter().enumerate().find_map(|(index, (input, utxo))| {
+			Self::check_input(input, utxo).err().map(|reason| (index as u32, reason))
+		});
+
+		let payload = Self::signing_payload(tx);
+		let aggregate_valid = tx.aggregate_sig.map_or(true, |(pubkey, signature)| {
+			T::SignatureVerifier::verify(&signature, &payload, &pubkey)
+		});
+		unchecked.or_else(|| inputs.iter().position(|(input, utxo)| {
+			if Self::is_aggregated(tx, utxo) {
+				!aggregate_valid
+			} else {
+				!Self::is_signed(input, utxo, &payload)
+			}
+		}).map(|index| (index as u32, "Signature must be valid")))
+	}
+
+	// whether the aggregate signature of `tx` stands in for the signature of an input spending
+	// `utxo`. It never does for a cosigned output
+	fn is_aggregated(tx: &Transaction, utxo: &TransactionOutput) -> bool {
+		utxo.cosigner.is_none()
+			&& tx.aggregate_sig.map_or(false, |(pubkey, _)| pubkey == utxo.pubkey)
+	}
+
+	// whether `input` carries the signature over `payload` of the owner of `utxo`, along with the
+	// one of its cosigner if it has one, or of its fallback key once that may spend it
+	fn is_signed(input: &TransactionInput, utxo: &TransactionOutput, payload: &[u8]) -> bool {
+		let by_owner = match utxo.cosigner {
+			Some(cosigner) => Self::is_cosigned(&input.witness, payload, &utxo.pubkey, &cosigner),
+			None => T::SignatureVerifier::verify(&input.sigscript, payload, &utxo.pubkey),
+		};
+
+		by_owner
+			|| Self::active_fallback(utxo).map_or(false, |fallback| {
+				T::SignatureVerifier::verify(&input.sigscript, payload, &fallback)
+			})
+	}
+
+	// whether `witness` holds the signatures over `payload` of both `owner` and `cosigner`, in
+	// either order. A signature only verifies against one key, the same one twice never passes
+	fn is_cosigned(witness: &[u8], payload: &[u8], owner: &H256, cosigner: &H256) -> bool {
+		if witness.len() != COSIGNED_WITNESS_BYTES {
+			return false;
+		}
+
+		let (first, second) = witness.split_at(COSIGNED_WITNESS_BYTES / 2);
+		let (first, second) = (H512::from_slice(first), H512::from_slice(second));
+		let verify = |sig: &H512, pubkey: &H256| T::SignatureVerifier::verify(sig, payload, pubkey);
+		if verify(&first, owner) {
+			verify(&second, cosigner)
+		} else {
+			verify(&first, cosigner) && verify(&second, owner)
+		}
+	}
+
+	// the `covenant_hash` of the channel close the output under `outpoint` is reserved for, until
+	// the reservation lapses
+	fn active_reservation(outpoint: &H256) -> Option<H256> {
+		let now = <frame_system::Module<T>>::block_number();
+		Reservations::<T>::get(outpoint)
+			.filter(|(until, _)| now <= *until)
+			.map(|(_, closing)| closing)
+	}
+
+	// the fallback key of `utxo`, from its `after` block on
+	fn active_fallback(utxo: &TransactionOutput) -> Option<H256> {
+		let now = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+		utxo.fallback.filter(|fallback| now >= fallback.after).map(|fallback| fallback.pubkey)
+	}
+
+	// `tx` only moves value between outputs of a single key and shrinks the UTXO set
+	fn check_consolidation(tx: &Transaction) -> Result<(), Error<T>> {
+		ensure!(tx.outputs.len() < tx.inputs.len(), Error::<T>::ConsolidationNotShrinking);
+
+		// there is at least one input, more than there are outputs
+		let spent = Self::spent_outputs(&OnChainUtxos, tx)?;
+		let owner = spent[0].pubkey;
+		let pays_owner = spent.iter().all(|utxo| utxo.pubkey == owner)
+			&& tx.outputs.iter().all(|output| output.pubkey == owner)
+			&& tx.change_to.map_or(true, |change| change == owner);
+		ensure!(pays_owner, Error::<T>::NotSelfConsolidation);
+
+		Ok(())
+	}
+
+	// the outputs the inputs of `tx` spend. All of them have to exist before any signature is
+	// checked, so a spend that lost the race for an outpoint is rejected cheaply
+	fn spent_outputs(
+		utxos: &impl UtxoProvider,
+		tx: &Transaction,
+	) -> Result<Vec<TransactionOutput>, Error<T>> {
+		tx.inputs
+			.iter()
+			.map(|input| utxos.utxo(&input.outpoint).ok_or(Error::<T>::MissingInput))
+			.collect()
+	}
+
+	// the total of `values`, failing instead of wrapping around
+	fn sum_values(values: impl IntoIterator<Item = Value>) -> Result<Value, Error<T>> {
+		values
+			.into_iter()
+			.try_fold(0, |total: Value, value| total.checked_add(value))
+			.ok_or(Error::<T>::ValueOverflow)
+	}
+
+	/// What is left of `total_input` after paying for `total_output`.
+	pub fn check_conservation(total_input: Value, total_output: Value) -> Result<Value, Error<T>> {
+		total_input.checked_sub(total_output).ok_or(Error::<T>::OutputsExceedInputs)
+	}
+
+	// vesting inputs only give up their vested value, the rest has to go back to their owner
+	// in outputs vesting on over the `remainder` schedule
+	fn check_vesting(
+		spent: &[TransactionOutput],
+		outputs: &[TransactionOutput],
+	) -> Result<(), Error<T>> {
+		let now = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+
+		let mut unvested = BTreeMap::<(H256, AssetId, Vesting), Value>::new();
+		for utxo in spent {
+			if let Some(vesting) = utxo.vesting {
+				let locked = utxo.value - vesting.vested(utxo.value, now);
+				if locked > 0 {
+					let key = (utxo.pubkey, utxo.asset_id, vesting.remainder(now));
+					let owed = unvested.entry(key).or_default();
+					*owed = owed.checked_add(locked).ok_or(Error::<T>::ValueOverflow)?;
+				}
+			}
+		}
+		if unvested.is_empty() {
+			return Ok(());
+		}
+
+		for output in outputs {
+			if let Some(vesting) = output.vesting {
+				if let Some(owed) = unvested.get_mut(&(output.pubkey, output.asset_id, vesting)) {
+					*owed = owed.saturating_sub(output.value);
+				}
+			}
+		}
+		ensure!(unvested.values().all(|owed| *owed == 0), Error::<T>::VestingRemainderMissing);
+
+		Ok(())
+	}
+
+	/// Fails if two of `inputs` spend the same outpoint.
+	pub fn check_no_duplicate_inputs(inputs: &[TransactionInput]) -> Result<(), &'static str> {
+		match duplicate_outpoint(inputs) {
+			Some(outpoint) => {
+				log!(debug, "outpoint {:?} is spent more than once", outpoint);
+				Err("Each input must be used once")
+			},
+			None => Ok(()),
+		}
+	}
+
+	/// The native value `outputs` leave over from `spent`, the outputs they are paid from, and
+	/// from the native value of the `deposits` returned along with them. Value of one asset can
+	/// never pay for another: every asset an output holds has to come from an input, and only the
+	/// native asset may be left over.
+	pub fn check_asset_balances(
+		spent: &[TransactionOutput],
+		outputs: &[TransactionOutput],
+		deposits: Value,
+	) -> Result<Value, Error<T>> {
+		let mut assets: BTreeMap<_, ()> = spent.iter().map(|utxo| (utxo.asset_id, ())).collect();
+		if deposits > 0 {
+			assets.insert(NATIVE_ASSET, ());
+		}
+		ensure!(
+			outputs.iter().all(|output| assets.contains_key(&output.asset_id)),
+			Error::<T>::AssetImbalance
+		);
+		let values_of = |utxos: &[TransactionOutput], asset_id: AssetId| {
+			Self::sum_values(utxos.iter().filter(|utxo| utxo.asset_id == asset_id).map(|utxo| utxo.value))
+		};
+
+		let mut leftover = 0;
+		for asset_id in assets.keys().copied() {
+			let mut total_input = values_of(spent, asset_id)?;
+			if asset_id == NATIVE_ASSET {
+				total_input = total_input.checked_add(deposits).ok_or(Error::<T>::ValueOverflow)?;
+			}
+			let total_output = values_of(outputs, asset_id)?;
+			log!(trace, "asset {} inputs {} outputs {}", asset_id, total_input, total_output);
+			let surplus = Self::check_conservation(total_input, total_output)?;
+
+			// rewards are only paid in the native asset, so nothing else may be left over
+			if asset_id == NATIVE_ASSET {
+				leftover = surplus;
+			} else {
+				ensure!(surplus == 0, Error::<T>::NonNativeFee);
+			}
+		}
+
+		Ok(leftover)
+	}
+
+	fn apply_spend(tx: &Transaction) -> DispatchResult {
+		// 1. check that the transaction is valid
+		let validated = Self::validate_authorized_transaction(&OnChainUtxos, tx, None)?;
+
+		Self::update_storage(tx, &validated)?;
+
+		// 3. emit success event
+		Self::note_applied(tx.clone(), validated.reward);
+
+		Ok(())
+	}
+
+	// announce `tx` as applied with the `fee` it paid and index its txid under the current block
+	fn note_applied(tx: Transaction, fee: Value) {
+		let txid = txid(&tx);
+
+		TxsInBlock::<T>::append(<frame_system::Module<T>>::block_number(), txid);
+		Self::deposit_event(Event::TransactionSuccess(txid, fee, tx));
+	}
+
+	// the deposits the outputs spent by `inputs` hold
+	fn refunded_deposits(inputs: &[TransactionInput]) -> Value {
+		inputs.iter().fold(0, |total: Value, input| {
+			total.saturating_add(Deposits::get(input.outpoint))
+		})
+	}
+
+	// the deposits `outputs` new outputs hold
+	fn held_deposits(outputs: usize) -> Value {
+		T::OutputDeposit::get().saturating_mul(outputs as Value)
+	}
+
+	// apply `tx`, storing its outputs under the keys validation checked
+	fn update_storage(tx: &Transaction, validated: &ValidatedTransaction) -> DispatchResult {
+		let reward = validated.reward;
+		let created = validated.output_keys.len() + validated.change.is_some() as usize;
+		Self::ensure_block_has_room(created)?;
+		Self::ensure_utxo_set_has_room(tx.inputs.len(), created)?;
+
+		if T::BurnFees::get() {
+			// never reaching `RewardTotal`, the fee leaves the supply
+			TotalSupply::mutate(|total| *total = total.saturating_sub(reward));
+			FeesBurned::mutate(|burned| *burned = burned.saturating_add(reward));
+		} else {
+			let new_total = RewardTotal::get()
+				.checked_add(reward)
+				.ok_or("reward overflow")?;
+
+			RewardTotal::put(new_total);
+		}
+		TxsThisBlock::mutate(|txs| *txs = txs.saturating_add(1));
+		FeesThisBlock::mutate(|fees| *fees = fees.saturating_add(reward));
+
+		// 1. Remove all input utxos from the UtxoStore
+		let mut spent_uniques = BTreeMap::new();
+		let mut owners = BTreeMap::new();
+		for input in &tx.inputs {
+			if let Some(utxo) = Self::remove_utxo(&input.outpoint) {
+				// validation returned it to the transaction
+				Deposits::remove(input.outpoint);
+				owners.insert(utxo.pubkey, ());
+				if let Some(id) = utxo.unique {
+					spent_uniques.insert(id, input.outpoint);
+				}
+			}
+		}
+		for owner in owners.keys() {
+			let count = SpendCounts::get(owner);
+			if count == 0 {
+				SpendCountEntries::mutate(|entries| *entries = entries.saturating_add(1));
+			}
+			SpendCounts::insert(owner, count.saturating_add(1));
+		}
+
+		// 2. Create a new utxo
+		let deposit = T::OutputDeposit::get();
+		let change_key = validated.change.as_ref().map(|(key, _)| key);
+		for key in validated.output_keys.iter().chain(change_key).filter(|_| deposit > 0) {
+			Deposits::insert(key, deposit);
+		}
+		for (output, key) in tx.outputs.iter().zip(validated.output_keys.iter().copied()) {
+			if let Some(id) = output.unique {
+				if let Some(old) = spent_uniques.get(&id) {
+					Self::deposit_event(Event::UniqueTransferred(id, *old, key));
+				}
+			}
+			if !output.memo.is_empty() {
+				Self::deposit_event(Event::OutputMemo(key, output.memo.clone()));
+			}
+
+			Self::insert_utxo(key, output.clone());
+		}
+
+		// 3. Return the leftover value
+		if let Some(((key, value), pubkey)) = validated.change.zip(tx.change_to) {
+			Self::insert_utxo(key, TransactionOutput { value, pubkey, ..Default::default() });
+			Self::deposit_event(Event::ChangeReturned(key, value));
+		}
+
+		// 4. Destroy the burned value, no output holds it
+		if let Some(value) = tx.burn {
+			TotalSupply::mutate(|total| *total = total.saturating_sub(value));
+			ValueBurned::mutate(|burned| *burned = burned.saturating_add(value));
+			Self::deposit_event(Event::ValueBurned(txid(tx), value));
+		}
+		Ok(())
+	}
+
+	fn ensure_block_has_room(outputs: usize) -> DispatchResult {
+		let created = CreatedThisBlock::decode_len().unwrap_or(0);
+		ensure!(
+			created.saturating_add(outputs) <= T::MaxOutputsPerBlock::get() as usize,
+			Error::<T>::TooManyOutputs
+		);
+
+		Ok(())
+	}
+
+	// only transactions creating more outputs than they spend can fill the set
+	fn ensure_utxo_set_has_room(spent: usize, created: usize) -> DispatchResult {
+		if created > spent {
+			let size = (UtxoCount::get() as usize).saturating_sub(spent).saturating_add(created);
+			ensure!(size <= T::MaxUtxoSetSize::get() as usize, Error::<T>::UtxoSetFull);
+		}
+
+		Ok(())
+	}
+
+	// pick UTXOs of `owner` until they cover `value` plus the minimum fee and the deposit of the
+	// payment, their own deposits counting towards it
+	fn build_transfer(owner: &H256, dest: H256, value: Value) -> Result<Transaction, Error<T>> {
+		let deposit = T::OutputDeposit::get();
+		let needed = value
+			.checked_add(T::MinimumFee::get())
+			.and_then(|needed| needed.checked_add(deposit))
+			.ok_or(Error::<T>::InsufficientFunds)?;
+
+		let mut inputs = Vec::new();
+		let mut total: Value = 0;
+
+		for (outpoint, ()) in OwnedUtxos::iter_prefix(owner) {
+			if total >= needed {
+				break;
+			}
+
+			ensure!((inputs.len() as u32) < T::MaxTxInputs::get(), Error::<T>::TooManyInputs);
+
+			let utxo = UtxoStore::get(&outpoint).filter(|utxo| Self::is_spendable(&outpoint, utxo));
+			if let Some(utxo) = utxo {
+				total = total.saturating_add(utxo.value).saturating_add(Deposits::get(outpoint));
+				inputs.push(TransactionInput {
+					outpoint,
+					sigscript: H512::zero(),
+					witness: Vec::new(),
+				});
+			}
+		}
+
+		ensure!(total >= needed, Error::<T>::InsufficientFunds);
+
+		let mut outputs = vec![TransactionOutput { value, pubkey: dest, ..Default::default() }];
+		// change too small to hold its own deposit goes to the fee
+		let change = (total - needed).saturating_sub(deposit);
+		if change > 0 {
+			outputs.push(TransactionOutput { value: change, pubkey: *owner, ..Default::default() });
+		}
+
+		Ok(Transaction { inputs, outputs, ..Default::default() })
+	}
+
+	/// Whether `utxo`, stored under `outpoint`, is plain native value its owner alone can spend in
+	/// this block, the outputs `transfer` and `sweep` pick. Frozen, unique, expired, locked,
+	/// vesting, cosigned, reserved, immature reward and, if forbidden, same block outputs aren't.
+	fn is_spendable(outpoint: &H256, utxo: &TransactionOutput) -> bool {
+		utxo.asset_id == NATIVE_ASSET
+			&& utxo.unique.is_none()
+			&& Self::is_unexpired(utxo)
+			&& Self::is_unlocked(utxo)
+			&& utxo.vesting.is_none()
+			&& utxo.cosigner.is_none()
+			&& !FrozenUtxos::<T>::contains_key(outpoint)
+			&& Self::active_reservation(outpoint).is_none()
+			&& Self::is_mature(outpoint)
+			&& Self::is_settled(outpoint)
+	}
+
+	/// The unsigned transaction `sweep` applies: every spendable native output of `from`, up to
+	/// `MaxTxInputs` of them, paid to `to` with their deposits, less the minimum fee and the
+	/// deposit of the output.
+	pub fn build_sweep(from: &H256, to: H256) -> Result<Transaction, Error<T>> {
+		let mut inputs = Vec::new();
+		let mut total: Value = 0;
+
+		for (outpoint, ()) in OwnedUtxos::iter_prefix(from) {
+			if inputs.len() as u32 >= T::MaxTxInputs::get() {
+				break;
+			}
+
+			let utxo = UtxoStore::get(&outpoint).filter(|utxo| Self::is_spendable(&outpoint, utxo));
+			if let Some(utxo) = utxo {
+				total = total.saturating_add(utxo.value).saturating_add(Deposits::get(outpoint));
+				inputs.push(TransactionInput {
+					outpoint,
+					sigscript: H512::zero(),
+					witness: Vec::new(),
+				});
+			}
+		}
+
+		ensure!(!inputs.is_empty(), Error::<T>::NothingToSweep);
+		let value = total
+			.checked_sub(T::MinimumFee::get())
+			.and_then(|value| value.checked_sub(T::OutputDeposit::get()))
+			.filter(|value| *value > 0)
+			.ok_or(Error::<T>::InsufficientFunds)?;
+
+		let outputs = vec![TransactionOutput { value, pubkey: to, ..Default::default() }];
+		Ok(Transaction { inputs, outputs, ..Default::default() })
+	}
+
+	/// The unsigned transaction `rekey` applies: the output under `outpoint` passed on to
+	/// `new_pubkey` as a plain output of its asset and unique token, without the conditions it was
+	/// spent under. A native output pays the minimum fee and the deposit of the new output out of
+	/// its value and deposit. An output of another asset moves whole and pays neither, the deposit
+	/// it holds is all that covers the one of the new output.
+	pub fn build_rekey(outpoint: &H256, new_pubkey: H256) -> Result<Transaction, Error<T>> {
+		let utxo = UtxoStore::get(outpoint).ok_or(Error::<T>::UnknownOutpoint)?;
+		ensure!(utxo.cosigner.is_none(), Error::<T>::CosignerRequired);
+		let value = if utxo.asset_id == NATIVE_ASSET {
+			utxo.value
+				.saturating_add(Deposits::get(outpoint))
+				.checked_sub(T::MinimumFee::get())
+				.and_then(|value| value.checked_sub(T::OutputDeposit::get()))
+				.filter(|value| *value > 0)
+				.ok_or(Error::<T>::InsufficientFunds)?
+		} else {
+			utxo.value
+		};
+
+		let input = TransactionInput {
+			outpoint: *outpoint,
+			sigscript: H512::zero(),
+			witness: Vec::new(),
+		};
+		let output = TransactionOutput {
+			value,
+			pubkey: new_pubkey,
+			asset_id: utxo.asset_id,
+			unique: utxo.unique,
+			..Default::default()
+		};
+
+		Ok(Transaction { inputs: vec![input], outputs: vec![output], ..Default::default() })
+	}
+
+	/// The unsigned transaction `vest` applies: the output under `outpoint` spent into an output
+	/// for `recipient` per `(unlocks_at, value)` tranche of `schedule`, leaving at least the
+	/// minimum fee and the deposits of the tranches.
+	pub fn build_vest(
+		outpoint: &H256,
+		recipient: H256,
+		schedule: &[(T::BlockNumber, Value)],
+	) -> Result<Transaction, Error<T>> {
+		ensure!(!schedule.is_empty(), Error::<T>::EmptySchedule);
+		ensure!(schedule.iter().all(|(_, value)| *value > 0), Error::<T>::ZeroValueOutput);
+		let utxo = UtxoStore::get(outpoint).ok_or(Error::<T>::UnknownOutpoint)?;
+		ensure!(utxo.asset_id == NATIVE_ASSET, Error::<T>::NonNativeFee);
+
+		let vested = Self::sum_values(schedule.iter().map(|(_, value)| *value))?;
+		let needed = vested
+			.checked_add(T::MinimumFee::get())
+			.and_then(|needed| needed.checked_add(Self::held_deposits(schedule.len())))
+			.ok_or(Error::<T>::ValueOverflow)?;
+		let available = utxo.value.saturating_add(Deposits::get(outpoint));
+		ensure!(needed <= available, Error::<T>::InsufficientFunds);
+
+		let input = TransactionInput {
+			outpoint: *outpoint,
+			sigscript: H512::zero(),
+			witness: Vec::new(),
+		};
+		let outputs = schedule.iter().map(|(unlocks_at, value)| TransactionOutput {
+			value: *value,
+			pubkey: recipient,
+			unlocks_at: Some((*unlocks_at).saturated_into()),
+			..Default::default()
+		}).collect();
+
+		Ok(Transaction { inputs: vec![input], outputs, ..Default::default() })
+	}
+
+	/// The unsigned transaction `split` applies: the native output under `outpoint` spent into `n`
+	/// outputs for `recipient`, sharing its value less the minimum fee and their deposits. The
+	/// first output also holds what doesn't divide evenly.
+	pub fn build_split(outpoint: &H256, n: u32, recipient: H256) -> Result<Transaction, Error<T>> {
+		ensure!(n >= 1, Error::<T>::EmptySplit);
+		// checked before the outputs are built, `n` is up to the caller
+		ensure!(n <= T::MaxOutputsPerBlock::get(), Error::<T>::TooManyOutputs);
+		let utxo = UtxoStore::get(outpoint).ok_or(Error::<T>::UnknownOutpoint)?;
+		ensure!(utxo.asset_id == NATIVE_ASSET, Error::<T>::NonNativeFee);
+
+		let total = utxo.value
+			.saturating_add(Deposits::get(outpoint))
+			.checked_sub(T::MinimumFee::get())
+			.and_then(|total| total.checked_sub(Self::held_deposits(n as usize)))
+			.ok_or(Error::<T>::InsufficientFunds)?;
+		let share = total / n as Value;
+		ensure!(share > T::MinimumFee::get(), Error::<T>::SplitIntoDust);
+
+		let input = TransactionInput {
+			outpoint: *outpoint,
+			sigscript: H512::zero(),
+			witness: Vec::new(),
+		};
+		let remainder = total % n as Value;
+		let outputs = (0..n).map(|index| TransactionOutput {
+			value: if index == 0 { share + remainder } else { share },
+			pubkey: recipient,
+			..Default::default()
+		}).collect();
+
+		Ok(Transaction { inputs: vec![input], outputs, ..Default::default() })
+	}
+
+	// the vesting transaction with the signature and witness of `input` attached
+	fn signed_vest(
+		input: TransactionInput,
+		recipient: H256,
+		schedule: &[(T::BlockNumber, Value)],
+	) -> Result<Transaction, Error<T>> {
+		let mut tx = Self::build_vest(&input.outpoint, recipient, schedule)?;
+		tx.inputs[0] = input;
+
+		Ok(tx)
+	}
+
+	// the sweep transaction with `signatures` attached to its inputs
+	fn signed_sweep(from: &H256, to: H256, signatures: &[H512]) -> Result<Transaction, Error<T>> {
+		let mut tx = Self::build_sweep(from, to)?;
+		ensure!(signatures.len() == tx.inputs.len(), Error::<T>::SignatureCountMismatch);
+
+		for (input, signature) in tx.inputs.iter_mut().zip(signatures) {
+			input.sigscript = *signature;
+		}
+
+		Ok(tx)
+	}
+
+	// reward outputs are spendable once `CoinbaseMaturity` blocks passed since their creation,
+	// everything else right away
+	fn is_mature(outpoint: &H256) -> bool {
+		if !CoinbaseUtxos::contains_key(outpoint) {
+			return true;
+		}
+
+		let now = <frame_system::Module<T>>::block_number();
+		UtxoCreatedAt::<T>::get(outpoint)
+			.map_or(true, |created| now >= created.saturating_add(T::CoinbaseMaturity::get()))
+	}
+
+	fn is_expired(tx: &Transaction) -> bool {
+		let now = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+		tx.valid_until.map_or(false, |last| now > last)
+	}
+
+	// outputs can be spent up to and including their `expiry` block
+	fn is_unexpired(utxo: &TransactionOutput) -> bool {
+		let now = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+		utxo.expiry.map_or(true, |last| now <= last)
+	}
+
+	// outputs can be spent from their `unlocks_at` block on
+	fn is_unlocked(utxo: &TransactionOutput) -> bool {
+		let now = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+		utxo.unlocks_at.map_or(true, |first| now >= first)
+	}
+
+	// remove the outputs whose last block is `block`, adding their native value to the reward
+	fn sweep_expired(block: u64) {
+		let mut outputs: u32 = 0;
+		let mut value: Value = 0;
+
+		for outpoint in ExpiringAt::take(block) {
+			// spent in the meantime
+			if UtxoStore::get(&outpoint).map_or(true, |utxo| utxo.expiry != Some(block)) {
+				continue;
+			}
+
+			if let Some(utxo) = Self::remove_utxo(&outpoint) {
+				FrozenUtxos::<T>::remove(outpoint);
+				outputs += 1;
+				value = value.saturating_add(Deposits::take(outpoint));
+				if utxo.asset_id == NATIVE_ASSET {
+					value = value.saturating_add(utxo.value);
+				}
+			}
+		}
+
+		if outputs > 0 {
+			RewardTotal::mutate(|total| *total = total.saturating_add(value));
+			Self::deposit_event(Event::OutputsExpired(outputs, value));
+		}
+	}
+
+	// how long the pool may keep `tx`: `PoolLongevity`, unless it expires sooner
+	fn pool_longevity(tx: &Transaction) -> TransactionLongevity {
+		let now = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+		let remaining = tx.valid_until.map(|last| last.saturating_sub(now).saturating_add(1));
+
+		remaining.map_or(T::PoolLongevity::get(), |remaining| remaining.min(T::PoolLongevity::get()))
+	}
+
+	// with `SameBlockSpendForbidden` set, outputs are spendable from the block after their
+	// creation. This holds within a block too, whether the spends come in separate extrinsics or
+	// through `transfer` and `sweep`
+	fn is_settled(outpoint: &H256) -> bool {
+		if !T::SameBlockSpendForbidden::get() {
+			return true;
+		}
+
+		let now = <frame_system::Module<T>>::block_number();
+		UtxoCreatedAt::<T>::get(outpoint).map_or(true, |created| created < now)
+	}
+
+	/// The keys under which the outputs of `tx` are stored once it is applied, in output order.
+	/// They are fixed by the content of `tx` alone: neither a re-signed nor a relayed transaction
+	/// with its witnesses swapped can move them, so children can be signed before their parent is
+	/// in a block. Outputs keyed by the whole encoding before this keep their keys, entries are
+	/// never re-derived and need no migration.
+	pub fn output_keys(tx: &Transaction) -> Vec<H256> {
+		// Make sure the key is unique by using the entire tx and a unique index. Signatures are
+		// left out, re-signing a transaction must not move its outputs
+		let encoded = get_simple_tx(tx);
+
+		(0..tx.outputs.len() as u64)
+			.map(|index| BlakeTwo256::hash_of(&(&encoded, index)))
+			.collect()
+	}
+
+	/// The keys of all outputs applying `tx` creates, in the order `update_storage` inserts
+	/// them. The change output comes last whenever `change_to` is set, it only exists if the
+	/// leftover of the transaction exceeded `AutoChangeFee`.
+	pub fn outpoints_for(tx: &Transaction) -> Vec<H256> {
+		let mut keys = Self::output_keys(tx);
+		if tx.change_to.is_some() {
+			keys.push(Self::change_key(tx));
+		}
+
+		keys
+	}
+
+	/// Whether `tx` was applied, from how many of its outputs are unspent. The change output
+	/// isn't counted, it only exists if the leftover of the transaction was large enough.
+	pub fn is_applied(tx: &Transaction) -> AppliedStatus {
+		let keys = Self::output_keys(tx);
+		let unspent = keys.iter().filter(|key| UtxoStore::contains_key(key)).count();
+
+		match unspent {
+			0 => AppliedStatus::NotApplied,
+			unspent if unspent == keys.len() => AppliedStatus::FullyUnspent,
+			_ => AppliedStatus::PartiallySpent,
+		}
+	}
+
+	/// The fee a transaction of `tx_weight` should pay to be included within `target_blocks`,
+	/// from the fees per weight of the blocks in `RecentFeeStats`: the highest of them for the
+	/// next block, lower ones the later the target. Never below `MinimumFee`, which is also the
+	/// answer while there is no history to go on.
+	pub fn estimate_fee_within(target_blocks: u32, tx_weight: Weight) -> Value {
+		let mut fees = RecentFeeStats::get()
+			.into_iter()
+			.filter(|(_, used)| *used > 0)
+			.map(|(fees, used)| fees.saturating_mul(tx_weight as Value) / used as Value)
+			.collect::<Vec<_>>();
+		fees.sort_unstable();
+
+		// a target of `n` blocks takes the fee `1/n` of the way up from the lowest one
+		let index = fees.len().saturating_sub(1) / target_blocks.max(1) as usize;
+		fees.get(index).copied().unwrap_or(0).max(T::MinimumFee::get())
+	}
+
+	/// The fee a `spend` with `inputs` inputs, `outputs` outputs and no witnesses is quoted at,
+	/// its `minimal_spend_weight` priced at `WeightPrice`, larger outputs weigh more. Rounded up
+	/// so wallets paying it never fall short.
+	pub fn estimate_fee(inputs: u32, outputs: u32) -> Value {
+		let weight = minimal_spend_weight::<T::WeightInfo>(inputs, outputs);
+		let weight = FixedU128::saturating_from_integer(weight);
+
+		weight.saturating_mul(T::WeightPrice::get()).ceil().into_inner() / FixedU128::accuracy()
+	}
+
+	/// The key of the change output of `tx`, which comes right after its regular outputs.
+	pub fn change_key(tx: &Transaction) -> H256 {
+		BlakeTwo256::hash_of(&(&get_simple_tx(tx), tx.outputs.len() as u64))
+	}
+
+	/// Validate `tx` against the current state and return the outpoints it would spend, the
+	/// outputs it would create with their keys and the fee it pays, without applying it.
+	pub fn dry_run(tx: &Transaction) -> Result<DryRunResult, DryRunError> {
+		let validated = Self::validate_transaction(tx).map_err(|reason| {
+			let input_index = Self::failing_input(tx)
+				.filter(|(_, input_reason)| *input_reason == reason)
+				.map(|(index, _)| index);
+
+			DryRunError { input_index, reason: reason.as_bytes().to_vec() }
+		})?;
+		let spent = tx.inputs.iter().map(|input| input.outpoint).collect();
+		let mut created: Vec<_> = validated.output_keys
+			.into_iter()
+			.zip(tx.outputs.iter().cloned())
+			.collect();
+
+		if let Some(((key, value), pubkey)) = validated.change.zip(tx.change_to) {
+			created.push((key, TransactionOutput { value, pubkey, ..Default::default() }));
+		}
+
+		Ok((spent, created, validated.reward))
+	}
+
+	// `signature` of `authority` authorizes paying its rewards to `payout`
+	fn check_payout_preference(
+		authority: &H256,
+		payout: &H256,
+		signature: &H512,
+	) -> Result<(), Error<T>> {
+		let payload = payout_preference_payload(payout, PayoutNonce::get(authority));
+		ensure!(
+			T::SignatureVerifier::verify(signature, &payload, authority),
+			Error::<T>::InvalidPayoutSignature
+		);
+
+		Ok(())
+	}
+
+	// payout preferences are authorized by their signature, and only one per nonce is pooled
+	fn validate_payout_preference(
+		authority: &H256,
+		payout: &H256,
+		signature: &H512,
+	) -> TransactionValidity {
+		Self::check_payout_preference(authority, payout, signature)
+			.map_err(|_| InvalidTransaction::BadProof)?;
+
+		Ok(ValidTransaction {
+			priority: 0,
+			requires: Vec::new(),
+			provides: vec![(b"payout preference", authority, PayoutNonce::get(authority)).encode()],
+			longevity: T::PoolLongevity::get(),
+			propagate: true,
+		})
+	}
+
+	// the key the reward of the current block goes to: the claimed payout key if the author's
+	// session key signed it, the payout preference of the session key otherwise, falling back to
+	// the owner `AuthorityToOwner` maps the author to
+	fn payout_key(author: &T::AuthorityId) -> H256 {
+		let session_key = Self::author_key(author);
+		let preferred = PayoutPreference::get(&session_key)
+			.unwrap_or_else(|| T::AuthorityToOwner::convert(author.clone()));
+		let claim = match BlockPayout::take() {
+			Some(claim) => claim,
+			None => return preferred,
+		};
+
+		let block = <frame_system::Module<T>>::block_number().saturated_into::<u64>();
+		let signed = T::SignatureVerifier::verify(
+			&claim.signature,
+			&payout_payload(&claim.payout, block),
+			&session_key,
+		);
+		if !signed {
+			log!(warn, "payout claim for {:?} isn't signed by the block author", claim.payout);
+			Self::deposit_event(Event::PayoutClaimRejected(claim.payout));
+			return preferred;
+		}
+
+		claim.payout
+	}
+
+	// the key payout claims and preferences of a block author are signed with: its public key,
+	// or the hash of authority ids that aren't 32 bytes long
+	fn author_key(author: &T::AuthorityId) -> H256 {
+		AuthorityKeyOwner::convert(author)
+	}
+
+	fn disperse_reward(payout: &H256) {
+		let block = <frame_system::Module<T>>::block_number();
+		// no fees are collected with `BurnFees` set, leaving the subsidy alone
+		let fees = RewardTotal::take();
+		// the fees were summed up checked as they were paid, only the subsidy can overflow them
+		let subsidy = Self::issue_subsidy(block, Value::max_value() - fees);
+		// the treasury only shares in the fees, rounded down so nothing gets lost. The rest of
+		// them and the subsidy go to the author
+		let treasury_reward = T::TreasuryShare::get().mul_floor(fees);
+		let reward = fees - treasury_reward + subsidy;
+		let current_block = block.saturated_into::<u64>();
+
+		if treasury_reward > 0 {
+			let utxo = TransactionOutput {
+				value: treasury_reward,
+				pubkey: T::TreasuryPubkey::get(),
+				asset_id: NATIVE_ASSET,
+				unique: None,
+				refund_to: None,
+				covenant: None,
+				expiry: None,
+				memo: Vec::new(),
+				unlocks_at: None,
+				vesting: None,
+				fallback: None,
+				cosigner: None,
+			};
+			// tagged so it can't collide with the author's output even if the keys are the same
+			let hash = BlakeTwo256::hash_of(&(b"treasury", &utxo, current_block));
+
+			Self::insert_utxo(hash, utxo);
+			CoinbaseUtxos::insert(hash, ());
+			Self::deposit_event(Event::TreasuryRewarded(treasury_reward, hash));
+		}
+
+		// nothing to pay, and empty outputs would only clutter the utxo set
+		if reward > 0 {
+			let utxo = TransactionOutput{
+				value: reward,
+				pubkey: *payout,
+				asset_id: NATIVE_ASSET,
+				unique: None,
+				refund_to: None,
+				covenant: None,
+				expiry: None,
+				memo: Vec::new(),
+				unlocks_at: None,
+				vesting: None,
+				fallback: None,
+				cosigner: None,
+			};
+
+			let hash = BlakeTwo256::hash_of(&(&utxo, current_block));
+
+			// Store the Utxo
+			Self::insert_utxo(hash, utxo);
+			CoinbaseUtxos::insert(hash, ());
+			LastRewardOutpoint::put(hash);
+
+			Self::deposit_event(Event::RewardsIssued(reward, hash));
+		}
+
+		// reading every output of the block is too much for a live chain
+		#[cfg(feature = "try-runtime")]
+		if let Err(reason) = Self::check_reward_invariant(fees, subsidy) {
+			log!(error, "block reward invariant broken: {}", reason);
+		}
+	}
+
+	/// Check that the block paid out all of its reward: nothing is left in `RewardTotal`, and
+	/// the reward outputs created in the block hold exactly the `fees` it collected and the
+	/// `subsidy` it issued. A reorg discards the state of the abandoned blocks, their rewards
+	/// included, and re-executes the new branch, so this holds on every branch.
+	///
+	/// Checked at the end of every block paying out its reward when built with the `try-runtime`
+	/// feature, which logs a broken invariant as an error.
+	pub fn check_reward_invariant(fees: Value, subsidy: Value) -> Result<(), &'static str> {
+		ensure!(RewardTotal::get() == 0, "rewards are left undistributed");
+
+		let issued = CreatedThisBlock::get()
+			.into_iter()
+			.filter(|outpoint| CoinbaseUtxos::contains_key(outpoint))
+			.filter_map(|outpoint| UtxoStore::get(outpoint))
+			.try_fold(0, |total: Value, utxo| total.checked_add(utxo.value))
+			.ok_or("reward outputs overflow")?;
+		ensure!(
+			fees.checked_add(subsidy) == Some(issued),
+			"reward outputs don't hold the fees and subsidy of the block"
+		);
+
+		Ok(())
+	}
+
+	// add the subsidy of `block` to the total supply, as far as `MaxSupply` allows and at most
+	// `room`, so that it can be added to the fees of the block
+	fn issue_subsidy(block: T::BlockNumber, room: Value) -> Value {
+		let requested = T::Issuance::issuance(block);
+		let headroom = T::MaxSupply::get().saturating_sub(TotalSupply::get());
+		let capped = requested.min(headroom);
+		if capped < requested {
+			Self::deposit_event(Event::SubsidyClamped(requested, capped));
+		}
+
+		// whatever doesn't fit is never issued, rather than issued and lost
+		let subsidy = capped.min(room);
+		if subsidy < capped {
+			log!(error, "the block reward overflows, subsidy cut from {} to {}", capped, subsidy);
+			Self::deposit_event(Event::RewardOverflowClamped(capped, subsidy));
+		}
+
+		TotalSupply::mutate(|total| *total = total.saturating_add(subsidy));
+		subsidy
+	}
+
+	/// The subsidy the current block pays, before the `MaxSupply` cap.
+	pub fn current_subsidy() -> Value {
+		T::Issuance::issuance(<frame_system::Module<T>>::block_number())
+	}
+
+	/// The fees collected in the current block so far, paid out when it is finalized.
+	pub fn pending_reward() -> Value {
+		RewardTotal::get()
+	}
+
+	/// The fees and subsidy the current block pays so far and where the last reward went.
+	pub fn reward_info() -> RewardInfo {
+		RewardInfo {
+			pending_fees: Self::pending_reward(),
+			next_subsidy: Self::current_subsidy(),
+			last_reward_outpoint: LastRewardOutpoint::get(),
+		}
+	}
+
+	/// The number of blocks until the subsidy next changes, if the configured schedule knows.
+	pub fn blocks_until_next_halving() -> Option<T::BlockNumber> {
+		let now = <frame_system::Module<T>>::block_number();
+
+		T::Issuance::next_change(now).map(|next| next.saturating_sub(now))
+	}
+
+	/// Total native value of all unspent outputs owned by `pubkey` in the current state.
+	///
+	/// Queried at a historical block (e.g. through the runtime API) this yields the balance as of
+	/// that block, which requires the node to keep the state around, i.e. run in archive mode.
+	pub fn balance_of(pubkey: &H256) -> Value {
+		Self::asset_balance_of(pubkey, NATIVE_ASSET)
+	}
+
+	/// Total value of `asset_id` held by the unspent outputs owned by `pubkey`.
+	pub fn asset_balance_of(pubkey: &H256, asset_id: AssetId) -> Value {
+		OwnedUtxos::iter_prefix(pubkey)
+			.filter_map(|(outpoint, ())| UtxoStore::get(&outpoint))
+			.filter(|utxo| utxo.asset_id == asset_id)
+			.fold(0, |total: Value, utxo| total.saturating_add(utxo.value))
+	}
+
+	/// Merkle proof that `outpoint` is unspent, against the root of the whole UTXO set.
+	///
+	/// This walks the entire set, so it is only meant to be called through the runtime API.
+	pub fn utxo_proof(outpoint: &H256) -> Option<merkle::UtxoProof> {
+		let utxo = UtxoStore::get(outpoint)?;
+
+		let mut entries = UtxoStore::iter().collect::<Vec<_>>();
+		entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+		let index = entries.iter().position(|(key, _)| key == outpoint)?;
+		let leaves = entries
+			.iter()
+			.map(|(key, utxo)| commitment_leaf(key, utxo))
+			.collect::<Vec<_>>();
+
+		Some(merkle::UtxoProof::V1 {
+			outpoint: *outpoint,
+			utxo,
+			index: index as u32,
+			leaves: leaves.len() as u32,
+			siblings: merkle::merkle_path(leaves.clone(), index),
+			root: merkle::merkle_root(leaves),
+		})
+	}
+
+	/// Number of confirmations `outpoint` has as of block `at`: 1 in the block that created it,
+	/// plus one for every block built on top. `None` if it is unspent or didn't exist yet at `at`.
+	pub fn confirmations(outpoint: &H256, at: T::BlockNumber) -> Option<u32> {
+		let created = UtxoCreatedAt::<T>::get(outpoint)?;
+		if at < created {
+			return None;
+		}
+
+		Some((at - created).saturated_into::<u32>().saturating_add(1))
+	}
+
+	/// Every entry of the UTXO set, in storage order.
+	pub fn utxo_iter() -> impl Iterator<Item = (H256, TransactionOutput)> {
+		UtxoStore::iter()
+	}
+
+	/// Up to `limit` entries of the UTXO set in storage order, starting after the storage key
+	/// `start_key` or at the first entry without one, and the cursor to continue from if any
+	/// entries are left. The cursor is a storage key, so the pages only add up to the UTXO set
+	/// when they are all read at the same block hash.
+	pub fn utxos_paged(
+		start_key: Option<Vec<u8>>,
+		limit: u32,
+	) -> (Vec<(H256, TransactionOutput)>, Option<Vec<u8>>) {
+		let prefix = UtxoStore::final_prefix();
+		let mut key = match start_key {
+			Some(key) if key.starts_with(&prefix) => key,
+			Some(_) => return (Vec::new(), None),
+			None => prefix.to_vec(),
+		};
+
+		let mut page = Vec::new();
+		while (page.len() as u32) < limit {
+			let next = match next_utxo_key(&key) {
+				Some(next) => next,
+				None => return (page, None),
+			};
+
+			// the key ends in the SCALE encoded outpoint after 16 bytes of its hash
+			let outpoint = H256::decode(&mut &next[prefix.len() + 16..]).ok();
+			let utxo = frame_support::storage::unhashed::get::<TransactionOutput>(&next);
+			if let Some(entry) = outpoint.zip(utxo) {
+				page.push(entry);
+			}
+			key = next;
+		}
+
+		let cursor = next_utxo_key(&key).map(|_| key);
+		(page, cursor)
+	}
+
+	/// Every entry of the UTXO set, for snapshotting it.
+	#[cfg(feature = "std")]
+	pub fn export_utxo_set() -> Vec<(H256, TransactionOutput)> {
+		Self::utxo_iter().collect()
+	}
+
+	/// Seed an empty UTXO set with previously exported `entries`, which count as created in the
+	/// current block.
+	#[cfg(feature = "std")]
+	pub fn import_utxo_set(entries: Vec<(H256, TransactionOutput)>) -> Result<(), &'static str> {
+		ensure!(UtxoStore::iter().next().is_none(), "utxo set is not empty");
+		ensure!(
+			entries.iter().all(|(_, utxo)| !utxo.pubkey.is_zero()),
+			"imported utxo paying the zero key"
+		);
+
+		for (key, utxo) in entries {
+			if utxo.asset_id == NATIVE_ASSET {
+				TotalSupply::mutate(|total| *total = total.saturating_add(utxo.value));
+			}
+			Self::insert_utxo(key, utxo);
+		}
+
+		Ok(())
+	}
+
+	/// Check the invariants of the UTXO ledger against the current state:
+	/// - no unspent output is empty
+	/// - `OwnedUtxos` indexes exactly the outputs in `UtxoStore` under their owners
+	/// - `TotalSupply` is the native value of all outputs plus the undistributed `RewardTotal` and
+	///   the `Deposits` held
+	/// - every deposit is held by an unspent output
+	/// - `UtxoCommitment` commits to the UTXO set
+	/// - every frozen outpoint is unspent
+	///
+	/// This walks the entire state, it is only meant for try-runtime and tests.
+	pub fn try_state(_block: T::BlockNumber) -> Result<(), &'static str> {
+		let mut native: Value = 0;
+		let mut commitment = H256::zero();
+		let mut count: u32 = 0;
+
+		for (outpoint, utxo) in UtxoStore::iter() {
+			count = count.saturating_add(1);
+			ensure!(utxo.value > 0, "utxo set holds an empty output");
+			ensure!(
+				OwnedUtxos::contains_key(&utxo.pubkey, &outpoint),
+				"utxo is missing from the owner index"
+			);
+
+			if utxo.asset_id == NATIVE_ASSET {
+				native = native.checked_add(utxo.value).ok_or("native value overflows")?;
+			}
+			commitment ^= commitment_leaf(&outpoint, &utxo);
+		}
+
+		for (owner, outpoint, ()) in OwnedUtxos::iter() {
+			ensure!(
+				UtxoStore::get(&outpoint).map(|utxo| utxo.pubkey) == Some(owner),
+				"owner index holds an outpoint the owner doesn't own"
+			);
+		}
+
+		let mut deposits: Value = 0;
+		for (outpoint, deposit) in Deposits::iter() {
+			ensure!(UtxoStore::contains_key(outpoint), "deposit is held by a spent output");
+			deposits = deposits.checked_add(deposit).ok_or("deposits overflow")?;
+		}
+
+		ensure!(
+			native.checked_add(RewardTotal::get()).and_then(|total| total.checked_add(deposits))
+				== Some(TotalSupply::get()),
+			"total supply doesn't match the utxo set"
+		);
+		ensure!(commitment == UtxoCommitment::get(), "commitment doesn't match the utxo set");
+		ensure!(count == UtxoCount::get(), "utxo count doesn't match the utxo set");
+		ensure!(
+			SpendCounts::iter().count() as u32 == SpendCountEntries::get(),
+			"spend count entries don't match the spend counts"
+		);
+
+		for outpoint in FrozenUtxos::<T>::iter_keys() {
+			ensure!(UtxoStore::contains_key(outpoint), "frozen outpoint is not in the utxo set");
+		}
+		for outpoint in Reservations::<T>::iter_keys() {
+			ensure!(UtxoStore::contains_key(outpoint), "reserved outpoint is not in the utxo set");
+		}
+
+		Ok(())
+	}
+
+	/// The tags `tx` provides in the transaction pool: the keys of its outputs and its inputs'
+	/// outpoints. Spends of the same outpoint provide the same tag, so the pool only keeps the one
+	/// with the higher priority.
+	pub fn pool_tags(tx: &Transaction) -> Vec<Vec<u8>> {
+		let outputs = Self::output_keys(tx).into_iter().map(|key| key.encode());
+		// tagged apart from output keys, a spend of an output of another pooled transaction
+		// doesn't conflict with it
+		let inputs = tx.inputs.iter().map(|input| (b"spent", input.outpoint).encode());
+
+		outputs.chain(inputs).collect()
+	}
+
+	/// The fee `tx` pays per byte of its encoding.
+	pub fn fee_rate(tx: &Transaction, fee: Value) -> Value {
+		fee / (tx.encoded_size() as Value).max(1)
+	}
+
+	/// The pool priority of `tx`: its fee rate in the upper half, so that higher rates always
+	/// come first, and bits of its hash in the lower half, so that every node orders
+	/// transactions paying the same rate the same way.
+	pub fn priority(tx: &Transaction, fee_rate: Value) -> u64 {
+		let rate: u32 = fee_rate.saturated_into();
+		let hash = BlakeTwo256::hash_of(tx);
+		let tiebreak = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]);
+
+		(rate as u64) << 32 | tiebreak as u64
+	}
+
+	// move the floor by an eighth towards where the fullness of this block asks for
+	fn update_min_fee_rate() {
+		let used = <frame_system::Module<T>>::block_weight().total();
+		let max = T::BlockWeights::get().max_block;
+		let fullness = Perbill::from_rational_approximation(used, max);
+		let target = T::TargetBlockFullness::get();
+
+		MinFeeRate::mutate(|rate| {
+			let step = (*rate / 8).max(1);
+
+			*rate = if fullness > target {
+				rate.saturating_add(step)
+			} else if fullness < target {
+				rate.saturating_sub(step).max(T::BaseFeeRate::get())
+			} else {
+				*rate
+			};
+		});
+	}
+
+	// add the fees and weight of the current block to `RecentFeeStats`, dropping the oldest
+	// entries beyond `FeeHistoryLength`
+	fn record_fee_stats() {
+		let used = <frame_system::Module<T>>::block_weight().total();
+		let length = T::FeeHistoryLength::get() as usize;
+
+		RecentFeeStats::mutate(|stats| {
+			stats.push((FeesThisBlock::get(), used));
+			let excess = stats.len().saturating_sub(length);
+			stats.drain(..excess);
+		});
+	}
+
+	// every write to the utxo set goes through these two so that the indexes stay consistent
+	fn insert_utxo(key: H256, utxo: TransactionOutput) {
+		UtxoCommitment::mutate(|c| *c ^= commitment_leaf(&key, &utxo));
+		if !UtxoStore::contains_key(key) {
+			UtxoCount::mutate(|count| *count = count.saturating_add(1));
+		}
+		OwnedUtxos::insert(&utxo.pubkey, &key, ());
+		UtxoCreatedAt::<T>::insert(key, <frame_system::Module<T>>::block_number());
+		CreatedThisBlock::append(key);
+		PaidThisBlock::append(utxo.pubkey);
+		if let Some(id) = utxo.unique {
+			UniqueOutpoint::insert(id, key);
+		}
+		if let Some(last) = utxo.expiry.filter(|_| T::SweepExpired::get()) {
+			ExpiringAt::append(last, key);
+		}
+		UtxoStore::insert(key, utxo);
+	}
+
+	fn remove_utxo(key: &H256) -> Option<TransactionOutput> {
+		let utxo = UtxoStore::take(key)?;
+
+		// XOR is its own inverse, so removing the leaf again cancels the insertion out
+		UtxoCommitment::mutate(|c| *c ^= commitment_leaf(key, &utxo));
+		UtxoCount::mutate(|count| *count = count.saturating_sub(1));
+		OwnedUtxos::remove(&utxo.pubkey, key);
+		UtxoCreatedAt::<T>::remove(key);
+		CoinbaseUtxos::remove(key);
+		Reservations::<T>::remove(key);
+		SpentThisBlock::append((*key, utxo.pubkey));
+		if let Some(id) = utxo.unique {
+			UniqueOutpoint::remove(id);
+		}
+
+		Some(utxo)
+	}
+}
+
+impl<T: Config> ProvideInherent for Module<T> {
+	type Call = Call<T>;
+	type Error = MakeFatalError<()>;
+	const INHERENT_IDENTIFIER: InherentIdentifier = INHERENT_IDENTIFIER;
+
+	// authors without a payout key provide no data, their reward goes to their session key
+	fn create_inherent(data: &InherentData) -> Option<Self::Call> {
+		data.get_data::<PayoutClaim>(&INHERENT_IDENTIFIER)
+			.ok()
+			.flatten()
+			.map(Call::set_payout)
+	}
+}
+
+impl<T: Config> ValidateUnsigned for Module<T> {
+	type Call = Call<T>;
+
+	// Unsigned spends are authorized by their input signatures, so the pool only needs the
+	// transaction to be valid and to pay at least the current fee rate floor
+	fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+		if let Call::set_payout_key(authority, payout, signature) = call {
+			return Self::validate_payout_preference(authority, payout, signature);
+		}
+
+		let tx = match call {
+			Call::spend(tx) => tx.clone(),
+			Call::consolidate(tx) => {
+				Self::check_consolidation(tx).map_err(|_| InvalidTransaction::Call)?;
+				tx.clone()
+			},
+			Call::sweep(from, to, signatures) => Self::signed_sweep(from, *to, signatures)
+				.map_err(|_| InvalidTransaction::Call)?,
+			Call::rekey(outpoint, sigscript, new_pubkey) => {
+				let mut tx = Self::build_rekey(outpoint, *new_pubkey)
+					.map_err(|_| InvalidTransaction::Stale)?;
+				tx.inputs[0].sigscript = *sigscript;
+				tx
+			},
+			Call::vest(input, recipient, schedule) => {
+				Self::signed_vest(input.clone(), *recipient, schedule)
+					.map_err(|_| InvalidTransaction::Stale)?
+			},
+			Call::split(outpoint, sigscript, n, recipient) => {
+				let mut tx = Self::build_split(outpoint, *n, *recipient)
+					.map_err(|_| InvalidTransaction::Stale)?;
+				tx.inputs[0].sigscript = *sigscript;
+				tx
+			},
+			_ => return InvalidTransaction::Call.into(),
+		};
+		let tx = &tx;
+
+		let fee = Self::validate_transaction(tx).map_err(|e| {
+			// the transaction was applied already, its inputs were spent by a conflicting
+			// transaction in the meantime, or it or one of its inputs expired
+			if e == <&'static str>::from(Error::<T>::AlreadyApplied)
+				|| e == <&'static str>::from(Error::<T>::MissingInput)
+				|| e == <&'static str>::from(Error::<T>::TransactionExpired)
+				|| e == <&'static str>::from(Error::<T>::UtxoExpired)
+			{
+				InvalidTransaction::Stale
+			} else if e == <&'static str>::from(Error::<T>::UtxoLocked) {
+				// spendable once the lock is over
+				InvalidTransaction::Future
+			} else {
+				InvalidTransaction::Call
+			}
+		})?.reward;
+		let fee_rate = Self::fee_rate(tx, fee);
+		let floor = match call {
+			Call::consolidate(_) => T::ConsolidationFeeMultiplier::get() * MinFeeRate::get(),
+			_ => MinFeeRate::get(),
+		};
+		if fee_rate < floor {
+			return InvalidTransaction::Payment.into();
+		}
 
-		Self::deposit_event(Event::RewardsIssued(reward, hash));
+		Ok(ValidTransaction {
+			priority: Self::priority(tx, fee_rate),
+			requires: Vec::new(),
+			provides: Self::pool_tags(tx),
+			longevity: Self::pool_longevity(tx),
+			propagate: true,
+		})
 	}
 }
@@ -0,0 +1,120 @@
+//! Merkle proofs of inclusion in the UTXO set.
+//!
+//! The tree is built over the `commitment_leaf` of every unspent output, ordered by outpoint.
+//! Nodes are hashed pairwise with `BlakeTwo256`; a node without a sibling is carried up to the
+//! next level unchanged.
+
+use codec::{Decode, Encode};
+use frame_support::dispatch::Vec;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+
+use crate::TransactionOutput;
+
+/// Proof that an output is part of the UTXO set with the given merkle `root`.
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Debug)]
+pub enum UtxoProof {
+	V1 {
+		outpoint: H256,
+		utxo: TransactionOutput,
+		// position of the leaf in the tree and the total number of leaves
+		index: u32,
+		leaves: u32,
+		// sibling hashes from the leaf level up to the root
+		siblings: Vec<H256>,
+		root: H256,
+	},
+}
+
+impl UtxoProof {
+	/// The root this proof was generated against.
+	pub fn root(&self) -> H256 {
+		match self {
+			UtxoProof::V1 { root, .. } => *root,
+		}
+	}
+}
+
+fn hash_node(left: &H256, right: &H256) -> H256 {
+	BlakeTwo256::hash_of(&(left, right))
+}
+
+fn next_layer(layer: &[H256]) -> Vec<H256> {
+	layer
+		.chunks(2)
+		.map(|pair| match pair {
+			[left, right] => hash_node(left, right),
+			[single] => *single,
+			_ => unreachable!("chunks(2) yields one or two elements; qed"),
+		})
+		.collect()
+}
+
+/// The merkle root over the given leaves, or zero for an empty tree.
+pub fn merkle_root(leaves: Vec<H256>) -> H256 {
+	let mut layer = leaves;
+
+	while layer.len() > 1 {
+		layer = next_layer(&layer);
+	}
+
+	layer.first().copied().unwrap_or_else(H256::zero)
+}
+
+/// The siblings needed to recompute the root from the leaf at `index`.
+pub fn merkle_path(leaves: Vec<H256>, index: usize) -> Vec<H256> {
+	let mut layer = leaves;
+	let mut index = index;
+	let mut siblings = Vec::new();
+
+	while layer.len() > 1 {
+		if let Some(sibling) = layer.get(index ^ 1) {
+			siblings.push(*sibling);
+		}
+
+		layer = next_layer(&layer);
+		index /= 2;
+	}
+
+	siblings
+}
+
+/// Check that `proof` shows its output to be part of the UTXO set committed to by `root`.
+pub fn verify_utxo_proof(root: &H256, proof: &UtxoProof) -> bool {
+	match proof {
+		UtxoProof::V1 { outpoint, utxo, index, leaves, siblings, root: proof_root } => {
+			if proof_root != root || index >= leaves {
+				return false;
+			}
+
+			let mut hash = crate::commitment_leaf(outpoint, utxo);
+			let mut siblings = siblings.iter();
+			let mut index = *index;
+			let mut width = *leaves;
+
+			while width > 1 {
+				// the last node of an odd layer has no sibling and is carried up as is
+				if index ^ 1 < width {
+					let sibling = match siblings.next() {
+						Some(sibling) => sibling,
+						None => return false,
+					};
+
+					hash = if index % 2 == 0 {
+						hash_node(&hash, sibling)
+					} else {
+						hash_node(sibling, &hash)
+					};
+				}
+
+				index /= 2;
+				width = (width + 1) / 2;
+			}
+
+			siblings.next().is_none() && hash == *root
+		}
+	}
+}
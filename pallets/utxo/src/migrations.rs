@@ -0,0 +1,468 @@
+//! Storage migrations, run from `on_runtime_upgrade`.
+//!
+//! `StorageVersion` records the layout the storage is in. `migrate` brings storage of any older
+//! version to the latest one and is a no-op on up to date chains. `pre_upgrade` and
+//! `post_upgrade` are checks that try-runtime can run against live state, asserting that the
+//! UTXO set keeps its entries and value. The versions that did more than add a field to outputs
+//! have a module with the steps they take and checks of their own.
+//!
+//! The keys of `UtxoStore` and of the maps following it are moved to their current hasher before
+//! anything else runs, so every other migration reads them in their current layout. Outputs only
+//! ever gained fields at their end, so an output of any older layout is translated to the current
+//! one in a single pass. One that doesn't decode in the layout of its version is logged and left
+//! in place, never removed.
+
+use codec::{Decode, DecodeAll};
+use frame_support::{
+	debug::error,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::Get,
+	weights::Weight,
+};
+use sp_core::H256;
+use sp_std::vec::Vec;
+
+use crate::{
+	commitment_leaf, next_utxo_key, Config, Releases, StorageVersion, TransactionOutput,
+	UtxoCommitment, UtxoCount, UtxoStore, Value, LOG_TARGET,
+};
+
+/// The versions that appended a field to `TransactionOutput`, in order. `asset_id` and `unique`
+/// came before `StorageVersion`, the v0 layout is the one without them.
+pub const FIELDS_ADDED_AT: [Releases; 8] = [
+	Releases::V2,
+	Releases::V5,
+	Releases::V6,
+	Releases::V7,
+	Releases::V8,
+	Releases::V9,
+	Releases::V10,
+	Releases::V11,
+];
+
+/// Bring the storage up to the latest version, returning the weight used.
+pub fn migrate<T: Config>() -> Weight {
+	let mut weight = T::DbWeight::get().reads(1);
+
+	let version = StorageVersion::get();
+	if version == Releases::V12 {
+		return weight;
+	}
+
+	if version < Releases::V4 {
+		weight = weight.saturating_add(v4::rehash_keys::<T>());
+	}
+	weight = weight.saturating_add(v12::rehash_keys::<T>());
+	if version < Releases::V11 {
+		weight = weight.saturating_add(translate_from::<T>(version));
+	}
+	if version < Releases::V1 {
+		weight = weight.saturating_add(v1::index_utxos::<T>());
+	}
+	if version < Releases::V3 {
+		weight = weight.saturating_add(v3::count_utxos::<T>());
+	}
+	StorageVersion::put(Releases::V12);
+
+	weight.saturating_add(T::DbWeight::get().writes(1))
+}
+
+/// The number of entries in the UTXO set and the value they hold, for comparing the set before
+/// and after a migration.
+pub fn utxo_set_summary() -> (u32, Value) {
+	UtxoStore::iter_values().fold((0, 0), |(count, total), utxo| {
+		(count + 1, total.saturating_add(utxo.value))
+	})
+}
+
+/// `utxo_set_summary` of a UTXO set stored in the layout of `version`, under either hasher.
+/// Entries that don't decode in that layout count without value, so a migration losing them
+/// doesn't keep the summary.
+pub fn summary_at(version: Releases) -> (u32, Value) {
+	let mut summary: (u32, Value) = (0, 0);
+
+	let mut key = UtxoStore::final_prefix().to_vec();
+	while let Some(next) = next_utxo_key(&key) {
+		let value = unhashed::get_raw(&next)
+			.and_then(|encoded| upgrade(&encoded, version))
+			.map_or(0, |utxo| utxo.value);
+		summary = (summary.0 + 1, summary.1.saturating_add(value));
+		key = next;
+	}
+
+	summary
+}
+
+/// The state `post_upgrade` compares against, the UTXO set read in the layout of the version
+/// the storage is at.
+pub fn pre_upgrade() -> Result<(u32, Value), &'static str> {
+	Ok(summary_at(StorageVersion::get()))
+}
+
+/// The checks of `migrate`, whatever version it started from.
+pub fn post_upgrade(before: (u32, Value)) -> Result<(), &'static str> {
+	if StorageVersion::get() != Releases::V12 {
+		return Err("storage is not at v12");
+	}
+	if utxo_set_summary() != before {
+		return Err("the utxo set changed");
+	}
+
+	Ok(())
+}
+
+/// `encoded`, an output stored in the layout of `version`, in the current layout. The fields
+/// added since all encode to a single zero byte when unset, so the output is completed by
+/// appending one for each of them.
+pub fn upgrade(encoded: &[u8], version: Releases) -> Option<TransactionOutput> {
+	if version == Releases::V0 {
+		return v1::OldTransactionOutput::decode_all(encoded).ok().map(Into::into);
+	}
+
+	let missing = FIELDS_ADDED_AT.iter().filter(|added| **added > version).count();
+	let mut encoded = encoded.to_vec();
+	encoded.resize(encoded.len() + missing, 0);
+
+	TransactionOutput::decode_all(&encoded).ok()
+}
+
+/// Rewrite every entry of `UtxoStore` from the layout of `version` to the current one and
+/// recompute the `UtxoCommitment` over the result, returning the weight used.
+///
+/// Entries that don't decode in that layout are left as they are and logged, they are never
+/// removed. The keys have to be under the current hasher already.
+pub fn translate_from<T: Config>(version: Releases) -> Weight {
+	let prefix_len = UtxoStore::final_prefix().len();
+	let mut entries: u64 = 0;
+	let mut translated: u64 = 0;
+	let mut commitment = H256::zero();
+
+	let mut key = UtxoStore::final_prefix().to_vec();
+	while let Some(next) = next_utxo_key(&key) {
+		entries += 1;
+		// the outpoint follows the 16 bytes of its hash
+		let utxo = unhashed::get_raw(&next)
+			.filter(|_| next.len() == prefix_len + 16 + 32)
+			.and_then(|encoded| upgrade(&encoded, version));
+		match utxo {
+			Some(utxo) => {
+				let outpoint = H256::from_slice(&next[prefix_len + 16..]);
+				commitment ^= commitment_leaf(&outpoint, &utxo);
+				unhashed::put(&next, &utxo);
+				translated += 1;
+			},
+			None => error!(
+				target: LOG_TARGET,
+				"utxo under {:?} doesn't decode at {:?}, left as is",
+				next,
+				version,
+			),
+		}
+		key = next;
+	}
+	if translated < entries {
+		let left = entries - translated;
+		error!(target: LOG_TARGET, "{} of {} utxos weren't translated", left, entries);
+	}
+	UtxoCommitment::put(commitment);
+
+	T::DbWeight::get().reads_writes(entries.saturating_mul(2), translated.saturating_add(1))
+}
+
+/// v0 to v1: introduces `StorageVersion`. Outputs stored before assets were introduced hold the
+/// native asset and no unique token, and the indexes the UTXO set had no storage for yet are
+/// built from it.
+pub mod v1 {
+	use super::*;
+	use codec::Encode;
+	use crate::{NATIVE_ASSET, OwnedUtxos, RewardTotal, TotalSupply};
+
+	/// An output as stored at v0.
+	#[derive(Encode, Decode)]
+	pub struct OldTransactionOutput {
+		pub value: Value,
+		pub pubkey: H256,
+	}
+
+	impl From<OldTransactionOutput> for TransactionOutput {
+		fn from(old: OldTransactionOutput) -> Self {
+			TransactionOutput {
+				value: old.value,
+				pubkey: old.pubkey,
+				asset_id: NATIVE_ASSET,
+				unique: None,
+				..Default::default()
+			}
+		}
+	}
+
+	/// Index every entry of `UtxoStore` under its owner in `OwnedUtxos` and put its native value
+	/// along with the undistributed `RewardTotal` into `TotalSupply`, returning the weight used.
+	pub fn index_utxos<T: Config>() -> Weight {
+		let mut entries: u64 = 0;
+		let mut native: Value = RewardTotal::get();
+		for (outpoint, utxo) in UtxoStore::iter() {
+			entries += 1;
+			OwnedUtxos::insert(utxo.pubkey, outpoint, ());
+			if utxo.asset_id == NATIVE_ASSET {
+				native = native.saturating_add(utxo.value);
+			}
+		}
+		TotalSupply::put(native);
+
+		T::DbWeight::get().reads_writes(entries.saturating_add(1), entries.saturating_add(1))
+	}
+
+	/// The state `post_upgrade` compares against, read in the v0 layout.
+	pub fn pre_upgrade() -> Result<(u32, Value), &'static str> {
+		if StorageVersion::get() != Releases::V0 {
+			return Err("storage is not at v0");
+		}
+
+		Ok(summary_at(Releases::V0))
+	}
+
+	pub fn post_upgrade(before: (u32, Value)) -> Result<(), &'static str> {
+		// the outputs are translated to the later layouts on top
+		if StorageVersion::get() < Releases::V1 {
+			return Err("storage is not at v1");
+		}
+		if utxo_set_summary() != before {
+			return Err("the utxo set changed");
+		}
+
+		Ok(())
+	}
+}
+
+/// v2 to v3: introduces `UtxoCount`, counted from the UTXO set.
+pub mod v3 {
+	use super::*;
+
+	/// Count the entries of `UtxoStore` into `UtxoCount`, returning the weight used.
+	pub fn count_utxos<T: Config>() -> Weight {
+		let count = UtxoStore::iter_keys().count() as u32;
+		UtxoCount::put(count);
+
+		T::DbWeight::get().reads_writes(count as Weight, 1)
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let weight = count_utxos::<T>();
+		StorageVersion::put(Releases::V3);
+
+		weight.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	/// The state `post_upgrade` compares against.
+	pub fn pre_upgrade() -> Result<(u32, Value), &'static str> {
+		if StorageVersion::get() != Releases::V2 {
+			return Err("storage is not at v2");
+		}
+
+		Ok(summary_at(Releases::V2))
+	}
+
+	pub fn post_upgrade(before: (u32, Value)) -> Result<(), &'static str> {
+		if StorageVersion::get() < Releases::V3 {
+			return Err("storage is not at v3");
+		}
+		// the outputs may not be translated yet
+		if summary_at(StorageVersion::get()) != before {
+			return Err("the utxo set changed");
+		}
+		if UtxoCount::get() != before.0 {
+			return Err("the utxo count doesn't match the utxo set");
+		}
+
+		Ok(())
+	}
+}
+
+/// v3 to v4: `UtxoStore` keys are hashed with `blake2_128_concat` instead of `identity`. Outpoints
+/// stay the same, only where they are stored in the trie changes.
+pub mod v4 {
+	use super::*;
+
+	/// Where `outpoint` was stored under the `identity` hasher.
+	pub fn old_key(outpoint: &H256) -> Vec<u8> {
+		let mut key = UtxoStore::final_prefix().to_vec();
+		key.extend_from_slice(outpoint.as_bytes());
+		key
+	}
+
+	// the `(outpoint, raw value)` pairs still stored under the old hasher. Their keys are the
+	// only ones ending in exactly an outpoint after the prefix, the new ones add 16 bytes of hash
+	fn old_entries() -> (u64, Vec<(H256, Vec<u8>)>) {
+		let prefix = UtxoStore::final_prefix();
+		let mut scanned: u64 = 0;
+		let mut entries = Vec::new();
+
+		let mut key = prefix.to_vec();
+		while let Some(next) = crate::next_utxo_key(&key) {
+			scanned += 1;
+			if next.len() == prefix.len() + 32 {
+				if let Some(value) = unhashed::get_raw(&next) {
+					entries.push((H256::from_slice(&next[prefix.len()..]), value));
+				}
+			}
+			key = next;
+		}
+
+		(scanned, entries)
+	}
+
+	/// Move every entry of `UtxoStore` still stored under the old hasher to its current key,
+	/// returning the weight used. The values are moved as they are, whatever their layout.
+	pub fn rehash_keys<T: Config>() -> Weight {
+		let (scanned, entries) = old_entries();
+		let moved = entries.len() as u64;
+
+		for (outpoint, value) in entries {
+			unhashed::kill(&old_key(&outpoint));
+			unhashed::put_raw(&UtxoStore::hashed_key_for(outpoint), &value);
+		}
+
+		T::DbWeight::get().reads_writes(scanned, moved.saturating_mul(2))
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let weight = rehash_keys::<T>();
+		StorageVersion::put(Releases::V4);
+
+		weight.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	/// The state `post_upgrade` compares against, read under the old hasher.
+	pub fn pre_upgrade() -> Result<(u32, Value), &'static str> {
+		if StorageVersion::get() != Releases::V3 {
+			return Err("storage is not at v3");
+		}
+
+		Ok(summary_at(Releases::V3))
+	}
+
+	pub fn post_upgrade(before: (u32, Value)) -> Result<(), &'static str> {
+		// the outputs are translated to the later layouts on top
+		if StorageVersion::get() < Releases::V4 {
+			return Err("storage is not at v4");
+		}
+		if !old_entries().1.is_empty() {
+			return Err("utxos are left under the old hasher");
+		}
+		if utxo_set_summary() != before {
+			return Err("the utxo set changed");
+		}
+
+		Ok(())
+	}
+}
+
+/// v11 to v12: the keys of `UtxoCreatedAt`, `CoinbaseUtxos` and `FrozenUtxos` are hashed with
+/// `blake2_128_concat` instead of `identity`, as the `UtxoStore` keys they follow are since v4.
+pub mod v12 {
+	use super::*;
+	use crate::{CoinbaseUtxos, FrozenUtxos, UtxoCreatedAt};
+
+	/// The prefixes of the maps keyed by outpoints under the `identity` hasher at v11, along with
+	/// the current key of an outpoint in each.
+	pub fn maps<T: Config>() -> [([u8; 32], fn(H256) -> Vec<u8>); 3] {
+		[
+			(
+				UtxoCreatedAt::<T>::final_prefix(),
+				|outpoint| UtxoCreatedAt::<T>::hashed_key_for(outpoint),
+			),
+			(CoinbaseUtxos::final_prefix(), |outpoint| CoinbaseUtxos::hashed_key_for(outpoint)),
+			(
+				FrozenUtxos::<T>::final_prefix(),
+				|outpoint| FrozenUtxos::<T>::hashed_key_for(outpoint),
+			),
+		]
+	}
+
+	/// Where `outpoint` was stored under the `identity` hasher in the map of `prefix`.
+	pub fn old_key(prefix: [u8; 32], outpoint: &H256) -> Vec<u8> {
+		let mut key = prefix.to_vec();
+		key.extend_from_slice(outpoint.as_bytes());
+		key
+	}
+
+	fn next_key_under(prefix: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+		sp_io::storage::next_key(key).filter(|next| next.starts_with(prefix))
+	}
+
+	// the keys scanned under `prefix` and the `(outpoint, raw value)` pairs still stored under
+	// the old hasher among them, told apart by their length as in v4
+	fn old_entries(prefix: [u8; 32]) -> (u64, Vec<(H256, Vec<u8>)>) {
+		let mut scanned: u64 = 0;
+		let mut entries = Vec::new();
+
+		let mut key = prefix.to_vec();
+		while let Some(next) = next_key_under(&prefix, &key) {
+			scanned += 1;
+			if next.len() == prefix.len() + 32 {
+				if let Some(value) = unhashed::get_raw(&next) {
+					entries.push((H256::from_slice(&next[prefix.len()..]), value));
+				}
+			}
+			key = next;
+		}
+
+		(scanned, entries)
+	}
+
+	/// Move every entry of the three maps still stored under the old hasher to its current key,
+	/// returning the weight used.
+	pub fn rehash_keys<T: Config>() -> Weight {
+		let mut scanned: u64 = 0;
+		let mut moved: u64 = 0;
+
+		for (prefix, new_key) in maps::<T>().iter() {
+			let (keys, entries) = old_entries(*prefix);
+			scanned = scanned.saturating_add(keys);
+			moved = moved.saturating_add(entries.len() as u64);
+
+			for (outpoint, value) in entries {
+				unhashed::kill(&old_key(*prefix, &outpoint));
+				unhashed::put_raw(&new_key(outpoint), &value);
+			}
+		}
+
+		T::DbWeight::get().reads_writes(scanned, moved.saturating_mul(2))
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let weight = rehash_keys::<T>();
+		StorageVersion::put(Releases::V12);
+
+		weight.saturating_add(T::DbWeight::get().writes(1))
+	}
+
+	/// The number of entries of the three maps, which `post_upgrade` compares against.
+	pub fn pre_upgrade<T: Config>() -> Result<u64, &'static str> {
+		if StorageVersion::get() != Releases::V11 {
+			return Err("storage is not at v11");
+		}
+
+		Ok(maps::<T>().iter().map(|(prefix, _)| old_entries(*prefix).0).sum())
+	}
+
+	pub fn post_upgrade<T: Config>(before: u64) -> Result<(), &'static str> {
+		if StorageVersion::get() < Releases::V12 {
+			return Err("storage is not at v12");
+		}
+		let mut entries: u64 = 0;
+		for (prefix, _) in maps::<T>().iter() {
+			let (keys, old) = old_entries(*prefix);
+			if !old.is_empty() {
+				return Err("outpoints are left under the old hasher");
+			}
+			entries += keys;
+		}
+		if entries != before {
+			return Err("entries were lost while rehashing");
+		}
+
+		Ok(())
+	}
+}
@@ -1,10 +1,18 @@
 use crate as pallet_utxo;
-use frame_support::parameter_types;
+use codec::Decode;
+use crate::{
+	issuance::{HalvingIssuance, Issuance, LinearDecayIssuance},
+	signature::{BatchedSr25519Verifier, Ed25519Verifier, SignatureVerifier, Sr25519Verifier},
+	TransactionOutput,
+};
+use frame_support::{parameter_types, traits::{FindAuthor, Get, OnFinalize, OnInitialize}};
+use std::cell::RefCell;
 use frame_system as system;
-use sp_core::H256;
+use sp_core::{sr25519, Pair, H256, H512};
 use sp_runtime::{
+	ConsensusEngineId, FixedPointNumber, FixedU128, Perbill,
 	testing::Header,
-	traits::{BlakeTwo256, IdentityLookup},
+	traits::{BlakeTwo256, Convert, Hash, IdentityLookup},
 };
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
@@ -18,7 +26,7 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
-		UtxoModule: pallet_utxo::{Module, Call, Storage, Event},
+		UtxoModule: pallet_utxo::{Module, Call, Storage, Config, Event, ValidateUnsigned},
 	}
 );
 
@@ -52,14 +60,387 @@ impl system::Config for Test {
 	type SS58Prefix = SS58Prefix;
 }
 
+/// The engine of the pre-runtime digests `TestAuthor` reads, not one of the real ones.
+pub const TEST_ENGINE_ID: ConsensusEngineId = *b"test";
+
+// Blocks are authored by the key in the `TEST_ENGINE_ID` pre-runtime digest, by the validator key
+// without one
+pub struct TestAuthor;
+
+impl FindAuthor<sr25519::Public> for TestAuthor {
+	fn find_author<'a, I>(digests: I) -> Option<sr25519::Public>
+	where
+		I: 'a + IntoIterator<Item = (ConsensusEngineId, &'a [u8])>,
+	{
+		let claimed = digests.into_iter().find_map(|(id, mut data)| match id {
+			TEST_ENGINE_ID => sr25519::Public::decode(&mut data).ok(),
+			_ => None,
+		});
+
+		Some(claimed.unwrap_or_else(|| validator().public()))
+	}
+}
+
+// Declares a `Get` implementation backed by a thread local, so tests can change it with `set`
+macro_rules! test_parameter {
+	($name:ident: $type:ty = $default:expr) => {
+		pub struct $name;
+
+		impl $name {
+			fn value() -> &'static std::thread::LocalKey<RefCell<$type>> {
+				thread_local! {
+					static VALUE: RefCell<$type> = RefCell::new($default);
+				}
+				&VALUE
+			}
+
+			pub fn set(value: $type) {
+				Self::value().with(|v| *v.borrow_mut() = value);
+			}
+		}
+
+		impl Get<$type> for $name {
+			fn get() -> $type {
+				Self::value().with(|v| v.borrow().clone())
+			}
+		}
+	};
+}
+
+test_parameter!(TreasuryShare: Perbill = Perbill::zero());
+test_parameter!(UseEd25519: bool = false);
+test_parameter!(EmitRejections: bool = false);
+test_parameter!(BlockSubsidy: u128 = 0);
+test_parameter!(SubsidyHalvingInterval: u32 = 0);
+test_parameter!(SubsidyDecayPerBlock: u128 = 0);
+test_parameter!(MaxSupply: u128 = u128::max_value());
+test_parameter!(CoinbaseMaturity: u64 = 0);
+test_parameter!(SameBlockSpendForbidden: bool = false);
+test_parameter!(MaxUtxoSetSize: u32 = u32::max_value());
+test_parameter!(BurnFees: bool = false);
+test_parameter!(RestrictedMode: bool = false);
+test_parameter!(MaxSpendsPerWindow: u32 = u32::max_value());
+test_parameter!(MaxTxBytes: u32 = u32::max_value());
+test_parameter!(SweepExpired: bool = false);
+test_parameter!(BatchVerify: bool = false);
+test_parameter!(OutputDeposit: u128 = 0);
+test_parameter!(WeightPrice: FixedU128 = FixedU128::saturating_from_rational(1, 100_000_000));
+
+type SubsidyDecay = LinearDecayIssuance<BlockSubsidy, SubsidyDecayPerBlock, ()>;
+
+// Every block is subsidized with `BlockSubsidy`, decaying by `SubsidyDecayPerBlock` down to
+// nothing unless that is zero, or else halved every `SubsidyHalvingInterval` blocks unless that
+// is zero
+impl Issuance<u64, u128> for BlockSubsidy {
+	fn issuance(block: u64) -> u128 {
+		if SubsidyDecayPerBlock::get() > 0 {
+			return SubsidyDecay::issuance(block);
+		}
+		match SubsidyHalvingInterval::get() {
+			0 => Self::get(),
+			_ => HalvingIssuance::<BlockSubsidy, SubsidyHalvingInterval>::issuance(block),
+		}
+	}
+
+	fn next_change(block: u64) -> Option<u64> {
+		if SubsidyDecayPerBlock::get() > 0 {
+			return SubsidyDecay::next_change(block);
+		}
+		match SubsidyHalvingInterval::get() {
+			0 => None,
+			_ => HalvingIssuance::<BlockSubsidy, SubsidyHalvingInterval>::next_change(block),
+		}
+	}
+}
+
+pub const ALICE: u64 = 1;
+pub const BOB: u64 = 2;
+
+// Accounts of the mock runtime are plain numbers, map the known ones to their keys
+pub struct AccountPubkey;
+
+impl Convert<u64, H256> for AccountPubkey {
+	fn convert(who: u64) -> H256 {
+		match who {
+			ALICE => pubkey_of(&alice()),
+			BOB => pubkey_of(&bob()),
+			_ => H256::from_low_u64_be(who),
+		}
+	}
+}
+
+// Verifies sr25519 signatures unless `UseEd25519` is set, in batches if `BatchVerify` is set
+pub struct TestVerifier;
+
+impl SignatureVerifier for TestVerifier {
+	fn verify(sig: &H512, msg: &[u8], pubkey: &H256) -> bool {
+		if UseEd25519::get() {
+			Ed25519Verifier::verify(sig, msg, pubkey)
+		} else {
+			Sr25519Verifier::verify(sig, msg, pubkey)
+		}
+	}
+
+	fn verify_all(signatures: &[(H512, H256)], msg: &[u8]) -> bool {
+		if UseEd25519::get() {
+			Ed25519Verifier::verify_all(signatures, msg)
+		} else if BatchVerify::get() {
+			BatchedSr25519Verifier::verify_all(signatures, msg)
+		} else {
+			Sr25519Verifier::verify_all(signatures, msg)
+		}
+	}
+}
+
+parameter_types! {
+	pub const TreasuryPubkey: H256 = H256([7u8; 32]);
+	pub const MinimumFee: u128 = 1;
+	pub const MaxTxInputs: u32 = 3;
+	pub const MaxWitnessBytes: u32 = 128;
+	pub const MaxOutputsPerBlock: u32 = 10;
+	pub const AutoChangeFee: u128 = 2;
+	pub const BaseFeeRate: u128 = 0;
+	pub const TargetBlockFullness: Perbill = Perbill::from_percent(50);
+	pub const PoolLongevity: u64 = 64;
+	pub const SpendWindow: u64 = 10;
+	pub const TxIndexRetention: u64 = 3;
+	pub const ConsolidationFeeMultiplier: Perbill = Perbill::from_percent(25);
+	pub const FeeHistoryLength: u32 = 5;
+	pub const MaxMemoBytes: u32 = 32;
+}
+
 impl pallet_utxo::Config for Test {
 	type Event = Event;
+	type AuthorityId = sr25519::Public;
+	type FindAuthor = TestAuthor;
+	type TreasuryShare = TreasuryShare;
+	type TreasuryPubkey = TreasuryPubkey;
+	type AccountPubkey = AccountPubkey;
+	type MinimumFee = MinimumFee;
+	type MaxTxInputs = MaxTxInputs;
+	type SignatureVerifier = TestVerifier;
+	type MaxWitnessBytes = MaxWitnessBytes;
+	type MaxOutputsPerBlock = MaxOutputsPerBlock;
+	type AutoChangeFee = AutoChangeFee;
+	type Issuance = BlockSubsidy;
+	type MaxSupply = MaxSupply;
+	type WeightInfo = ();
+	type BaseFeeRate = BaseFeeRate;
+	type TargetBlockFullness = TargetBlockFullness;
+	type EmitRejections = EmitRejections;
+	type CoinbaseMaturity = CoinbaseMaturity;
+	type SameBlockSpendForbidden = SameBlockSpendForbidden;
+	type PoolLongevity = PoolLongevity;
+	type BurnFees = BurnFees;
+	type MaxUtxoSetSize = MaxUtxoSetSize;
+	type RestrictedMode = RestrictedMode;
+	type MaxSpendsPerWindow = MaxSpendsPerWindow;
+	type SpendWindow = SpendWindow;
+	type TxIndexRetention = TxIndexRetention;
+	type WeightPrice = WeightPrice;
+	type MaxTxBytes = MaxTxBytes;
+	type ConsolidationFeeMultiplier = ConsolidationFeeMultiplier;
+	type SweepExpired = SweepExpired;
+	type FeeHistoryLength = FeeHistoryLength;
+	type MaxMemoBytes = MaxMemoBytes;
+	type OutputDeposit = OutputDeposit;
+	type AuthorityToOwner = pallet_utxo::AuthorityKeyOwner;
+}
+
+pub const GENESIS_UTXO_VALUE: u128 = 100;
+// the genesis hash of every test chain, input signatures are made for it
+pub const GENESIS_HASH: H256 = H256([42u8; 32]);
+// alice issues it
+pub const TEST_ASSET: u32 = 1;
+
+pub fn alice() -> sr25519::Pair {
+	sr25519::Pair::from_string("//Alice", None).expect("static values are valid; qed")
+}
+
+pub fn bob() -> sr25519::Pair {
+	sr25519::Pair::from_string("//Bob", None).expect("static values are valid; qed")
+}
+
+pub fn validator() -> sr25519::Pair {
+	sr25519::Pair::from_string("//Validator", None).expect("static values are valid; qed")
+}
+
+pub fn pubkey_of(pair: &sr25519::Pair) -> H256 {
+	H256::from(pair.public().0)
+}
+
+pub fn genesis_utxo() -> TransactionOutput {
+	TransactionOutput {
+		value: GENESIS_UTXO_VALUE,
+		pubkey: pubkey_of(&alice()),
+		..Default::default()
+	}
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default()
+	ext_with_genesis(pallet_utxo::GenesisConfig {
+		genesis_utxos: vec![genesis_utxo()],
+		asset_issuers: vec![(TEST_ASSET, pubkey_of(&alice()))],
+		..Default::default()
+	})
+}
+
+pub fn ext_with_genesis(genesis: pallet_utxo::GenesisConfig) -> sp_io::TestExternalities {
+	let mut storage = system::GenesisConfig::default()
 		.build_storage::<Test>()
-		.unwrap()
-		.into()
+		.unwrap();
+
+	genesis.assimilate_storage::<Test>(&mut storage).unwrap();
+
+	let mut ext: sp_io::TestExternalities = storage.into();
+	// events are only recorded from block 1 onwards
+	ext.execute_with(|| {
+		frame_system::BlockHash::<Test>::insert(0, GENESIS_HASH);
+		System::set_block_number(1);
+		UtxoModule::on_initialize(1);
+	});
+	ext
+}
+
+// Batch verification runs on a task executor
+pub fn batch_verify_ext() -> sp_io::TestExternalities {
+	use sp_core::{testing::TaskExecutor, traits::TaskExecutorExt};
+
+	let mut ext = new_test_ext();
+	ext.register_extension(TaskExecutorExt::new(TaskExecutor::new()));
+	ext
+}
+
+// The benchmarks sign with keys generated in the keystore
+#[cfg(feature = "runtime-benchmarks")]
+pub fn new_benchmark_ext() -> sp_io::TestExternalities {
+	use sp_keystore::{testing::KeyStore, KeystoreExt};
+
+	let mut ext = new_test_ext();
+	ext.register_extension(KeystoreExt(std::sync::Arc::new(KeyStore::new())));
+	ext
+}
+
+// Finalize the current block and move on to the next one
+pub fn next_block() {
+	let block = System::block_number();
+	UtxoModule::on_finalize(block);
+	System::set_block_number(block + 1);
+	// blocks start out empty
+	System::set_block_limits(0, 0);
+	UtxoModule::on_initialize(block + 1);
+}
+
+// The rewards of an author go to the hash of its key, as if it were locked behind a script
+pub struct HashedAuthority;
+
+impl Convert<sr25519::Public, H256> for HashedAuthority {
+	fn convert(author: sr25519::Public) -> H256 {
+		BlakeTwo256::hash_of(&(b"authority", author))
+	}
+}
+
+/// A second mock runtime, the same as [`Test`] but for the owner of the rewards of authors,
+/// mapped with [`HashedAuthority`].
+pub mod hashed_owner {
+	use super::*;
+
+	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<HashedOwnerTest>;
+	type Block = frame_system::mocking::MockBlock<HashedOwnerTest>;
+
+	frame_support::construct_runtime!(
+		pub enum HashedOwnerTest where
+			Block = Block,
+			NodeBlock = Block,
+			UncheckedExtrinsic = UncheckedExtrinsic,
+		{
+			System: frame_system::{Module, Call, Config, Storage, Event<T>},
+			UtxoModule: pallet_utxo::{Module, Call, Storage, Config, Event, ValidateUnsigned},
+		}
+	);
+
+	impl system::Config for HashedOwnerTest {
+		type BaseCallFilter = ();
+		type BlockWeights = ();
+		type BlockLength = ();
+		type DbWeight = ();
+		type Origin = Origin;
+		type Call = Call;
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type Event = Event;
+		type BlockHashCount = BlockHashCount;
+		type Version = ();
+		type PalletInfo = PalletInfo;
+		type AccountData = ();
+		type OnNewAccount = ();
+		type OnKilledAccount = ();
+		type SystemWeightInfo = ();
+		type SS58Prefix = SS58Prefix;
+	}
+
+	impl pallet_utxo::Config for HashedOwnerTest {
+		type Event = Event;
+		type AuthorityId = sr25519::Public;
+		type FindAuthor = TestAuthor;
+		type TreasuryShare = TreasuryShare;
+		type TreasuryPubkey = TreasuryPubkey;
+		type AccountPubkey = AccountPubkey;
+		type MinimumFee = MinimumFee;
+		type MaxTxInputs = MaxTxInputs;
+		type SignatureVerifier = TestVerifier;
+		type MaxWitnessBytes = MaxWitnessBytes;
+		type MaxOutputsPerBlock = MaxOutputsPerBlock;
+		type AutoChangeFee = AutoChangeFee;
+		type Issuance = BlockSubsidy;
+		type MaxSupply = MaxSupply;
+		type WeightInfo = ();
+		type BaseFeeRate = BaseFeeRate;
+		type TargetBlockFullness = TargetBlockFullness;
+		type EmitRejections = EmitRejections;
+		type CoinbaseMaturity = CoinbaseMaturity;
+		type SameBlockSpendForbidden = SameBlockSpendForbidden;
+		type PoolLongevity = PoolLongevity;
+		type BurnFees = BurnFees;
+		type MaxUtxoSetSize = MaxUtxoSetSize;
+		type RestrictedMode = RestrictedMode;
+		type MaxSpendsPerWindow = MaxSpendsPerWindow;
+		type SpendWindow = SpendWindow;
+		type TxIndexRetention = TxIndexRetention;
+		type WeightPrice = WeightPrice;
+		type MaxTxBytes = MaxTxBytes;
+		type ConsolidationFeeMultiplier = ConsolidationFeeMultiplier;
+		type SweepExpired = SweepExpired;
+		type FeeHistoryLength = FeeHistoryLength;
+		type MaxMemoBytes = MaxMemoBytes;
+		type OutputDeposit = OutputDeposit;
+		type AuthorityToOwner = HashedAuthority;
+	}
+
+	// The genesis of `new_test_ext`, built for this runtime
+	pub fn new_test_ext() -> sp_io::TestExternalities {
+		let mut storage = system::GenesisConfig::default()
+			.build_storage::<HashedOwnerTest>()
+			.unwrap();
+
+		pallet_utxo::GenesisConfig {
+			genesis_utxos: vec![genesis_utxo()],
+			asset_issuers: vec![(TEST_ASSET, pubkey_of(&alice()))],
+			..Default::default()
+		}.assimilate_storage::<HashedOwnerTest>(&mut storage).unwrap();
+
+		let mut ext: sp_io::TestExternalities = storage.into();
+		ext.execute_with(|| {
+			frame_system::BlockHash::<HashedOwnerTest>::insert(0, GENESIS_HASH);
+			System::set_block_number(1);
+			UtxoModule::on_initialize(1);
+		});
+		ext
+	}
 }
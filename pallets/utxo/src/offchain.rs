@@ -0,0 +1,96 @@
+//! Offchain index of the outpoints owned by each pubkey.
+//!
+//! The offchain worker keeps an owner -> outpoints index in the node's persistent offchain
+//! storage so wallets can look up their coins through the `utxo_ownedBy` RPC without the chain
+//! having to pay for the index in state. The index follows the best chain: every block that builds
+//! on the last indexed one is applied as a diff, anything else (a reorg, or blocks the worker never
+//! saw) triggers a rebuild from the UTXO set of the new block.
+
+use codec::Encode;
+use frame_support::dispatch::Vec;
+use sp_core::H256;
+use sp_runtime::offchain::storage::StorageValueRef;
+use sp_std::collections::btree_map::BTreeMap;
+
+use crate::{Config, CreatedThisBlock, Module, SpentThisBlock, UtxoStore};
+
+/// Prefix of the keys under which the outpoints owned by a pubkey are stored.
+pub const OWNED_PREFIX: &[u8] = b"utxo::owned::";
+// every pubkey that has an entry in the index, needed to clear it before a rebuild
+const OWNERS_KEY: &[u8] = b"utxo::owners";
+// hash of the last block applied to the index
+const TIP_KEY: &[u8] = b"utxo::tip";
+
+/// The persistent offchain storage key holding the SCALE encoded `Vec<H256>` of outpoints owned
+/// by `pubkey`.
+pub fn owned_key(pubkey: &H256) -> Vec<u8> {
+	(OWNED_PREFIX, pubkey).encode()
+}
+
+fn owned(pubkey: &H256) -> Vec<H256> {
+	StorageValueRef::persistent(&owned_key(pubkey)).get().flatten().unwrap_or_default()
+}
+
+fn set_owned(pubkey: &H256, outpoints: &Vec<H256>) {
+	StorageValueRef::persistent(&owned_key(pubkey)).set(outpoints);
+}
+
+impl<T: Config> Module<T> {
+	pub(crate) fn index_owned_utxos(block: T::BlockNumber) {
+		let tip = StorageValueRef::persistent(TIP_KEY);
+		let parent = <frame_system::Module<T>>::parent_hash();
+
+		if tip.get::<T::Hash>().flatten() == Some(parent) {
+			Self::apply_block_to_index();
+		} else {
+			Self::rebuild_index();
+		}
+
+		tip.set(&<frame_system::Module<T>>::block_hash(block));
+	}
+
+	fn apply_block_to_index() {
+		let owners = StorageValueRef::persistent(OWNERS_KEY);
+		let mut known_owners: Vec<H256> = owners.get().flatten().unwrap_or_default();
+
+		// outputs that were already spent within the block are no longer in the store
+		for outpoint in CreatedThisBlock::get() {
+			if let Some(utxo) = UtxoStore::get(&outpoint) {
+				let mut outpoints = owned(&utxo.pubkey);
+				outpoints.push(outpoint);
+				set_owned(&utxo.pubkey, &outpoints);
+
+				if !known_owners.contains(&utxo.pubkey) {
+					known_owners.push(utxo.pubkey);
+				}
+			}
+		}
+
+		for (outpoint, owner) in SpentThisBlock::get() {
+			let mut outpoints = owned(&owner);
+			outpoints.retain(|o| *o != outpoint);
+			set_owned(&owner, &outpoints);
+		}
+
+		owners.set(&known_owners);
+	}
+
+	fn rebuild_index() {
+		let owners = StorageValueRef::persistent(OWNERS_KEY);
+
+		for owner in owners.get::<Vec<H256>>().flatten().unwrap_or_default() {
+			set_owned(&owner, &Vec::new());
+		}
+
+		let mut index = BTreeMap::<H256, Vec<H256>>::new();
+		for (outpoint, utxo) in UtxoStore::iter() {
+			index.entry(utxo.pubkey).or_default().push(outpoint);
+		}
+
+		for (owner, outpoints) in index.iter() {
+			set_owned(owner, outpoints);
+		}
+
+		owners.set(&index.keys().collect::<Vec<_>>());
+	}
+}
@@ -0,0 +1,181 @@
+//! Partially signed transactions, for passing a transaction between the parties that have to sign
+//! it.
+//!
+//! A [`PartialTransaction`] carries the unsigned transaction together with the output each input
+//! spends, the keys that have to sign it and the signatures collected so far. Every signer adds
+//! their signatures to their own copy, the copies are merged and the result is finalized into a
+//! transaction that can be submitted with `spend`.
+
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use sp_core::{sr25519, Pair, H256, H512};
+
+use crate::{signing_payload, Transaction, TransactionOutput};
+
+/// What is known about an input of a [`PartialTransaction`].
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Encode, Decode, Debug)]
+pub struct PartialInput {
+	/// The output the input spends.
+	pub utxo: TransactionOutput,
+	/// The keys that have to sign the input.
+	pub required_keys: Vec<H256>,
+	/// The signatures collected so far, by key.
+	pub signatures: Vec<(H256, H512)>,
+}
+
+impl PartialInput {
+	/// An input spending `utxo`, to be signed by its owner and its cosigner if it has one.
+	pub fn new(utxo: TransactionOutput) -> Self {
+		Self {
+			required_keys: Some(utxo.pubkey).into_iter().chain(utxo.cosigner).collect(),
+			utxo,
+			signatures: Vec::new(),
+		}
+	}
+
+	fn signature_of(&self, pubkey: &H256) -> Option<H512> {
+		self.signatures.iter().find(|(key, _)| key == pubkey).map(|(_, sig)| *sig)
+	}
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Encode, Decode, Debug)]
+pub struct PartialTransaction {
+	/// The transaction being signed, with zeroed sigscripts.
+	pub tx: Transaction,
+	/// Metadata for each input of `tx`, in the same order.
+	pub inputs: Vec<PartialInput>,
+	/// The genesis hash of the chain `tx` is signed for.
+	pub genesis_hash: H256,
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum PartialError {
+	/// There is no input at the given index.
+	UnknownInput(u32),
+	/// The key isn't required to sign the input at the given index.
+	UnexpectedSigner(u32, H256),
+	/// The transactions being merged differ.
+	Mismatch,
+}
+
+/// A signature is still missing when finalizing.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct IncompleteError {
+	/// The index of the input lacking a signature.
+	pub input: u32,
+	/// The key whose signature is missing.
+	pub pubkey: H256,
+}
+
+impl PartialTransaction {
+	/// Start signing `tx` for the chain with genesis hash `genesis_hash`, its inputs spending
+	/// `utxos` in order.
+	pub fn new(tx: Transaction, utxos: Vec<TransactionOutput>, genesis_hash: H256) -> Self {
+		let mut tx = tx;
+		for input in tx.inputs.iter_mut() {
+			input.sigscript = H512::zero();
+		}
+
+		Self {
+			tx,
+			inputs: utxos.into_iter().map(PartialInput::new).collect(),
+			genesis_hash,
+		}
+	}
+
+	/// The payload every signature has to cover.
+	pub fn signing_payload(&self) -> Vec<u8> {
+		signing_payload(&self.tx, &self.genesis_hash)
+	}
+
+	/// The payload the signatures of the input at `input_index` have to cover, for signers that
+	/// sign outside of this module. It is the same for every input.
+	pub fn sighash(&self, input_index: u32) -> Result<Vec<u8>, PartialError> {
+		if input_index as usize >= self.inputs.len() {
+			return Err(PartialError::UnknownInput(input_index));
+		}
+
+		Ok(self.signing_payload())
+	}
+
+	/// Record `sig` as the signature of `pubkey` over the input at `input_index`. The signature
+	/// itself is only checked on-chain.
+	pub fn add_signature(&mut self, input_index: u32, pubkey: H256, sig: H512) -> Result<(), PartialError> {
+		let input = self.inputs
+			.get_mut(input_index as usize)
+			.ok_or(PartialError::UnknownInput(input_index))?;
+
+		if !input.required_keys.contains(&pubkey) {
+			return Err(PartialError::UnexpectedSigner(input_index, pubkey));
+		}
+
+		input.signatures.retain(|(key, _)| *key != pubkey);
+		input.signatures.push((pubkey, sig));
+
+		Ok(())
+	}
+
+	/// Sign every input `pair` is required to sign.
+	pub fn sign(&mut self, pair: &sr25519::Pair) {
+		let pubkey = H256::from(pair.public().0);
+		let sig = H512::from(pair.sign(&self.signing_payload()).0);
+
+		for input in self.inputs.iter_mut().filter(|input| input.required_keys.contains(&pubkey)) {
+			input.signatures.retain(|(key, _)| *key != pubkey);
+			input.signatures.push((pubkey, sig));
+		}
+	}
+
+	/// Add the signatures collected in `other`, a copy of the same transaction.
+	pub fn merge(&mut self, other: &PartialTransaction) -> Result<(), PartialError> {
+		if self.tx != other.tx
+			|| self.genesis_hash != other.genesis_hash
+			|| self.inputs.len() != other.inputs.len()
+		{
+			return Err(PartialError::Mismatch);
+		}
+
+		for (index, input) in other.inputs.iter().enumerate() {
+			for (pubkey, sig) in input.signatures.iter() {
+				self.add_signature(index as u32, *pubkey, *sig)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Whether every required signature has been collected.
+	pub fn is_complete(&self) -> bool {
+		self.missing_signature().is_none()
+	}
+
+	/// Assemble the signed transaction. A sigscript holds a single signature, that of the first
+	/// required key. The inputs spending a cosigned output carry the signatures of the owner and
+	/// the cosigner in their witness instead.
+	pub fn finalize(&self) -> Result<Transaction, IncompleteError> {
+		if let Some(missing) = self.missing_signature() {
+			return Err(missing);
+		}
+
+		let mut tx = self.tx.clone();
+		for (input, partial) in tx.inputs.iter_mut().zip(self.inputs.iter()) {
+			let mut sigs = partial.required_keys.iter().filter_map(|key| partial.signature_of(key));
+			if partial.utxo.cosigner.is_some() {
+				input.witness = sigs.flat_map(|sig| sig.to_fixed_bytes().to_vec()).collect();
+			} else if let Some(sig) = sigs.next() {
+				input.sigscript = sig;
+			}
+		}
+
+		Ok(tx)
+	}
+
+	fn missing_signature(&self) -> Option<IncompleteError> {
+		self.inputs.iter().enumerate().find_map(|(index, input)| {
+			input.required_keys
+				.iter()
+				.find(|key| input.signature_of(key).is_none())
+				.map(|pubkey| IncompleteError { input: index as u32, pubkey: *pubkey })
+		})
+	}
+}
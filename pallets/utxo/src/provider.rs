@@ -0,0 +1,53 @@
+//! Where validation looks the outputs of the UTXO set up, so that transactions can be validated
+//! against other sets than the one in storage, by off-chain tools or tests without a chain.
+
+use sp_core::H256;
+use sp_std::collections::btree_map::BTreeMap;
+
+use crate::{TransactionOutput, UtxoStore};
+
+/// A UTXO set, the outputs spendable by their outpoints.
+pub trait UtxoProvider {
+	/// The output under `outpoint`, if it is part of the set.
+	fn utxo(&self, outpoint: &H256) -> Option<TransactionOutput>;
+
+	/// Whether an output is stored under `outpoint`.
+	fn contains(&self, outpoint: &H256) -> bool {
+		self.utxo(outpoint).is_some()
+	}
+}
+
+/// The UTXO set of the chain, in `UtxoStore`.
+pub struct OnChainUtxos;
+
+impl UtxoProvider for OnChainUtxos {
+	fn utxo(&self, outpoint: &H256) -> Option<TransactionOutput> {
+		UtxoStore::get(outpoint)
+	}
+
+	fn contains(&self, outpoint: &H256) -> bool {
+		UtxoStore::contains_key(outpoint)
+	}
+}
+
+/// An in-memory UTXO set.
+impl UtxoProvider for BTreeMap<H256, TransactionOutput> {
+	fn utxo(&self, outpoint: &H256) -> Option<TransactionOutput> {
+		self.get(outpoint).cloned()
+	}
+
+	fn contains(&self, outpoint: &H256) -> bool {
+		self.contains_key(outpoint)
+	}
+}
+
+/// The set a reference points to, so that wrapping providers can borrow the one they wrap.
+impl<P: UtxoProvider + ?Sized> UtxoProvider for &P {
+	fn utxo(&self, outpoint: &H256) -> Option<TransactionOutput> {
+		(**self).utxo(outpoint)
+	}
+
+	fn contains(&self, outpoint: &H256) -> bool {
+		(**self).contains(outpoint)
+	}
+}
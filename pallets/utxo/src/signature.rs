@@ -0,0 +1,68 @@
+//! Signature schemes the pallet can verify input signatures with.
+
+use sp_core::{ed25519, sr25519, H256, H512};
+
+/// Verifies the signature authorizing the spend of an output owned by `pubkey`.
+pub trait SignatureVerifier {
+	fn verify(sig: &H512, msg: &[u8], pubkey: &H256) -> bool;
+
+	/// Whether every `(sig, pubkey)` pair signs `msg`, one by one unless the scheme can do better.
+	fn verify_all(signatures: &[(H512, H256)], msg: &[u8]) -> bool {
+		signatures.iter().all(|(sig, pubkey)| Self::verify(sig, msg, pubkey))
+	}
+}
+
+/// Outputs are owned by sr25519 keys.
+pub struct Sr25519Verifier;
+
+impl SignatureVerifier for Sr25519Verifier {
+	fn verify(sig: &H512, msg: &[u8], pubkey: &H256) -> bool {
+		sp_io::crypto::sr25519_verify(
+			&sr25519::Signature::from_raw(*sig.as_fixed_bytes()),
+			msg,
+			&sr25519::Public::from_raw(*pubkey.as_fixed_bytes()),
+		)
+	}
+}
+
+/// Outputs are owned by sr25519 keys, and the signatures of a transaction are verified as one
+/// batch. Batching needs the runtime to be executed with a `TaskExecutorExt`, use
+/// `Sr25519Verifier` where that isn't the case.
+pub struct BatchedSr25519Verifier;
+
+impl SignatureVerifier for BatchedSr25519Verifier {
+	fn verify(sig: &H512, msg: &[u8], pubkey: &H256) -> bool {
+		Sr25519Verifier::verify(sig, msg, pubkey)
+	}
+
+	fn verify_all(signatures: &[(H512, H256)], msg: &[u8]) -> bool {
+		// nothing to gain from a batch
+		if signatures.len() < 2 {
+			return signatures.iter().all(|(sig, pubkey)| Self::verify(sig, msg, pubkey));
+		}
+
+		sp_io::crypto::start_batch_verify();
+		let queued = signatures.iter().all(|(sig, pubkey)| sp_io::crypto::sr25519_batch_verify(
+			&sr25519::Signature::from_raw(*sig.as_fixed_bytes()),
+			msg,
+			&sr25519::Public::from_raw(*pubkey.as_fixed_bytes()),
+		));
+		// the batch has to be finished even when queueing failed
+		let verified = sp_io::crypto::finish_batch_verify();
+
+		queued && verified
+	}
+}
+
+/// Outputs are owned by ed25519 keys.
+pub struct Ed25519Verifier;
+
+impl SignatureVerifier for Ed25519Verifier {
+	fn verify(sig: &H512, msg: &[u8], pubkey: &H256) -> bool {
+		sp_io::crypto::ed25519_verify(
+			&ed25519::Signature::from_raw(*sig.as_fixed_bytes()),
+			msg,
+			&ed25519::Public::from_raw(*pubkey.as_fixed_bytes()),
+		)
+	}
+}
@@ -0,0 +1,48 @@
+//! Snapshots of the UTXO set, for starting a new chain with the coins of an existing one.
+//!
+//! [`export`] captures the UTXO set of the current state, [`GenesisConfig::from_snapshot`] turns
+//! a snapshot into the genesis of the new chain. Entries keep their outpoints, so transactions
+//! built against the old chain spend the same outputs on the new one. Snapshots serialize to
+//! JSON through serde and to SCALE through `Encode`.
+
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use sp_runtime::traits::SaturatedConversion;
+
+use crate::{Config, GenesisConfig, Module, RewardTotal, TotalSupply, TransactionOutput, Value};
+
+/// The UTXO set as of a block.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Encode, Decode, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UtxoSnapshot {
+	/// The block the snapshot was taken at.
+	pub block: u64,
+	/// Every unspent output with its outpoint, in storage order.
+	pub entries: Vec<(H256, TransactionOutput)>,
+	/// The native value of `entries`. Fees not yet paid out to a block author are not carried
+	/// over.
+	pub total_supply: Value,
+}
+
+/// Snapshot the UTXO set of the current state, to be called from within the externalities of the
+/// state to export.
+pub fn export<T: Config>() -> UtxoSnapshot {
+	UtxoSnapshot {
+		block: <frame_system::Module<T>>::block_number().saturated_into(),
+		entries: Module::<T>::utxo_iter().collect(),
+		total_supply: TotalSupply::get().saturating_sub(RewardTotal::get()),
+	}
+}
+
+impl GenesisConfig {
+	/// A genesis holding exactly the entries of `snapshot`, under their outpoints. Asset issuers
+	/// are not part of a snapshot and have to be set again.
+	pub fn from_snapshot(snapshot: UtxoSnapshot) -> Self {
+		GenesisConfig {
+			keyed_utxos: snapshot.entries,
+			expected_total_supply: Some(snapshot.total_supply),
+			..Default::default()
+		}
+	}
+}
@@ -1,23 +1,5207 @@
-use crate::{Error, mock::*};
-use frame_support::{assert_ok, assert_noop};
+use crate::{
+	AppliedStatus, AssetId, BlockSummary, DryRunError, NATIVE_ASSET, RewardInfo, UtxoProvider,
+	bloom::{FilteredUtxos, OutpointFilter},
+	filters::{block_filter, header_may_concern, BlockFilter},
+	builder::TxBuilder,
+	coin_select::{select_coins, select_coins_seeded, Selection, Strategy},
+	commitment_leaf, Error, merkle::{verify_utxo_proof, UtxoProof}, mock::*, OwnedUtxos, Transaction,
+	TransactionInput, TransactionOutput, UtxoStore, UTXO_COMMITMENT_ID, WeightInfo,
+	trie_proof::{check_utxo_proof, prove_utxo},
+};
+use codec::{Decode, Encode};
+use proptest::{collection::vec, prelude::*};
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::DispatchResult,
+	storage::with_transaction,
+	traits::{Get, OffchainWorker, OnFinalize, OnInitialize, OnRuntimeUpgrade},
+	weights::GetDispatchInfo,
+};
+use sp_core::{
+	offchain::{testing::TestOffchainExt, OffchainExt},
+	ed25519, sr25519, Pair, H256, H512,
+};
+use sp_runtime::{
+	FixedPointNumber, FixedU128, Perbill, TransactionOutcome,
+	generic::DigestItem,
+	offchain::storage::StorageValueRef,
+	testing::Header,
+	traits::{BlakeTwo256, Convert, Hash, Header as HeaderT, ValidateUnsigned},
+	transaction_validity::{InvalidTransaction, TransactionSource},
+};
+
+fn genesis_outpoint() -> H256 {
+	BlakeTwo256::hash_of(&genesis_utxo())
+}
+
+fn input(outpoint: H256) -> TransactionInput {
+	TransactionInput {
+		outpoint,
+		sigscript: H512::zero(),
+		witness: Vec::new(),
+	}
+}
+
+fn output(value: u128, owner: &sr25519::Pair) -> TransactionOutput {
+	TransactionOutput {
+		value,
+		pubkey: pubkey_of(owner),
+		..Default::default()
+	}
+}
+
+// The signature of `signer` over the inputs of `tx` on the test chain
+fn signature_of(tx: &Transaction, signer: &sr25519::Pair) -> H512 {
+	H512::from(signer.sign(&crate::signing_payload(tx, &GENESIS_HASH)).0)
+}
+
+// Rebuild the transaction with `TxBuilder`, signing every input with the given key
+fn signed(tx: Transaction, owner: &sr25519::Pair) -> Transaction {
+	let mut builder = TxBuilder::new(GENESIS_HASH);
+
+	for input in tx.inputs {
+		builder.add_input(input.outpoint);
+	}
+	for output in tx.outputs {
+		builder.push_output(output);
+	}
+	if let Some(pubkey) = tx.change_to {
+		builder.change_to(pubkey);
+	}
+	if let Some(block) = tx.valid_until {
+		builder.valid_until(block);
+	}
+	if let Some(value) = tx.burn {
+		builder.burn(value);
+	}
+
+	builder.sign_with(owner)
+}
+
+// The outpoint under which the output at `index` of `tx` is stored
+fn outpoint_of(tx: &Transaction, index: u64) -> H256 {
+	BlakeTwo256::hash_of(&(&crate::get_simple_tx(tx), index))
+}
+
+#[test]
+fn genesis_utxos_are_indexed_by_owner() {
+	new_test_ext().execute_with(|| {
+		assert!(UtxoStore::contains_key(genesis_outpoint()));
+		assert!(OwnedUtxos::contains_key(pubkey_of(&alice()), genesis_outpoint()));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), GENESIS_UTXO_VALUE);
+	});
+}
+
+#[test]
+fn balance_of_reflects_the_state_of_each_block() {
+	new_test_ext().execute_with(|| {
+		let alice_key = pubkey_of(&alice());
+		let bob_key = pubkey_of(&bob());
+
+		// block 1: alice pays bob 60 and keeps 40 as change
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(60, &bob()), output(40, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		next_block();
+
+		assert_eq!(UtxoModule::balance_of(&alice_key), 40);
+		assert_eq!(UtxoModule::balance_of(&bob_key), 60);
+
+		// block 2: bob pays 50 back to alice, leaving 10 as fee
+		let tx = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 0))],
+			outputs: vec![output(50, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		next_block();
+
+		assert_eq!(UtxoModule::balance_of(&alice_key), 90);
+		assert_eq!(UtxoModule::balance_of(&bob_key), 0);
+		assert_eq!(OwnedUtxos::iter_prefix(bob_key).count(), 0);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 10);
+	});
+}
+
+// Recompute the utxo set commitment from scratch
+fn reference_commitment() -> H256 {
+	UtxoStore::iter().fold(H256::zero(), |commitment, (outpoint, utxo)| {
+		commitment ^ commitment_leaf(&outpoint, &utxo)
+	})
+}
+
+fn digest_commitment() -> Option<H256> {
+	System::digest().logs.iter().find_map(|log| match log {
+		DigestItem::Other(data) => <([u8; 4], H256)>::decode(&mut &data[..])
+			.ok()
+			.filter(|(id, _)| *id == UTXO_COMMITMENT_ID)
+			.map(|(_, commitment)| commitment),
+		_ => None,
+	})
+}
+
+#[test]
+fn genesis_initializes_the_utxo_commitment() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			UtxoModule::utxo_commitment(),
+			commitment_leaf(&genesis_outpoint(), &genesis_utxo()),
+		);
+	});
+}
+
+#[test]
+fn utxo_commitment_tracks_spends_and_rewards() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(60, &bob()), output(30, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		assert_eq!(UtxoModule::utxo_commitment(), reference_commitment());
+
+		// finalizing adds the reward and logs the commitment to the digest
+		UtxoModule::on_finalize(System::block_number());
+		assert_eq!(UtxoModule::utxo_commitment(), reference_commitment());
+		assert_eq!(digest_commitment(), Some(reference_commitment()));
+		System::set_block_number(2);
+
+		let tx = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 0))],
+			outputs: vec![output(55, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::utxo_commitment(), reference_commitment());
+	});
+}
+
+#[test]
+fn independent_histories_with_the_same_utxo_set_share_a_commitment() {
+	let spend_genesis = || {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(100, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	};
+
+	let mut first = new_test_ext();
+	let mut second = new_test_ext();
+
+	// apply the spend directly in one chain and after an unrelated failed spend in the other
+	let first_commitment = first.execute_with(|| {
+		spend_genesis();
+		UtxoModule::utxo_commitment()
+	});
+	let second_commitment = second.execute_with(|| {
+		let bogus = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(200, &bob())],
+			..Default::default()
+		}, &alice());
+		assert!(UtxoModule::spend(Origin::none(), bogus).is_err());
+		spend_genesis();
+		assert_eq!(UtxoModule::utxo_commitment(), reference_commitment());
+		UtxoModule::utxo_commitment()
+	});
+
+	assert_eq!(first_commitment, second_commitment);
+}
+
+// Spend the genesis utxo leaving `fee` for the block author and finalize the block
+fn spend_genesis_with_fee(fee: u128) {
+	let tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(GENESIS_UTXO_VALUE - fee, &bob())],
+		..Default::default()
+	}, &alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	next_block();
+}
+
+#[test]
+fn fees_are_split_with_the_treasury() {
+	new_test_ext().execute_with(|| {
+		TreasuryShare::set(Perbill::from_percent(50));
+		spend_genesis_with_fee(11);
+
+		// the odd unit left over by rounding goes to the author
+		assert_eq!(UtxoModule::balance_of(&TreasuryPubkey::get()), 5);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 6);
+	});
+}
+
+#[test]
+fn zero_treasury_share_pays_all_fees_to_the_author() {
+	new_test_ext().execute_with(|| {
+		TreasuryShare::set(Perbill::zero());
+		spend_genesis_with_fee(11);
+
+		assert_eq!(UtxoModule::balance_of(&TreasuryPubkey::get()), 0);
+		assert_eq!(OwnedUtxos::iter_prefix(TreasuryPubkey::get()).count(), 0);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 11);
+	});
+}
+
+#[test]
+fn the_treasury_takes_no_share_of_the_subsidy() {
+	new_test_ext().execute_with(|| {
+		TreasuryShare::set(Perbill::from_percent(50));
+		BlockSubsidy::set(10);
+		spend_genesis_with_fee(11);
+
+		// half of the fees, the author gets the other half and the whole subsidy
+		assert_eq!(UtxoModule::balance_of(&TreasuryPubkey::get()), 5);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 16);
+	});
+}
+
+#[test]
+fn authority_to_owner_decides_who_owns_unclaimed_rewards() {
+	use crate::mock::hashed_owner;
+
+	BlockSubsidy::set(10);
+	let hashed = HashedAuthority::convert(validator().public());
+
+	// the authority key owns its rewards itself by default
+	new_test_ext().execute_with(|| {
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 10);
+	});
+
+	hashed_owner::new_test_ext().execute_with(|| {
+		hashed_owner::UtxoModule::on_finalize(1);
+		assert_eq!(hashed_owner::UtxoModule::balance_of(&pubkey_of(&validator())), 0);
+		assert_eq!(hashed_owner::UtxoModule::balance_of(&hashed), 10);
+
+		// a payout preference still comes first
+		crate::PayoutPreference::insert(pubkey_of(&validator()), pubkey_of(&bob()));
+		hashed_owner::System::set_block_number(2);
+		hashed_owner::UtxoModule::on_finalize(2);
+		assert_eq!(hashed_owner::UtxoModule::balance_of(&pubkey_of(&bob())), 10);
+	});
+}
+
+// The outpoint of the last reward paid to the block author so far
+fn last_reward_issued() -> Option<H256> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			Event::pallet_utxo(crate::Event::RewardsIssued(_, outpoint)) => Some(outpoint),
+			_ => None,
+		})
+		.last()
+}
+
+#[test]
+fn reward_info_reports_the_pending_reward_of_each_block() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(UtxoModule::reward_info(), RewardInfo::default());
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(GENESIS_UTXO_VALUE - 11, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::pending_reward(), 11);
+		assert_eq!(UtxoModule::reward_info().last_reward_outpoint, None);
+
+		next_block();
+		let fees_reward = last_reward_issued();
+		assert!(fees_reward.is_some());
+		assert_eq!(UtxoModule::reward_info(), RewardInfo {
+			pending_fees: 0,
+			next_subsidy: 0,
+			last_reward_outpoint: fees_reward,
+		});
+
+		// a block without fees or subsidy pays nothing, the last reward stays where it was
+		next_block();
+		assert_eq!(UtxoModule::reward_info().last_reward_outpoint, fees_reward);
+
+		// a block without fees still pays its subsidy
+		BlockSubsidy::set(40);
+		assert_eq!(UtxoModule::reward_info().next_subsidy, 40);
+		next_block();
+		let subsidy_reward = last_reward_issued();
+		assert_ne!(subsidy_reward, fees_reward);
+		assert_eq!(UtxoModule::reward_info(), RewardInfo {
+			pending_fees: 0,
+			next_subsidy: 40,
+			last_reward_outpoint: subsidy_reward,
+		});
+	});
+}
+
+#[test]
+fn utxo_proof_proves_inclusion_of_a_fresh_output() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(50, &bob()), output(30, &alice()), output(10, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		next_block();
+
+		for index in 0..3 {
+			let proof = UtxoModule::utxo_proof(&outpoint_of(&tx, index)).unwrap();
+			assert!(verify_utxo_proof(&proof.root(), &proof));
+		}
+
+		// a proof doesn't hold up against another root or for another output
+		let proof = UtxoModule::utxo_proof(&outpoint_of(&tx, 0)).unwrap();
+		assert!(!verify_utxo_proof(&H256::repeat_byte(1), &proof));
+
+		let UtxoProof::V1 { outpoint, index, leaves, siblings, root, .. } = proof.clone();
+		let forged = UtxoProof::V1 { outpoint, utxo: output(500, &bob()), index, leaves, siblings, root };
+		assert!(!verify_utxo_proof(&proof.root(), &forged));
+
+		// the proof survives a SCALE round trip
+		assert_eq!(UtxoProof::decode(&mut &proof.encode()[..]).unwrap(), proof);
+	});
+}
+
+#[test]
+fn utxo_proof_is_none_once_spent() {
+	new_test_ext().execute_with(|| {
+		assert!(UtxoModule::utxo_proof(&genesis_outpoint()).is_some());
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(100, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+
+		assert_eq!(UtxoModule::utxo_proof(&genesis_outpoint()), None);
+	});
+}
+
+#[test]
+fn trie_proof_proves_a_utxo_against_the_state_root() {
+	let mut ext = new_test_ext();
+	let tx = ext.execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(60, &bob()), output(40, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		tx
+	});
+
+	let root = *ext.as_backend().root();
+	let outpoint = outpoint_of(&tx, 0);
+	let (utxo, proof) = prove_utxo(ext.as_backend(), &outpoint).unwrap();
+	assert_eq!(utxo, output(60, &bob()));
+	assert_eq!(check_utxo_proof(root, &outpoint, proof.clone()), Some(utxo));
+
+	// the proof holds neither against another root nor for another outpoint
+	assert_eq!(check_utxo_proof(H256::repeat_byte(1), &outpoint, proof.clone()), None);
+	assert_eq!(check_utxo_proof(root, &outpoint_of(&tx, 1), proof), None);
+
+	// the spent genesis output has nothing left to prove
+	assert!(prove_utxo(ext.as_backend(), &genesis_outpoint()).is_none());
+}
+
+fn genesis_declaring(expected_total_supply: Option<u128>) -> crate::GenesisConfig {
+	crate::GenesisConfig {
+		genesis_utxos: vec![genesis_utxo(), output(50, &bob())],
+		expected_total_supply,
+		..Default::default()
+	}
+}
+
+#[test]
+fn genesis_accepts_matching_total_supply() {
+	ext_with_genesis(genesis_declaring(Some(150))).execute_with(|| {
+		assert_eq!(UtxoStore::iter().count(), 2);
+	});
+	ext_with_genesis(genesis_declaring(None)).execute_with(|| {
+		assert_eq!(UtxoStore::iter().count(), 2);
+	});
+}
+
+#[test]
+#[should_panic(expected = "genesis utxos add up to 150 but the declared total supply is 100")]
+fn genesis_rejects_mismatching_total_supply() {
+	ext_with_genesis(genesis_declaring(Some(100)));
+}
+
+#[test]
+#[should_panic(expected = "duplicate genesis utxo")]
+fn genesis_rejects_duplicate_utxos() {
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: vec![genesis_utxo(), genesis_utxo()],
+		..Default::default()
+	});
+}
+
+#[test]
+#[should_panic(expected = "genesis utxo paying the zero key")]
+fn genesis_rejects_utxos_paying_the_zero_key() {
+	let to_nobody = TransactionOutput { pubkey: H256::zero(), ..genesis_utxo() };
+	ext_with_genesis(crate::GenesisConfig {
+		keyed_utxos: vec![(H256::repeat_byte(1), to_nobody)],
+		..Default::default()
+	});
+}
+
+#[test]
+fn genesis_funds_the_treasury_and_the_first_reward() {
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: vec![genesis_utxo()],
+		initial_reward_total: 5,
+		treasury_premine: Some(1_000),
+		..Default::default()
+	}).execute_with(|| {
+		let premine = UtxoStore::get(crate::treasury_premine_outpoint()).unwrap();
+		assert_eq!(premine.value, 1_000);
+		assert_eq!(premine.pubkey, TreasuryPubkey::get());
+		assert_eq!(UtxoModule::total_supply(), GENESIS_UTXO_VALUE + 1_000 + 5);
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 5);
+	});
+}
+
+#[test]
+#[should_panic(expected = "the treasury premine must be nonzero")]
+fn genesis_rejects_an_empty_treasury_premine() {
+	ext_with_genesis(crate::GenesisConfig { treasury_premine: Some(0), ..Default::default() });
+}
+
+// Run the offchain worker for the current block, pretending the block hashes to `hash`
+fn run_offchain_worker(hash: H256) {
+	let block = System::block_number();
+	frame_system::BlockHash::<Test>::insert(block, hash);
+	UtxoModule::offchain_worker(block);
+}
+
+fn offchain_owned(owner: &sr25519::Pair) -> Vec<H256> {
+	let key = crate::offchain::owned_key(&pubkey_of(owner));
+	let mut outpoints: Vec<H256> = StorageValueRef::persistent(&key).get().flatten().unwrap_or_default();
+	outpoints.sort();
+	outpoints
+}
+
+fn sorted(mut outpoints: Vec<H256>) -> Vec<H256> {
+	outpoints.sort();
+	outpoints
+}
+
+#[test]
+fn offchain_worker_indexes_outpoints_by_owner() {
+	let mut ext = new_test_ext();
+	let (offchain, _state) = TestOffchainExt::new();
+	ext.register_extension(OffchainExt::new(offchain));
+
+	ext.execute_with(|| {
+		// block 1 is the first block the worker sees, so the index is built from scratch
+		let tx1 = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(60, &bob()), output(39, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx1.clone()));
+		UtxoModule::on_finalize(1);
+		run_offchain_worker(H256::repeat_byte(1));
+
+		assert_eq!(offchain_owned(&alice()), vec![outpoint_of(&tx1, 1)]);
+		assert_eq!(offchain_owned(&bob()), vec![outpoint_of(&tx1, 0)]);
+
+		// block 2 builds on block 1, so only its changes are applied
+		System::set_block_number(2);
+		System::set_parent_hash(H256::repeat_byte(1));
+		UtxoModule::on_initialize(2);
+
+		let tx2 = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx1, 0))],
+			outputs: vec![output(50, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx2.clone()));
+		UtxoModule::on_finalize(2);
+		run_offchain_worker(H256::repeat_byte(2));
+
+		assert_eq!(offchain_owned(&alice()), sorted(vec![outpoint_of(&tx1, 1), outpoint_of(&tx2, 0)]));
+		assert_eq!(offchain_owned(&bob()), Vec::<H256>::new());
+		assert_eq!(offchain_owned(&validator()).len(), 2);
+	});
+}
+
+#[test]
+fn offchain_worker_rebuilds_the_index_after_a_reorg() {
+	let mut ext = new_test_ext();
+	let (offchain, _state) = TestOffchainExt::new();
+	ext.register_extension(OffchainExt::new(offchain));
+
+	ext.execute_with(|| {
+		run_offchain_worker(H256::repeat_byte(1));
+		assert_eq!(offchain_owned(&alice()), vec![genesis_outpoint()]);
+
+		// a block on another fork moves alice's coins, and doesn't build on the indexed tip
+		System::set_block_number(2);
+		System::set_parent_hash(H256::repeat_byte(9));
+		UtxoModule::on_initialize(2);
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(100, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		run_offchain_worker(H256::repeat_byte(2));
+
+		assert_eq!(offchain_owned(&alice()), Vec::<H256>::new());
+		assert_eq!(offchain_owned(&bob()), vec![outpoint_of(&tx, 0)]);
+	});
+}
+
+// Everything a reorg has to restore: the UTXO set, its owner index, the coinbase outputs, the
+// deposits and the root of the whole state
+#[derive(PartialEq, Debug)]
+struct ChainState {
+	utxos: Vec<(H256, TransactionOutput)>,
+	owned: Vec<(H256, H256)>,
+	coinbase: Vec<H256>,
+	deposits: Vec<(H256, u128)>,
+	root: Vec<u8>,
+}
+
+fn chain_state() -> ChainState {
+	ChainState {
+		utxos: UtxoStore::iter().collect(),
+		owned: OwnedUtxos::iter().map(|(owner, outpoint, ())| (owner, outpoint)).collect(),
+		coinbase: crate::CoinbaseUtxos::iter().map(|(outpoint, ())| outpoint).collect(),
+		deposits: crate::Deposits::iter().collect(),
+		root: sp_io::storage::root(),
+	}
+}
+
+// Apply `blocks` of transactions on top of the current state, finalizing each, and revert them
+// again, the way a node abandons a branch. Returns the state at the tip of the branch
+fn on_branch(blocks: &[Vec<Transaction>]) -> ChainState {
+	with_transaction(|| {
+		for block in blocks {
+			for tx in block {
+				assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+			}
+			next_block();
+		}
+
+		TransactionOutcome::Rollback(chain_state())
+	})
+}
+
+// Alice pays bob in the first block and bob pays her back in the second, each leaving a fee
+fn chained_spends() -> Vec<Vec<Transaction>> {
+	let to_bob = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(GENESIS_UTXO_VALUE - 3, &bob())],
+		..Default::default()
+	}, &alice());
+	let to_alice = signed(Transaction {
+		inputs: vec![input(outpoint_of(&to_bob, 0))],
+		outputs: vec![output(GENESIS_UTXO_VALUE - 5, &alice())],
+		..Default::default()
+	}, &bob());
+
+	vec![vec![to_bob], vec![to_alice]]
+}
+
+// A competing first block paying bob with another fee, followed by an empty one
+fn competing_spends() -> Vec<Vec<Transaction>> {
+	let to_bob = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(GENESIS_UTXO_VALUE - 7, &bob())],
+		..Default::default()
+	}, &alice());
+
+	vec![vec![to_bob], vec![]]
+}
+
+#[test]
+fn reverted_branches_leave_the_fork_point_untouched() {
+	new_test_ext().execute_with(|| {
+		let fork_point = chain_state();
+
+		let tip = on_branch(&chained_spends());
+		assert_ne!(tip, fork_point);
+		assert_eq!(chain_state(), fork_point);
+
+		// reorging to the competing branch and back ends on the very same state
+		assert_ne!(on_branch(&competing_spends()), tip);
+		assert_eq!(chain_state(), fork_point);
+		assert_eq!(on_branch(&chained_spends()), tip);
+	});
+}
+
+#[test]
+fn identical_histories_produce_identical_storage() {
+	BlockSubsidy::set(10);
+	let direct = new_test_ext().execute_with(|| on_branch(&chained_spends()));
+	let after_reorg = new_test_ext().execute_with(|| {
+		on_branch(&competing_spends());
+		on_branch(&chained_spends())
+	});
+
+	assert_eq!(direct, after_reorg);
+	// the author's outputs of both blocks, chained spends included
+	assert_eq!(direct.coinbase.len(), 2);
+	assert_eq!(direct.utxos.len(), 3);
+}
+
+#[test]
+fn reward_outpoints_derive_from_the_block_and_the_reward_alone() {
+	new_test_ext().execute_with(|| {
+		let reward_of = |value: u128| TransactionOutput {
+			value,
+			pubkey: pubkey_of(&validator()),
+			..Default::default()
+		};
+		let tip = on_branch(&chained_spends());
+		let competing = on_branch(&competing_spends());
+
+		// the same height pays another reward on the competing branch, under another outpoint
+		let first = BlakeTwo256::hash_of(&(reward_of(3), 1u64));
+		assert!(tip.coinbase.contains(&first));
+		assert!(!competing.coinbase.contains(&first));
+		assert!(competing.coinbase.contains(&BlakeTwo256::hash_of(&(reward_of(7), 1u64))));
+		assert!(tip.coinbase.contains(&BlakeTwo256::hash_of(&(reward_of(2), 2u64))));
+	});
+}
+
+#[test]
+fn dry_run_matches_the_effects_of_a_real_spend() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(60, &bob()), output(30, &alice())],
+			..Default::default()
+		}, &alice());
+
+		let (spent, created, fee) = UtxoModule::dry_run(&tx).unwrap();
+		// nothing is written by the dry run
+		assert!(UtxoStore::contains_key(genesis_outpoint()));
+		assert_eq!(UtxoModule::reward_total(), 0);
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		assert_eq!(spent, vec![genesis_outpoint()]);
+		assert!(spent.iter().all(|outpoint| !UtxoStore::contains_key(outpoint)));
+		assert_eq!(created.len(), 2);
+		for (outpoint, utxo) in created {
+			assert_eq!(UtxoStore::get(outpoint), Some(utxo));
+		}
+		assert_eq!(fee, 10);
+		assert_eq!(UtxoModule::reward_total(), fee);
+	});
+}
+
+#[test]
+fn dry_run_reports_why_validation_failed() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(160, &bob())],
+			..Default::default()
+		}, &alice());
+
+		assert_eq!(UtxoModule::dry_run(&tx), Err(DryRunError {
+			input_index: None,
+			reason: b"OutputsExceedInputs".to_vec(),
+		}));
+	});
+}
+
+#[test]
+fn dry_run_reports_the_failing_input() {
+	new_test_ext().execute_with(|| {
+		let split = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: (0..5).map(|_| output(19, &alice())).collect(),
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		let outpoints = UtxoModule::output_keys(&split);
+		assert_ok!(UtxoModule::spend(Origin::none(), split));
+
+		let mut tx = signed(Transaction {
+			inputs: outpoints.iter().copied().map(input).collect(),
+			outputs: vec![output(90, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		tx.inputs[3].sigscript = signature_of(&tx, &bob());
+
+		assert_eq!(UtxoModule::dry_run(&tx), Err(DryRunError {
+			input_index: Some(3),
+			reason: b"Signature must be valid".to_vec(),
+		}));
+
+		tx.inputs[1].outpoint = H256::repeat_byte(9);
+		assert_eq!(UtxoModule::dry_run(&tx), Err(DryRunError {
+			input_index: Some(1),
+			reason: b"MissingInput".to_vec(),
+		}));
+	});
+}
+
+#[test]
+fn transfer_of_the_exact_amount_leaves_no_change() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(UtxoModule::transfer(Origin::signed(ALICE), pubkey_of(&bob()), 99));
+
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), 0);
+		assert_eq!(OwnedUtxos::iter_prefix(pubkey_of(&alice())).count(), 0);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 99);
+		assert_eq!(UtxoModule::reward_total(), MinimumFee::get());
+	});
+}
+
+#[test]
+fn transfer_sends_change_back_to_the_sender() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(UtxoModule::transfer(Origin::signed(ALICE), pubkey_of(&bob()), 60));
+
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), 39);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 60);
+	});
+}
+
+#[test]
+fn transfer_fails_without_enough_funds() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			UtxoModule::transfer(Origin::signed(ALICE), pubkey_of(&bob()), 100),
+			Error::<Test>::InsufficientFunds,
+		);
+		assert_noop!(
+			UtxoModule::transfer(Origin::signed(BOB), pubkey_of(&alice()), 1),
+			Error::<Test>::InsufficientFunds,
+		);
+	});
+}
+
+#[test]
+fn transfer_is_bounded_by_max_tx_inputs() {
+	// genesis outpoints are the hash of the output, so give each one a different value
+	let small_utxos = (10..14).map(|value| output(value, &alice())).collect();
+
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: small_utxos,
+		..Default::default()
+	}).execute_with(|| {
+		assert_noop!(
+			UtxoModule::transfer(Origin::signed(ALICE), pubkey_of(&bob()), 40),
+			Error::<Test>::TooManyInputs,
+		);
+		assert_ok!(UtxoModule::transfer(Origin::signed(ALICE), pubkey_of(&bob()), 20));
+	});
+}
+
+#[test]
+fn transfer_passes_over_outputs_it_can_not_spend() {
+	let freeze = |outpoint| assert_ok!(UtxoModule::freeze(Origin::root(), outpoint));
+	let make_immature = |outpoint| {
+		CoinbaseMaturity::set(5);
+		crate::CoinbaseUtxos::insert(outpoint, ());
+	};
+
+	for make_unspendable in [&freeze as &dyn Fn(H256), &make_immature].iter() {
+		ext_with_genesis(crate::GenesisConfig {
+			genesis_utxos: vec![output(50, &alice()), output(51, &alice())],
+			..Default::default()
+		}).execute_with(|| {
+			// the output listed first can't be spent, the one after it covers the transfer
+			let (first, ()) = OwnedUtxos::iter_prefix(pubkey_of(&alice())).next().unwrap();
+			make_unspendable(first);
+
+			assert_ok!(UtxoModule::transfer(Origin::signed(ALICE), pubkey_of(&bob()), 40));
+			assert!(UtxoStore::contains_key(first));
+			assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 40);
+		});
+	}
+}
+
+#[test]
+fn confirmations_count_blocks_since_creation() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(10);
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(100, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		let outpoint = outpoint_of(&tx, 0);
+		assert_eq!(UtxoModule::utxo_created_at(outpoint), Some(10));
+		assert_eq!(UtxoModule::confirmations(&outpoint, 10), Some(1));
+		assert_eq!(UtxoModule::confirmations(&outpoint, 15), Some(6));
+		assert_eq!(UtxoModule::confirmations(&outpoint, 9), None);
+
+		// genesis outputs were created at block 0 and are gone once spent
+		assert_eq!(UtxoModule::confirmations(&genesis_outpoint(), 15), None);
+		assert_eq!(UtxoModule::utxo_created_at(genesis_outpoint()), None);
+	});
+}
+
+fn coins(values: &[u128]) -> Vec<(H256, u128)> {
+	values
+		.iter()
+		.enumerate()
+		.map(|(i, value)| (H256::from_low_u64_be(i as u64), *value))
+		.collect()
+}
+
+fn selected_values(available: &[(H256, u128)], selection: &Selection) -> Vec<u128> {
+	let mut values = selection.outpoints
+		.iter()
+		.map(|outpoint| available.iter().find(|(o, _)| o == outpoint).unwrap().1)
+		.collect::<Vec<_>>();
+	values.sort();
+	values
+}
+
+#[test]
+fn coin_selection_spends_everything_when_the_target_equals_the_total() {
+	let available = coins(&[1, 2, 3]);
+
+	for strategy in [Strategy::LargestFirst, Strategy::SmallestFirst, Strategy::BranchAndBound].iter() {
+		let selection = select_coins(&available, 6, *strategy).unwrap();
+		assert_eq!(selection.outpoints.len(), 3);
+		assert_eq!(selection.total, 6);
+		assert_eq!(selection.change, 0);
+	}
+}
+
+#[test]
+fn coin_selection_fails_for_an_unreachable_target() {
+	let available = coins(&[1, 2, 3]);
+
+	for strategy in [Strategy::LargestFirst, Strategy::SmallestFirst, Strategy::BranchAndBound].iter() {
+		assert_eq!(select_coins(&available, 7, *strategy), None);
+	}
+	assert_eq!(select_coins(&[], 1, Strategy::LargestFirst), None);
+}
+
+#[test]
+fn coin_selection_strategies_pick_different_coins() {
+	let available = coins(&[5, 4, 3, 2]);
+
+	let largest = select_coins(&available, 7, Strategy::LargestFirst).unwrap();
+	assert_eq!(selected_values(&available, &largest), vec![4, 5]);
+	assert_eq!(largest.change, 2);
+
+	let smallest = select_coins(&available, 7, Strategy::SmallestFirst).unwrap();
+	assert_eq!(selected_values(&available, &smallest), vec![2, 3, 4]);
+	assert_eq!(smallest.change, 2);
+
+	// branch and bound finds the exact match greedy selection misses
+	let exact = select_coins(&available, 7, Strategy::BranchAndBound).unwrap();
+	assert_eq!(selected_values(&available, &exact), vec![2, 5]);
+	assert_eq!(exact.total, 7);
+	assert_eq!(exact.change, 0);
+
+	// without an exact match it falls back to largest first
+	let fallback = select_coins(&coins(&[5, 5]), 7, Strategy::BranchAndBound).unwrap();
+	assert_eq!(fallback.change, 3);
+}
+
+#[test]
+fn coin_selection_is_deterministic_for_a_seed() {
+	let available = coins(&[5, 5, 5, 5]);
+	let mut reversed = available.clone();
+	reversed.reverse();
+
+	for seed in 0..4 {
+		let selection = select_coins_seeded(&available, 5, Strategy::LargestFirst, seed).unwrap();
+		assert_eq!(selection.outpoints.len(), 1);
+		assert_eq!(select_coins_seeded(&reversed, 5, Strategy::LargestFirst, seed), Some(selection));
+	}
+}
+
+fn spend_signed_by_scheme(scheme_is_ed25519: bool, sign_with_ed25519: bool) -> bool {
+	let ed_alice = ed25519::Pair::from_string("//Alice", None).unwrap();
+	let owner = if scheme_is_ed25519 { H256::from(ed_alice.public().0) } else { pubkey_of(&alice()) };
+	let utxo = TransactionOutput { value: 100, pubkey: owner, ..Default::default() };
+
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: vec![utxo.clone()],
+		..Default::default()
+	}).execute_with(|| {
+		UseEd25519::set(scheme_is_ed25519);
+
+		let mut tx = Transaction {
+			inputs: vec![input(BlakeTwo256::hash_of(&utxo))],
+			outputs: vec![output(100, &bob())],
+			..Default::default()
+		};
+		let message = UtxoModule::signing_payload(&tx);
+		tx.inputs[0].sigscript = if sign_with_ed25519 {
+			H512::from(ed_alice.sign(&message).0)
+		} else {
+			H512::from(alice().sign(&message).0)
+		};
+
+		UtxoModule::spend(Origin::none(), tx).is_ok()
+	})
+}
+
+#[test]
+fn sr25519_scheme_verifies_sr25519_signatures() {
+	assert!(spend_signed_by_scheme(false, false));
+	assert!(!spend_signed_by_scheme(false, true));
+}
+
+#[test]
+fn ed25519_scheme_verifies_ed25519_signatures() {
+	assert!(spend_signed_by_scheme(true, true));
+	assert!(!spend_signed_by_scheme(true, false));
+}
+
+#[test]
+fn tx_builder_produces_transactions_the_pallet_accepts() {
+	new_test_ext().execute_with(|| {
+		let mut builder = TxBuilder::new(GENESIS_HASH);
+		builder
+			.add_input(genesis_outpoint())
+			.add_output(pubkey_of(&bob()), 70)
+			.add_output(pubkey_of(&alice()), 20);
+		assert_eq!(builder.fee(&[GENESIS_UTXO_VALUE]), Some(10));
+		assert_eq!(builder.fee(&[50]), None);
+
+		let tx = builder.sign_with(&alice());
+		assert_eq!(UtxoModule::validate_transaction(&tx).map(|validated| validated.reward), Ok(10));
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+#[test]
+fn tx_builder_inputs_can_be_signed_by_different_owners() {
+	new_test_ext().execute_with(|| {
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(genesis_outpoint())
+			.add_output(pubkey_of(&alice()), 50)
+			.add_output(pubkey_of(&bob()), 50)
+			.sign_with(&alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(outpoint_of(&tx, 0))
+			.add_input(outpoint_of(&tx, 1))
+			.add_output(pubkey_of(&bob()), 100)
+			.sign_input(0, &alice())
+			.sign_input(1, &bob())
+			.build();
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 100);
+	});
+}
+
+// Split alice's genesis value into three outputs of hers
+fn split_genesis_in_three() -> Vec<H256> {
+	let tx = TxBuilder::new(GENESIS_HASH)
+		.add_input(genesis_outpoint())
+		.add_output(pubkey_of(&alice()), 40)
+		.add_output(pubkey_of(&alice()), 30)
+		.add_output(pubkey_of(&alice()), 30)
+		.sign_with(&alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	UtxoModule::output_keys(&tx)
+}
+
+// An unsigned transaction of all of `outpoints` paying bob
+fn paying_bob_from(outpoints: &[H256]) -> TxBuilder {
+	let mut builder = TxBuilder::new(GENESIS_HASH);
+	for outpoint in outpoints {
+		builder.add_input(*outpoint);
+	}
+	builder.add_output(pubkey_of(&bob()), 99);
+
+	builder
+}
+
+#[test]
+fn aggregate_signatures_verify_like_one_signature_per_input() {
+	new_test_ext().execute_with(|| {
+		let outpoints = split_genesis_in_three();
+		let per_input = paying_bob_from(&outpoints).sign_with(&alice());
+		let aggregated = paying_bob_from(&outpoints).aggregate_with(&alice());
+		assert!(aggregated.inputs.iter().all(|input| input.sigscript == H512::zero()));
+
+		// the aggregate isn't part of what is signed, the outputs land under the same keys
+		assert_eq!(crate::txid(&per_input), crate::txid(&aggregated));
+		assert_eq!(
+			UtxoModule::validate_transaction(&per_input),
+			UtxoModule::validate_transaction(&aggregated),
+		);
+		assert_ok!(UtxoModule::spend(Origin::none(), aggregated));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 99);
+	});
+}
+
+#[test]
+fn aggregate_signatures_only_cover_the_inputs_of_their_key() {
+	new_test_ext().execute_with(|| {
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		let mut builder = TxBuilder::new(GENESIS_HASH);
+		builder.add_input(alice_outpoint).add_input(bob_outpoint).add_output(pubkey_of(&bob()), 99);
+
+		// bob's input still needs his own signature
+		let tx = builder.clone().aggregate_with(&alice());
+		assert_eq!(UtxoModule::dry_run(&tx), Err(DryRunError {
+			input_index: Some(1),
+			reason: b"Signature must be valid".to_vec(),
+		}));
+		assert_ok!(UtxoModule::spend(
+			Origin::none(),
+			builder.sign_input(1, &bob()).aggregate_with(&alice()),
+		));
+	});
+}
+
+#[test]
+fn aggregate_signatures_have_to_be_valid_and_used() {
+	new_test_ext().execute_with(|| {
+		let outpoints = split_genesis_in_three();
+		let mut tx = paying_bob_from(&outpoints).aggregate_with(&alice());
+		let signature = tx.aggregate_sig.unwrap().1;
+
+		// claimed for bob, who owns none of the inputs
+		tx.aggregate_sig = Some((pubkey_of(&bob()), signature));
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx.clone()),
+			Error::<Test>::UnusedAggregate
+		);
+
+		// claimed for alice's key but signed by bob, even with valid signatures on every input
+		tx.inputs = paying_bob_from(&outpoints).sign_with(&alice()).inputs;
+		tx.aggregate_sig = Some((pubkey_of(&alice()), signature_of(&tx, &bob())));
+		assert_eq!(UtxoModule::dry_run(&tx), Err(DryRunError {
+			input_index: Some(0),
+			reason: b"Signature must be valid".to_vec(),
+		}));
+	});
+}
+
+#[test]
+fn tx_builder_signatures_do_not_cover_outputs_added_afterwards() {
+	new_test_ext().execute_with(|| {
+		let mut builder = TxBuilder::new(GENESIS_HASH);
+		builder.add_input(genesis_outpoint());
+		builder.sign_with(&alice());
+		builder.add_output(pubkey_of(&bob()), 100);
+
+		assert_eq!(
+			UtxoModule::validate_transaction(&builder.build()),
+			Err("Signature must be valid"),
+		);
+	});
+}
+
+fn overspending_tx() -> Transaction {
+	signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(GENESIS_UTXO_VALUE + 1, &bob())],
+		..Default::default()
+	}, &alice())
+}
+
+fn rejection_reasons() -> Vec<Vec<u8>> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			Event::pallet_utxo(crate::Event::TransactionRejected(reason)) => Some(reason),
+			_ => None,
+		})
+		.collect()
+}
+
+#[test]
+fn rejected_spend_emits_reason_when_enabled() {
+	new_test_ext().execute_with(|| {
+		EmitRejections::set(true);
+
+		assert!(UtxoModule::spend(Origin::none(), overspending_tx()).is_err());
+		assert_eq!(
+			rejection_reasons(),
+			vec![b"OutputsExceedInputs".to_vec()],
+		);
+		assert!(UtxoStore::contains_key(genesis_outpoint()));
+	});
+}
+
+#[test]
+fn rejected_spend_emits_nothing_when_disabled() {
+	new_test_ext().execute_with(|| {
+		assert!(UtxoModule::spend(Origin::none(), overspending_tx()).is_err());
+		assert!(rejection_reasons().is_empty());
+	});
+}
+
+// Alice and bob each own one output worth half of the genesis value
+fn split_genesis() -> (H256, H256) {
+	let tx = TxBuilder::new(GENESIS_HASH)
+		.add_input(genesis_outpoint())
+		.add_output(pubkey_of(&alice()), 50)
+		.add_output(pubkey_of(&bob()), 50)
+		.sign_with(&alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	(outpoint_of(&tx, 0), outpoint_of(&tx, 1))
+}
+
+fn joint_partial_tx(alice_outpoint: H256, bob_outpoint: H256) -> PartialTransaction {
+	let tx = TxBuilder::new(GENESIS_HASH)
+		.add_input(alice_outpoint)
+		.add_input(bob_outpoint)
+		.add_output(pubkey_of(&validator()), 95)
+		.build();
+
+	PartialTransaction::new(tx, vec![output(50, &alice()), output(50, &bob())], GENESIS_HASH)
+}
+
+#[test]
+fn partial_transactions_round_trip_through_scale_and_json() {
+	let mut partial = joint_partial_tx(H256::repeat_byte(1), H256::repeat_byte(2));
+	partial.sign(&alice());
+
+	let decoded = PartialTransaction::decode(&mut &partial.encode()[..]).unwrap();
+	assert_eq!(decoded, partial);
+
+	let json = serde_json::to_string(&partial).unwrap();
+	assert_eq!(serde_json::from_str::<PartialTransaction>(&json).unwrap(), partial);
+}
+
+#[test]
+fn partial_transaction_signed_by_two_parties_is_accepted() {
+	new_test_ext().execute_with(|| {
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		let unsigned = joint_partial_tx(alice_outpoint, bob_outpoint).encode();
+
+		// each party signs its own copy
+		let mut alices = PartialTransaction::decode(&mut &unsigned[..]).unwrap();
+		alices.sign(&alice());
+		let mut bobs = PartialTransaction::decode(&mut &unsigned[..]).unwrap();
+		bobs.sign(&bob());
+
+		assert!(!alices.is_complete());
+		assert_eq!(
+			alices.finalize(),
+			Err(IncompleteError { input: 1, pubkey: pubkey_of(&bob()) }),
+		);
+
+		assert_ok!(alices.merge(&bobs));
+		assert!(alices.is_complete());
+
+		let tx = alices.finalize().unwrap();
+		assert_eq!(UtxoModule::validate_transaction(&tx).map(|validated| validated.reward), Ok(5));
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 95);
+	});
+}
+
+#[test]
+fn partial_transaction_collects_external_signatures_one_at_a_time() {
+	new_test_ext().execute_with(|| {
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		let mut partial = joint_partial_tx(alice_outpoint, bob_outpoint);
+
+		let sighash = partial.sighash(0).unwrap();
+		assert_eq!(sighash, crate::signing_payload(&partial.tx, &GENESIS_HASH));
+		assert_eq!(partial.sighash(1).unwrap(), sighash);
+		assert_eq!(partial.sighash(2), Err(PartialError::UnknownInput(2)));
+
+		let alices = H512::from(alice().sign(&sighash).0);
+		assert_ok!(partial.add_signature(0, pubkey_of(&alice()), alices));
+		assert!(!partial.is_complete());
+
+		let bobs = H512::from(bob().sign(&partial.sighash(1).unwrap()).0);
+		assert_ok!(partial.add_signature(1, pubkey_of(&bob()), bobs));
+		assert!(partial.is_complete());
+
+		assert_ok!(UtxoModule::spend(Origin::none(), partial.finalize().unwrap()));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 95);
+	});
+}
+
+#[test]
+fn partial_transactions_only_take_signatures_from_required_keys() {
+	let mut partial = joint_partial_tx(H256::repeat_byte(1), H256::repeat_byte(2));
+
+	assert_eq!(
+		partial.add_signature(0, pubkey_of(&bob()), H512::zero()),
+		Err(PartialError::UnexpectedSigner(0, pubkey_of(&bob()))),
+	);
+	assert_eq!(
+		partial.add_signature(2, pubkey_of(&bob()), H512::zero()),
+		Err(PartialError::UnknownInput(2)),
+	);
+
+	let other = joint_partial_tx(H256::repeat_byte(1), H256::repeat_byte(3));
+	assert_eq!(partial.merge(&other), Err(PartialError::Mismatch));
+}
+
+#[test]
+fn utxo_set_export_round_trips_through_import() {
+	let (exported, commitment) = new_test_ext().execute_with(|| {
+		split_genesis();
+		(UtxoModule::export_utxo_set(), UtxoModule::utxo_commitment())
+	});
+	assert_eq!(exported.len(), 2);
+
+	ext_with_genesis(Default::default())
+		.execute_with(|| {
+			assert_ok!(UtxoModule::import_utxo_set(exported.clone()));
+
+			let mut imported = UtxoModule::export_utxo_set();
+			imported.sort();
+			let mut expected = exported;
+			expected.sort();
+			assert_eq!(imported, expected);
+			assert_eq!(UtxoModule::utxo_commitment(), commitment);
+			assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 50);
+		});
+}
+
+#[test]
+fn utxo_set_import_requires_an_empty_store() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(
+			UtxoModule::import_utxo_set(vec![(H256::repeat_byte(1), output(1, &bob()))]),
+			Err("utxo set is not empty"),
+		);
+	});
+}
+
+// Spends the genesis output with a witness of `len` bytes, which the signature doesn't cover
+fn spend_genesis_with_witness(len: usize) -> Transaction {
+	let mut tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(GENESIS_UTXO_VALUE, &bob())],
+		..Default::default()
+	}, &alice());
+	tx.inputs[0].witness = vec![1; len];
+	tx
+}
+
+#[test]
+fn witnesses_are_bounded_by_max_witness_bytes() {
+	new_test_ext().execute_with(|| {
+		let limit = MaxWitnessBytes::get() as usize;
+
+		assert_eq!(
+			UtxoModule::validate_transaction(&spend_genesis_with_witness(limit + 1)),
+			Err(Error::<Test>::WitnessTooLarge.into()),
+		);
+		assert_ok!(UtxoModule::spend(Origin::none(), spend_genesis_with_witness(limit)));
+	});
+}
+
+#[test]
+fn spend_weight_grows_with_witness_length() {
+	let weight_of = |len| crate::Call::<Test>::spend(spend_genesis_with_witness(len))
+		.get_dispatch_info()
+		.weight;
+
+	// the witness bytes are part of the encoded transaction as well
+	assert_eq!(
+		weight_of(32) - weight_of(0),
+		<() as WeightInfo>::spend(1, 1, 32) - <() as WeightInfo>::spend(1, 1, 0)
+			+ <() as WeightInfo>::spend_bytes(32),
+	);
+	assert!(weight_of(32) > weight_of(0));
+}
+
+#[test]
+fn spend_weight_grows_with_inputs_outputs_and_bytes() {
+	let weight_of = |inputs: usize, outputs: usize, memo: usize| {
+		let tx = Transaction {
+			inputs: (0 .. inputs as u64).map(|i| input(H256::from_low_u64_be(i))).collect(),
+			outputs: (0 .. outputs)
+				.map(|_| TransactionOutput { memo: vec![0; memo], ..output(1, &alice()) })
+				.collect(),
+			..Default::default()
+		};
+		assert_eq!(
+			crate::Call::<Test>::spend(tx.clone()).get_dispatch_info().weight,
+			crate::spend_weight::<()>(&tx),
+		);
+
+		crate::spend_weight::<()>(&tx)
+	};
+
+	assert!(weight_of(2, 1, 0) > weight_of(1, 1, 0));
+	assert!(weight_of(500, 1, 0) > weight_of(2, 1, 0));
+	assert!(weight_of(1, 2, 0) > weight_of(1, 1, 0));
+	assert!(weight_of(1, 1, 32) > weight_of(1, 1, 0));
+
+	// a memo only adds its bytes, the fixed parts of the weight stay the same
+	assert_eq!(weight_of(1, 1, 32) - weight_of(1, 1, 0), <() as WeightInfo>::spend_bytes(32));
+}
+
+#[test]
+fn calls_checking_a_signature_are_weighed_for_it() {
+	// the mock prices storage at nothing, which leaves the signature check
+	let signature = <() as WeightInfo>::cosigned(1) / 2;
+	let calls = vec![
+		crate::Call::<Test>::prove_ownership(genesis_outpoint(), H512::zero()),
+		crate::Call::<Test>::reserve(genesis_outpoint(), H512::zero(), 2, H256::zero()),
+		crate::Call::<Test>::set_payout_key(H256::zero(), H256::zero(), H512::zero()),
+	];
+	for call in calls {
+		assert_eq!(call.get_dispatch_info().weight, signature);
+	}
+	assert_eq!(crate::Call::<Test>::freeze(genesis_outpoint()).get_dispatch_info().weight, 0);
+}
+
+fn asset_output(value: u128, owner: &sr25519::Pair) -> TransactionOutput {
+	TransactionOutput { asset_id: TEST_ASSET, ..output(value, owner) }
+}
+
+// Issues 30 of the test asset to alice and returns its outpoint
+fn issue_test_asset() -> H256 {
+	assert_ok!(UtxoModule::issue_asset(Origin::signed(ALICE), TEST_ASSET, 30, pubkey_of(&alice())));
+
+	let (_, outpoint) = UtxoModule::export_utxo_set()
+		.into_iter()
+		.find(|(_, utxo)| utxo.asset_id == TEST_ASSET)
+		.unwrap();
+	outpoint
+}
+
+#[test]
+fn mixed_asset_transactions_conserve_every_asset() {
+	new_test_ext().execute_with(|| {
+		let asset_outpoint = issue_test_asset();
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint()), input(asset_outpoint)],
+			outputs: vec![
+				output(90, &bob()),
+				asset_output(20, &bob()),
+				asset_output(10, &alice()),
+			],
+			..Default::default()
+		}, &alice());
+
+		// only the native surplus is a fee
+		assert_eq!(UtxoModule::validate_transaction(&tx).map(|validated| validated.reward), Ok(10));
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 90);
+		assert_eq!(UtxoModule::asset_balance_of(&pubkey_of(&bob()), TEST_ASSET), 20);
+		assert_eq!(UtxoModule::asset_balance_of(&pubkey_of(&alice()), TEST_ASSET), 10);
+	});
+}
+
+#[test]
+fn spends_cannot_create_asset_value() {
+	new_test_ext().execute_with(|| {
+		let asset_outpoint = issue_test_asset();
+
+		// the native surplus can't pay for extra asset value
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint()), input(asset_outpoint)],
+			outputs: vec![output(50, &bob()), asset_output(31, &bob())],
+			..Default::default()
+		}, &alice());
+
+		assert_eq!(
+			UtxoModule::validate_transaction(&tx),
+			Err("OutputsExceedInputs"),
+		);
+	});
+}
+
+#[test]
+fn one_asset_cannot_pay_for_another() {
+	let other_asset = |value, owner| TransactionOutput {
+		asset_id: TEST_ASSET + 1,
+		..output(value, owner)
+	};
+
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: vec![asset_output(100, &alice())],
+		..Default::default()
+	}).execute_with(|| {
+		let outpoint = BlakeTwo256::hash_of(&asset_output(100, &alice()));
+
+		for outputs in vec![vec![other_asset(100, &bob())], vec![output(100, &bob())]] {
+			let tx = Transaction { inputs: vec![input(outpoint)], outputs, ..Default::default() };
+			let tx = signed(tx, &alice());
+			assert_noop!(UtxoModule::spend(Origin::none(), tx), Error::<Test>::AssetImbalance);
+		}
+
+		let tx = signed(Transaction {
+			inputs: vec![input(outpoint)],
+			outputs: vec![asset_output(60, &bob()), asset_output(40, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::asset_balance_of(&pubkey_of(&bob()), TEST_ASSET), 60);
+		assert_eq!(UtxoModule::asset_balance_of(&pubkey_of(&bob()), TEST_ASSET + 1), 0);
+	});
+}
+
+#[test]
+fn fees_are_only_paid_in_the_native_asset() {
+	new_test_ext().execute_with(|| {
+		let asset_outpoint = issue_test_asset();
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint()), input(asset_outpoint)],
+			outputs: vec![output(95, &bob()), asset_output(20, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_noop!(UtxoModule::spend(Origin::none(), tx), Error::<Test>::NonNativeFee);
+		assert_eq!(UtxoModule::reward_total(), 0);
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint()), input(asset_outpoint)],
+			outputs: vec![output(95, &bob()), asset_output(30, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::reward_total(), 5);
+
+		next_block();
+		let reward = crate::OwnedUtxos::iter_prefix(pubkey_of(&validator()))
+			.filter_map(|(outpoint, ())| UtxoStore::get(outpoint))
+			.collect::<Vec<_>>();
+		assert_eq!(reward, vec![output(5, &validator())]);
+	});
+}
+
+#[test]
+fn only_the_asset_issuer_can_issue() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			UtxoModule::issue_asset(Origin::signed(BOB), TEST_ASSET, 30, pubkey_of(&bob())),
+			Error::<Test>::NotAssetIssuer,
+		);
+		assert_noop!(
+			UtxoModule::issue_asset(Origin::signed(ALICE), TEST_ASSET + 1, 30, pubkey_of(&bob())),
+			Error::<Test>::UnknownAsset,
+		);
+
+		assert_ok!(UtxoModule::set_asset_issuer(Origin::root(), TEST_ASSET, pubkey_of(&bob())));
+		assert_ok!(UtxoModule::issue_asset(Origin::signed(BOB), TEST_ASSET, 30, pubkey_of(&bob())));
+		assert_eq!(UtxoModule::asset_balance_of(&pubkey_of(&bob()), TEST_ASSET), 30);
+	});
+}
+
+fn fill_block() {
+	let max = <Test as frame_system::Config>::BlockWeights::get().max_block;
+	System::set_block_limits(max, 0);
+}
+
+#[test]
+fn min_fee_rate_rises_after_full_blocks_and_falls_after_empty_ones() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(UtxoModule::min_fee_rate(), BaseFeeRate::get());
+
+		for _ in 0..20 {
+			fill_block();
+			next_block();
+		}
+		let congested = UtxoModule::min_fee_rate();
+		assert!(congested > 10);
+
+		next_block();
+		assert!(UtxoModule::min_fee_rate() < congested);
+
+		for _ in 0..100 {
+			next_block();
+		}
+		assert_eq!(UtxoModule::min_fee_rate(), BaseFeeRate::get());
+	});
+}
+
+#[test]
+fn unsigned_spends_below_the_fee_rate_floor_are_rejected() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(GENESIS_UTXO_VALUE - 10, &bob())],
+			..Default::default()
+		}, &alice());
+		let call = crate::Call::<Test>::spend(tx.clone());
+		assert_eq!(UtxoModule::fee_rate(&tx, 10), 0);
+
+		assert_ok!(UtxoModule::validate_unsigned(TransactionSource::External, &call));
+
+		fill_block();
+		next_block();
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Payment.into(),
+		);
+	});
+}
+
+fn unique_id() -> H256 {
+	H256::repeat_byte(42)
+}
+
+// Mints the unique test output to alice and returns its outpoint
+fn mint_test_unique() -> H256 {
+	assert_ok!(UtxoModule::mint_unique(Origin::signed(ALICE), TEST_ASSET, unique_id(), pubkey_of(&alice())));
+	UtxoModule::unique_outpoint(unique_id()).unwrap()
+}
+
+fn unique_output(owner: &sr25519::Pair) -> TransactionOutput {
+	TransactionOutput { unique: Some(unique_id()), ..asset_output(1, owner) }
+}
+
+#[test]
+fn unique_outputs_can_be_passed_on() {
+	new_test_ext().execute_with(|| {
+		let minted = mint_test_unique();
+		assert_noop!(
+			UtxoModule::mint_unique(Origin::signed(ALICE), TEST_ASSET, unique_id(), pubkey_of(&bob())),
+			Error::<Test>::UniqueExists,
+		);
+
+		let tx = signed(Transaction {
+			inputs: vec![input(minted)],
+			outputs: vec![unique_output(&bob())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		let moved = outpoint_of(&tx, 0);
+		assert_eq!(UtxoModule::unique_outpoint(unique_id()), Some(moved));
+		assert!(System::events().into_iter().any(|record| record.event
+			== Event::pallet_utxo(crate::Event::UniqueTransferred(unique_id(), minted, moved))));
+	});
+}
+
+#[test]
+fn unique_outputs_cannot_be_duplicated_or_created() {
+	new_test_ext().execute_with(|| {
+		let minted = mint_test_unique();
+
+		// split into two outputs, paying for the second unit with native value won't do either
+		let tx = signed(Transaction {
+			inputs: vec![input(minted)],
+			outputs: vec![unique_output(&bob()), unique_output(&alice())],
+			..Default::default()
+		}, &alice());
+		assert_eq!(UtxoModule::validate_transaction(&tx), Err(Error::<Test>::UniqueDuplicated.into()));
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![TransactionOutput { unique: Some(H256::repeat_byte(7)), ..output(100, &bob()) }],
+			..Default::default()
+		}, &alice());
+		assert_eq!(UtxoModule::validate_transaction(&tx), Err(Error::<Test>::UniqueCreated.into()));
+	});
+}
+
+#[test]
+fn unique_outputs_are_only_burned_explicitly() {
+	new_test_ext().execute_with(|| {
+		let minted = mint_test_unique();
+
+		let tx = signed(Transaction {
+			inputs: vec![input(minted), input(genesis_outpoint())],
+			outputs: vec![output(100, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_eq!(UtxoModule::validate_transaction(&tx), Err(Error::<Test>::UniqueOmitted.into()));
+
+		assert_noop!(UtxoModule::burn_unique(Origin::signed(BOB), minted), Error::<Test>::NotOwner);
+		assert_ok!(UtxoModule::burn_unique(Origin::signed(ALICE), minted));
+		assert!(!UtxoStore::contains_key(minted));
+		assert_eq!(UtxoModule::unique_outpoint(unique_id()), None);
+	});
+}
+
+#[test]
+fn mint_requires_root() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			UtxoModule::mint(Origin::signed(ALICE), vec![output(10, &alice())]),
+			sp_runtime::DispatchError::BadOrigin,
+		);
+		assert_noop!(
+			UtxoModule::mint(Origin::root(), vec![output(0, &alice())]),
+			Error::<Test>::ZeroValueOutput,
+		);
+		assert_noop!(
+			UtxoModule::mint(Origin::root(), vec![output(1, &alice()); MaxOutputsPerBlock::get() as usize + 1]),
+			Error::<Test>::TooManyOutputs,
+		);
+	});
+}
+
+#[test]
+fn mint_stores_identical_outputs_under_distinct_keys() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(UtxoModule::total_supply(), GENESIS_UTXO_VALUE);
+
+		assert_ok!(UtxoModule::mint(Origin::root(), vec![output(10, &bob()), output(10, &bob())]));
+		assert_ok!(UtxoModule::mint(Origin::root(), vec![output(10, &bob())]));
+
+		assert_eq!(OwnedUtxos::iter_prefix(pubkey_of(&bob())).count(), 3);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 30);
+		assert_eq!(UtxoModule::total_supply(), GENESIS_UTXO_VALUE + 30);
+	});
+}
+
+#[test]
+fn nothing_is_minted_to_the_zero_key() {
+	new_test_ext().execute_with(|| {
+		let zero = H256::zero();
+		assert_noop!(
+			UtxoModule::issue_asset(Origin::signed(ALICE), TEST_ASSET, 30, zero),
+			Error::<Test>::UnspendableRecipient,
+		);
+		assert_noop!(
+			UtxoModule::mint_unique(Origin::signed(ALICE), TEST_ASSET, unique_id(), zero),
+			Error::<Test>::UnspendableRecipient,
+		);
+		let to_zero = TransactionOutput { pubkey: zero, ..output(10, &bob()) };
+		let refunding_zero = TransactionOutput { refund_to: Some(zero), ..output(10, &bob()) };
+		for minted in vec![to_zero, refunding_zero] {
+			assert_noop!(
+				UtxoModule::mint(Origin::root(), vec![minted]),
+				Error::<Test>::UnspendableRecipient,
+			);
+		}
+	});
+}
+
+#[test]
+fn minted_outputs_pass_the_checks_of_created_ones() {
+	restricted_ext(&[&alice()]).execute_with(|| {
+		assert_noop!(
+			UtxoModule::mint(Origin::root(), vec![output(10, &bob())]),
+			Error::<Test>::RecipientNotApproved,
+		);
+		let memo = vec![0; MaxMemoBytes::get() as usize + 1];
+		let with_memo = TransactionOutput { memo, ..output(10, &alice()) };
+		assert_noop!(
+			UtxoModule::mint(Origin::root(), vec![with_memo]),
+			Error::<Test>::MemoTooLarge,
+		);
+
+		MaxUtxoSetSize::set(1);
+		assert_noop!(
+			UtxoModule::mint(Origin::root(), vec![output(10, &alice())]),
+			Error::<Test>::UtxoSetFull,
+		);
+	});
+}
+
+#[test]
+fn issuing_an_asset_needs_room_in_the_block() {
+	new_test_ext().execute_with(|| {
+		let room = MaxOutputsPerBlock::get() as usize - crate::CreatedThisBlock::get().len();
+		assert_ok!(UtxoModule::mint(Origin::root(), vec![output(1, &bob()); room]));
+
+		assert_noop!(
+			UtxoModule::issue_asset(Origin::signed(ALICE), TEST_ASSET, 30, pubkey_of(&alice())),
+			Error::<Test>::TooManyOutputs,
+		);
+		let alice = pubkey_of(&alice());
+		assert_noop!(
+			UtxoModule::mint_unique(Origin::signed(ALICE), TEST_ASSET, unique_id(), alice),
+			Error::<Test>::TooManyOutputs,
+		);
+	});
+}
+
+fn spend_genesis_with_change(amount: u128) -> Transaction {
+	signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(amount, &bob())],
+		change_to: Some(pubkey_of(&alice())),
+		valid_until: None,
+		burn: None,
+		aggregate_sig: None,
+	}, &alice())
+}
+
+#[test]
+fn leftover_value_is_returned_to_change_to() {
+	new_test_ext().execute_with(|| {
+		let tx = spend_genesis_with_change(60);
+		assert_eq!(
+			UtxoModule::validate_transaction(&tx).map(|validated| validated.reward),
+			Ok(AutoChangeFee::get()),
+		);
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		let change_key = UtxoModule::change_key(&tx);
+		assert_eq!(
+			UtxoStore::get(change_key),
+			Some(output(GENESIS_UTXO_VALUE - 60 - AutoChangeFee::get(), &alice())),
+		);
+		assert!(System::events().into_iter().any(|record| record.event
+			== Event::pallet_utxo(crate::Event::ChangeReturned(change_key, 38))));
+
+		assert_eq!(UtxoModule::reward_total(), AutoChangeFee::get());
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), AutoChangeFee::get());
+	});
+}
+
+#[test]
+fn leftover_not_exceeding_the_change_fee_is_all_fee() {
+	new_test_ext().execute_with(|| {
+		let tx = spend_genesis_with_change(GENESIS_UTXO_VALUE - AutoChangeFee::get());
+
+		assert_eq!(
+			UtxoModule::validate_transaction(&tx).map(|validated| validated.reward),
+			Ok(AutoChangeFee::get()),
+		);
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		assert!(!UtxoStore::contains_key(UtxoModule::change_key(&tx)));
+	});
+}
+
+fn genesis_spend_to(fee: u128, dest: &sr25519::Pair) -> Transaction {
+	signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(GENESIS_UTXO_VALUE - fee, dest)],
+		..Default::default()
+	}, &alice())
+}
+
+fn pool_priority(tx: &Transaction) -> u64 {
+	let call = crate::Call::<Test>::spend(tx.clone());
+	UtxoModule::validate_unsigned(TransactionSource::External, &call).unwrap().priority
+}
+
+#[test]
+fn equal_fee_rates_are_ordered_by_transaction_hash() {
+	new_test_ext().execute_with(|| {
+		let to_bob = genesis_spend_to(5, &bob());
+		let to_validator = genesis_spend_to(5, &validator());
+		assert_eq!(
+			UtxoModule::fee_rate(&to_bob, 5),
+			UtxoModule::fee_rate(&to_validator, 5),
+		);
+
+		let (bob_priority, validator_priority) = (pool_priority(&to_bob), pool_priority(&to_validator));
+		assert_ne!(bob_priority, validator_priority);
+		assert_eq!(pool_priority(&to_bob), bob_priority);
+		assert_eq!(
+			bob_priority < validator_priority,
+			BlakeTwo256::hash_of(&to_bob)[..4] < BlakeTwo256::hash_of(&to_validator)[..4],
+		);
+	});
+}
+
+#[test]
+fn higher_fee_rates_take_priority_over_the_tiebreak() {
+	let rate_priority = |rate| UtxoModule::priority(&Transaction::default(), rate);
+
+	assert!(rate_priority(1) > rate_priority(0));
+	assert_eq!(rate_priority(1) >> 32, 1);
+}
+
+#[test]
+fn frozen_outpoints_cannot_be_spent_until_unfrozen() {
+	new_test_ext().execute_with(|| {
+		let tx = genesis_spend_to(5, &bob());
+		let call = crate::Call::<Test>::spend(tx.clone());
+
+		assert_noop!(
+			UtxoModule::freeze(Origin::signed(ALICE), genesis_outpoint()),
+			sp_runtime::DispatchError::BadOrigin,
+		);
+		assert_ok!(UtxoModule::freeze(Origin::root(), genesis_outpoint()));
+		assert_eq!(UtxoModule::frozen_at(genesis_outpoint()), Some(1));
+
+		assert_eq!(UtxoModule::validate_transaction(&tx), Err(Error::<Test>::UtxoFrozen.into()));
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Call.into(),
+		);
+
+		assert_ok!(UtxoModule::unfreeze(Origin::root(), genesis_outpoint()));
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+#[test]
+fn only_existing_outpoints_can_be_frozen() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			UtxoModule::freeze(Origin::root(), H256::repeat_byte(1)),
+			Error::<Test>::UnknownOutpoint,
+		);
+		assert_noop!(
+			UtxoModule::unfreeze(Origin::root(), genesis_outpoint()),
+			Error::<Test>::NotFrozen,
+		);
+	});
+}
+
+fn subsidy_events() -> Vec<(u128, u128)> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			Event::pallet_utxo(crate::Event::SubsidyClamped(requested, actual)) => Some((requested, actual)),
+			_ => None,
+		})
+		.collect()
+}
+
+#[test]
+fn rewards_overflowing_a_value_are_clamped() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(10);
+		let author = pubkey_of(&validator());
+		// more fees than the supply accounts for, only 3 of the subsidy fit on top
+		crate::RewardTotal::put(u128::max_value() - 3);
+		let supply = UtxoModule::total_supply();
+
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&author), u128::max_value());
+		assert_eq!(UtxoModule::total_supply(), supply + 3);
+		assert!(System::events().into_iter().any(|record| record.event
+			== Event::pallet_utxo(crate::Event::RewardOverflowClamped(10, 3))));
+		assert!(subsidy_events().is_empty());
+
+		// with room again the subsidy is paid whole
+		next_block();
+		assert_eq!(UtxoModule::total_supply(), supply + 13);
+	});
+}
+
+#[test]
+fn finalization_pays_out_exactly_the_fees_and_subsidy() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(5);
+		TreasuryShare::set(Perbill::from_percent(20));
+		assert_ok!(UtxoModule::spend(Origin::none(), genesis_spend_to(10, &bob())));
+		let fees = UtxoModule::reward_total();
+		assert_eq!(fees, 10);
+
+		UtxoModule::on_finalize(System::block_number());
+		assert_ok!(UtxoModule::check_reward_invariant(fees, 5));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 13);
+		assert_eq!(
+			UtxoModule::check_reward_invariant(fees, 6),
+			Err("reward outputs don't hold the fees and subsidy of the block"),
+		);
+
+		// a reward left behind after the payout
+		crate::RewardTotal::put(1);
+		assert_eq!(
+			UtxoModule::check_reward_invariant(fees, 5),
+			Err("rewards are left undistributed"),
+		);
+	});
+}
+
+#[test]
+fn subsidies_stop_at_the_max_supply() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(10);
+		MaxSupply::set(GENESIS_UTXO_VALUE + 25);
+		let author = pubkey_of(&validator());
+
+		next_block();
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&author), 20);
+		assert!(subsidy_events().is_empty());
+
+		// only 5 are left under the cap
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&author), 25);
+		assert_eq!(subsidy_events(), vec![(10, 5)]);
+
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&author), 25);
+		assert_eq!(subsidy_events(), vec![(10, 5), (10, 0)]);
+		assert_eq!(UtxoModule::total_supply(), MaxSupply::get());
+	});
+}
+
+#[test]
+fn fees_are_paid_at_the_max_supply() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(10);
+		MaxSupply::set(GENESIS_UTXO_VALUE);
+
+		spend_genesis_with_fee(7);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 7);
+		assert_eq!(UtxoModule::total_supply(), GENESIS_UTXO_VALUE);
+	});
+}
+
+#[test]
+fn mint_respects_the_max_supply() {
+	new_test_ext().execute_with(|| {
+		MaxSupply::set(GENESIS_UTXO_VALUE + 10);
+
+		assert_noop!(
+			UtxoModule::mint(Origin::root(), vec![output(11, &bob())]),
+			Error::<Test>::ExceedsMaxSupply,
+		);
+		assert_ok!(UtxoModule::mint(Origin::root(), vec![output(10, &bob())]));
+	});
+}
+
+#[test]
+fn halving_issuance_halves_every_interval() {
+	use crate::issuance::{HalvingIssuance, Issuance, HALVING_EVERY_BLOCKS, INITIAL_ISSUANCE};
+
+	let issuance = <HalvingIssuance as Issuance<u64, u128>>::issuance;
+	let interval = HALVING_EVERY_BLOCKS as u64;
+
+	assert_eq!(issuance(0), INITIAL_ISSUANCE);
+	assert_eq!(issuance(interval - 1), INITIAL_ISSUANCE);
+	assert_eq!(issuance(interval), INITIAL_ISSUANCE / 2);
+	assert_eq!(issuance(interval * 6), 0);
+	assert_eq!(issuance(u64::max_value()), 0);
+}
+
+frame_support::parameter_types! {
+	pub const DecayStart: u128 = 100;
+	pub const DecayPerBlock: u128 = 3;
+	pub const DecayFloor: u128 = 10;
+	pub const Tail: u128 = 5;
+	pub const FastInitial: u128 = 1_000;
+	pub const FastInterval: u32 = 100;
+	pub const SlowInitial: u128 = 64;
+	pub const SlowInterval: u32 = 1_000;
+}
+
+#[test]
+fn halving_schedules_are_configured_independently() {
+	use crate::issuance::{BitcoinIssuance, HalvingIssuance, Issuance};
+
+	let fast = <HalvingIssuance<FastInitial, FastInterval> as Issuance<u64, u128>>::issuance;
+	let slow = <HalvingIssuance<SlowInitial, SlowInterval> as Issuance<u64, u128>>::issuance;
+	let bitcoin = <BitcoinIssuance as Issuance<u64, u128>>::issuance;
+
+	assert_eq!((fast(99), fast(100), fast(250)), (1_000, 500, 250));
+	assert_eq!((slow(999), slow(1_000), slow(2_500)), (64, 32, 16));
+	assert_eq!((bitcoin(1_000), bitcoin(210_000)), (50, 25));
+	assert_eq!(bitcoin(1_000), <HalvingIssuance as Issuance<u64, u128>>::issuance(1_000));
+}
+
+#[test]
+fn linear_decay_issuance_stops_at_the_floor() {
+	use crate::issuance::{Issuance, LinearDecayIssuance};
+
+	type Decay = LinearDecayIssuance<DecayStart, DecayPerBlock, DecayFloor>;
+	let issuance = <Decay as Issuance<u64, u128>>::issuance;
+
+	assert_eq!(issuance(0), 100);
+	assert_eq!(issuance(29), 13);
+	assert_eq!(issuance(30), 10);
+	assert_eq!(issuance(31), 10);
+	assert_eq!(issuance(u64::max_value()), 10);
+}
+
+#[test]
+fn tail_emission_issuance_kicks_in_once_halvings_drop_below_the_tail() {
+	use crate::issuance::{Issuance, TailEmissionIssuance, HALVING_EVERY_BLOCKS, INITIAL_ISSUANCE};
+
+	let issuance = <TailEmissionIssuance<Tail> as Issuance<u64, u128>>::issuance;
+
+	assert_eq!(issuance(0), INITIAL_ISSUANCE);
+	// 50, 25, 12, 6 and then 3, which is below the tail
+	let interval = HALVING_EVERY_BLOCKS as u64;
+	assert_eq!(issuance(interval * 4 - 1), 6);
+	assert_eq!(issuance(interval * 4), 5);
+	assert_eq!(issuance(u64::max_value()), 5);
+}
+
+fn block_summaries() -> Vec<BlockSummary> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			Event::pallet_utxo(crate::Event::BlockSummary(summary)) => Some(summary),
+			_ => None,
+		})
+		.collect()
+}
+
+#[test]
+fn block_summary_aggregates_the_spends_of_the_block() {
+	new_test_ext().execute_with(|| {
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		let tx = signed(Transaction {
+			inputs: vec![input(bob_outpoint)],
+			outputs: vec![output(20, &alice()), output(27, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		next_block();
+
+		// the second spend paid 3, and the block added the author's reward output
+		assert_eq!(block_summaries(), vec![BlockSummary {
+			txs: 2,
+			total_fees: 3,
+			utxos_created: 5,
+			utxos_spent: 2,
+		}]);
+		assert!(UtxoStore::contains_key(alice_outpoint));
+
+		// empty blocks pay no reward output
+		next_block();
+		assert_eq!(block_summaries()[1], BlockSummary::default());
+	});
+}
+
+#[test]
+fn fees_are_reported_per_transaction_and_per_block() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(10);
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		let pay_validator = |outpoint, value, pair: &sr25519::Pair| {
+			let tx = signed(Transaction {
+				inputs: vec![input(outpoint)],
+				outputs: vec![output(value, &validator())],
+				..Default::default()
+			}, pair);
+			assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+			tx
+		};
+		let cheap = pay_validator(alice_outpoint, 49, &alice());
+		let dear = pay_validator(bob_outpoint, 45, &bob());
+
+		let fees = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				Event::pallet_utxo(crate::Event::TransactionSuccess(txid, fee, _)) => {
+					Some((txid, fee))
+				},
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(fees[1..], [(crate::txid(&cheap), 1), (crate::txid(&dear), 5)]);
+		assert_eq!(crate::FeesThisBlock::get(), 6);
+
+		next_block();
+		assert!(System::events().into_iter().any(|record| record.event
+			== Event::pallet_utxo(crate::Event::BlockFees(6, 3))));
+
+		// the author's reward output holds the fees of the block and its subsidy
+		let author = pubkey_of(&validator());
+		assert_eq!(UtxoModule::balance_of(&author), 49 + 45 + 6 + 10);
+		assert!(System::events().into_iter().any(|record| match record.event {
+			Event::pallet_utxo(crate::Event::RewardsIssued(reward, _)) => reward == 6 + 10,
+			_ => false,
+		}));
+	});
+}
+
+#[test]
+fn created_in_current_block_lists_the_new_outputs_and_the_reward() {
+	new_test_ext().execute_with(|| {
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		let tx = signed(Transaction {
+			inputs: vec![input(bob_outpoint)],
+			outputs: vec![output(45, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		UtxoModule::on_finalize(System::block_number());
+		let reward = System::events()
+			.into_iter()
+			.find_map(|record| match record.event {
+				Event::pallet_utxo(crate::Event::RewardsIssued(5, outpoint)) => Some(outpoint),
+				_ => None,
+			})
+			.unwrap();
+
+		// the spent output of bob is listed too, it was created in the same block
+		assert_eq!(
+			UtxoModule::created_in_current_block(),
+			vec![alice_outpoint, bob_outpoint, outpoint_of(&tx, 0), reward],
+		);
+
+		UtxoModule::on_initialize(System::block_number() + 1);
+		assert!(UtxoModule::created_in_current_block().is_empty());
+	});
+}
+
+#[test]
+fn fee_estimates_follow_recent_fee_densities() {
+	new_test_ext().execute_with(|| {
+		// nothing to go on yet
+		assert_eq!(UtxoModule::estimate_fee_within(1, 1_000), MinimumFee::get());
+
+		let mut outpoint = genesis_outpoint();
+		let mut value = GENESIS_UTXO_VALUE;
+		// the first block drops out of the history again
+		for &fee in [50, 4, 10, 2, 8, 6].iter() {
+			value -= fee;
+			let tx = signed(Transaction {
+				inputs: vec![input(outpoint)],
+				outputs: vec![output(value, &alice())],
+				..Default::default()
+			}, &alice());
+			assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+			outpoint = outpoint_of(&tx, 0);
+
+			// the fee of every block is paid for 1_000 of weight
+			System::set_block_limits(1_000, 0);
+			next_block();
+		}
+		assert_eq!(UtxoModule::recent_fee_stats().len(), 5);
+
+		// later targets never pay more
+		let estimates = (1..=6)
+			.map(|target| UtxoModule::estimate_fee_within(target, 1_000))
+			.collect::<Vec<_>>();
+		assert_eq!(estimates, vec![10, 6, 4, 4, 2, 2]);
+
+		// heavier transactions pay in proportion, but never less than the minimum fee
+		assert_eq!(UtxoModule::estimate_fee_within(1, 2_000), 20);
+		assert_eq!(UtxoModule::estimate_fee_within(6, 100), MinimumFee::get());
+	});
+}
+
+#[test]
+fn on_initialize_resets_block_counters_but_not_rewards() {
+	new_test_ext().execute_with(|| {
+		let tx = genesis_spend_to(10, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(crate::TxsThisBlock::get(), 1);
+		assert_eq!(crate::FeesThisBlock::get(), 10);
+
+		let weight = UtxoModule::on_initialize(2);
+		assert_eq!(weight, <Test as frame_system::Config>::DbWeight::get().writes(6));
+
+		assert_eq!(crate::TxsThisBlock::get(), 0);
+		assert_eq!(crate::FeesThisBlock::get(), 0);
+		assert!(crate::CreatedThisBlock::get().is_empty());
+		assert!(crate::SpentThisBlock::get().is_empty());
+		assert!(crate::PaidThisBlock::get().is_empty());
+		assert_eq!(UtxoModule::reward_total(), 10);
+
+		next_block();
+		assert_eq!(UtxoModule::reward_total(), 0);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 10);
+	});
+}
+
+#[test]
+fn sweep_skips_immature_rewards() {
+	new_test_ext().execute_with(|| {
+		CoinbaseMaturity::set(3);
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(30, &validator()), output(31, &validator()), output(32, &validator())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		// the 7 of fees are paid out in a reward output maturing in block 4
+		next_block();
+		let validator_key = pubkey_of(&validator());
+		assert_eq!(UtxoModule::balance_of(&validator_key), 100);
+
+		let sweep = UtxoModule::build_sweep(&validator_key, pubkey_of(&bob())).unwrap();
+		assert_eq!(sweep.inputs.len(), 3);
+		assert_eq!(sweep.outputs, vec![output(92, &bob())]);
+
+		let signature = signature_of(&sweep, &validator());
+		assert_noop!(
+			UtxoModule::sweep(Origin::none(), validator_key, pubkey_of(&bob()), vec![signature; 2]),
+			Error::<Test>::SignatureCountMismatch,
+		);
+		let call = crate::Call::<Test>::sweep(validator_key, pubkey_of(&bob()), vec![signature; 3]);
+		assert_ok!(UtxoModule::validate_unsigned(TransactionSource::External, &call));
+		assert_ok!(UtxoModule::sweep(Origin::none(), validator_key, pubkey_of(&bob()), vec![signature; 3]));
+
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 92);
+		assert_eq!(UtxoModule::balance_of(&validator_key), 7);
+		assert_noop!(
+			UtxoModule::sweep(Origin::none(), validator_key, pubkey_of(&bob()), Vec::new()),
+			Error::<Test>::NothingToSweep,
+		);
+	});
+}
+
+#[test]
+fn reward_outputs_mature_after_the_configured_blocks() {
+	new_test_ext().execute_with(|| {
+		CoinbaseMaturity::set(2);
+		assert_ok!(UtxoModule::spend(Origin::none(), genesis_spend_to(10, &bob())));
+		next_block();
+
+		let (reward, _) = crate::OwnedUtxos::iter_prefix(pubkey_of(&validator())).next().unwrap();
+		let tx = signed(Transaction {
+			inputs: vec![input(reward)],
+			outputs: vec![output(10, &bob())],
+			..Default::default()
+		}, &validator());
+		assert_noop!(UtxoModule::spend(Origin::none(), tx.clone()), Error::<Test>::ImmatureCoinbase);
+
+		next_block();
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+fn subsidy_changes() -> Vec<(u128, u128, u64)> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			Event::pallet_utxo(crate::Event::SubsidyChanged(old, new, block)) => {
+				Some((old, new, block))
+			},
+			_ => None,
+		})
+		.collect()
+}
+
+#[test]
+fn halvings_are_announced_at_the_boundary() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(40);
+		SubsidyHalvingInterval::set(4);
+
+		assert_eq!(UtxoModule::current_subsidy(), 40);
+		assert_eq!(UtxoModule::blocks_until_next_halving(), Some(3));
+
+		next_block();
+		next_block();
+		assert_eq!(System::block_number(), 3);
+		assert_eq!(UtxoModule::blocks_until_next_halving(), Some(1));
+		assert!(subsidy_changes().is_empty());
+
+		next_block();
+		assert_eq!(UtxoModule::current_subsidy(), 20);
+		assert_eq!(UtxoModule::blocks_until_next_halving(), Some(4));
+		assert_eq!(subsidy_changes(), vec![(40, 20, 4)]);
+
+		next_block();
+		assert_eq!(subsidy_changes().len(), 1);
+	});
+}
+
+#[test]
+fn a_decaying_subsidy_is_announced_every_block_until_it_stops() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(40);
+		SubsidyDecayPerBlock::set(10);
+
+		for _ in 0..4 {
+			next_block();
+		}
+		assert_eq!(UtxoModule::current_subsidy(), 0);
+		assert_eq!(subsidy_changes(), vec![(30, 20, 2), (20, 10, 3), (10, 0, 4)]);
+		assert_eq!(UtxoModule::blocks_until_next_halving(), None);
+	});
+}
+
+#[test]
+fn schedules_without_a_known_change_report_no_halving() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(40);
+
+		assert_eq!(UtxoModule::current_subsidy(), 40);
+		assert_eq!(UtxoModule::blocks_until_next_halving(), None);
+	});
+}
+
+#[test]
+fn value_sums_fail_on_overflow() {
+	let sum = |values: Vec<u128>| UtxoModule::sum_values(values).map_err(<&str>::from);
+	let fee = |input, output| UtxoModule::check_conservation(input, output).map_err(<&str>::from);
+
+	assert_eq!(sum(vec![u128::max_value() - 1, 1]), Ok(u128::max_value()));
+	assert_eq!(sum(vec![u128::max_value(), 1]), Err("ValueOverflow"));
+	assert_eq!(fee(10, 7), Ok(3));
+	assert_eq!(fee(7, 10), Err("OutputsExceedInputs"));
+
+	let huge = TransactionOutput { value: u128::max_value(), ..asset_output(0, &alice()) };
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: vec![genesis_utxo(), huge.clone(), asset_output(1, &alice())],
+		..Default::default()
+	}).execute_with(|| {
+		let huge_outpoint = BlakeTwo256::hash_of(&huge);
+		let one_outpoint = BlakeTwo256::hash_of(&asset_output(1, &alice()));
+
+		let inputs_overflow = signed(Transaction {
+			inputs: vec![input(huge_outpoint), input(one_outpoint)],
+			outputs: vec![asset_output(1, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_eq!(UtxoModule::validate_transaction(&inputs_overflow), Err("ValueOverflow"));
+
+		let outputs_overflow = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(u128::max_value(), &bob()), output(1, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_eq!(UtxoModule::validate_transaction(&outputs_overflow), Err("ValueOverflow"));
+	});
+}
+
+#[test]
+fn v0_outputs_are_kept_and_indexed() {
+	use crate::{
+		migrations::{self, v1, v4}, OwnedUtxos, Releases, StorageVersion, TotalSupply,
+		UtxoCommitment, UtxoCount, UtxoCreatedAt,
+	};
+	use frame_support::storage::{unhashed, StoragePrefixedMap};
+
+	new_test_ext().execute_with(|| {
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		let outpoints = [H256::repeat_byte(1), H256::repeat_byte(2)];
+
+		// a chain launched before assets, storage versions and the indexes of the utxo set
+		UtxoStore::remove_all();
+		OwnedUtxos::remove_all();
+		UtxoCreatedAt::<Test>::remove_all();
+		UtxoCount::kill();
+		TotalSupply::kill();
+		UtxoCommitment::kill();
+		StorageVersion::kill();
+		crate::RewardTotal::put(7);
+		let owners = [(60u128, pubkey_of(&alice())), (40, pubkey_of(&bob()))];
+		for (outpoint, (value, pubkey)) in outpoints.iter().zip(owners.iter()) {
+			// the value and the pubkey, 48 bytes
+			let mut encoded = value.encode();
+			encoded.extend_from_slice(pubkey.as_bytes());
+			assert_eq!(encoded.len(), 48);
+			let old = v1::OldTransactionOutput { value: *value, pubkey: *pubkey };
+			assert_eq!(encoded, old.encode());
+			unhashed::put_raw(&v4::old_key(outpoint), &encoded);
+		}
+
+		let before = v1::pre_upgrade().unwrap();
+		assert_eq!(before, (2, 100));
+
+		assert_eq!(migrations::migrate::<Test>(), db.reads_writes(12, 12));
+		assert_eq!(UtxoModule::storage_version(), Releases::V12);
+		assert_ok!(v1::post_upgrade(before));
+		assert_eq!(migrations::utxo_set_summary(), (2, 100));
+
+		let alice_utxo = UtxoStore::get(outpoints[0]).unwrap();
+		assert_eq!(alice_utxo, TransactionOutput {
+			value: 60,
+			pubkey: pubkey_of(&alice()),
+			asset_id: NATIVE_ASSET,
+			unique: None,
+			..Default::default()
+		});
+		assert!(OwnedUtxos::contains_key(pubkey_of(&alice()), outpoints[0]));
+		assert!(OwnedUtxos::contains_key(pubkey_of(&bob()), outpoints[1]));
+		assert_eq!(UtxoModule::utxo_count(), 2);
+		assert_eq!(UtxoModule::total_supply(), 107);
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+		assert_eq!(v1::pre_upgrade(), Err("storage is not at v0"));
+	});
+}
+
+#[test]
+fn outputs_that_dont_decode_are_left_in_place() {
+	use crate::{migrations, Releases};
+	use frame_support::storage::unhashed;
+
+	new_test_ext().execute_with(|| {
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		let corrupt = UtxoStore::hashed_key_for(H256::repeat_byte(1));
+		unhashed::put_raw(&corrupt, &[1, 2, 3]);
+		let utxo = UtxoStore::get(genesis_outpoint());
+
+		// only the genesis output is rewritten, the corrupt entry is logged and left
+		assert_eq!(migrations::translate_from::<Test>(Releases::V11), db.reads_writes(4, 2));
+		assert_eq!(unhashed::get_raw(&corrupt), Some(vec![1, 2, 3]));
+		assert_eq!(UtxoStore::get(genesis_outpoint()), utxo);
+		assert_eq!(migrations::summary_at(Releases::V12), (2, GENESIS_UTXO_VALUE));
+	});
+}
+
+// `utxo` as it was stored at `version`, which leaves out the fields added since. The output sets
+// none of them, so they are the trailing zero bytes of its encoding
+fn encoded_at(utxo: &TransactionOutput, version: crate::Releases) -> Vec<u8> {
+	let added = crate::migrations::FIELDS_ADDED_AT.iter().filter(|added| **added > version);
+	let mut encoded = utxo.encode();
+	for _ in added {
+		assert_eq!(encoded.pop(), Some(0));
+	}
+
+	encoded
+}
+
+#[test]
+fn v2_storage_counts_its_utxos() {
+	use crate::{migrations, Releases, StorageVersion, UtxoCount};
+
+	new_test_ext().execute_with(|| {
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		split_genesis();
+		assert_eq!(UtxoModule::utxo_count(), 2);
+
+		for (outpoint, utxo) in UtxoModule::export_utxo_set() {
+			let key = UtxoStore::hashed_key_for(outpoint);
+			frame_support::storage::unhashed::put_raw(&key, &encoded_at(&utxo, Releases::V2));
+		}
+		UtxoCount::kill();
+		StorageVersion::put(Releases::V2);
+		let before = migrations::v3::pre_upgrade().unwrap();
+
+		assert_eq!(migrations::v3::migrate::<Test>(), db.reads_writes(2, 2));
+		assert_eq!(UtxoModule::storage_version(), Releases::V3);
+		assert_ok!(migrations::v3::post_upgrade(before));
+		assert_eq!(UtxoModule::utxo_count(), 2);
+	});
+}
+
+#[test]
+fn old_outpoints_resolve_after_rehashing_the_keys() {
+	use crate::{migrations::{self, v4}, Releases, StorageVersion};
+
+	new_test_ext().execute_with(|| {
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		split_genesis();
+		let utxos = UtxoModule::export_utxo_set();
+		let commitment = UtxoModule::utxo_commitment();
+
+		// store the utxo set under the identity hasher
+		for (outpoint, utxo) in utxos.iter() {
+			let encoded = encoded_at(utxo, Releases::V3);
+			frame_support::storage::unhashed::kill(&UtxoStore::hashed_key_for(outpoint));
+			frame_support::storage::unhashed::put_raw(&v4::old_key(outpoint), &encoded);
+		}
+		StorageVersion::put(Releases::V3);
+		assert!(utxos.iter().all(|(outpoint, _)| UtxoStore::get(outpoint).is_none()));
+
+		let before = v4::pre_upgrade().unwrap();
+		assert_eq!(before, (2, GENESIS_UTXO_VALUE));
+
+		// the outputs are translated to the current layout on top, in a single pass
+		assert_eq!(migrations::migrate::<Test>(), db.reads_writes(9, 8));
+		assert_eq!(UtxoModule::storage_version(), Releases::V12);
+		assert_ok!(v4::post_upgrade(before));
+
+		// the same outpoints find the same outputs
+		for (outpoint, utxo) in utxos.iter() {
+			assert_eq!(UtxoStore::get(outpoint).as_ref(), Some(utxo));
+			assert!(!frame_support::storage::unhashed::exists(&v4::old_key(outpoint)));
+		}
+		assert_eq!(UtxoModule::utxo_commitment(), commitment);
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+
+		// up to date storage is left alone
+		assert_eq!(migrations::migrate::<Test>(), db.reads(1));
+		UtxoModule::on_runtime_upgrade();
+		assert_eq!(UtxoModule::storage_version(), Releases::V12);
+	});
+}
+
+#[test]
+fn outputs_of_every_older_layout_reach_the_current_one() {
+	use crate::{migrations::{self, v4}, Releases, StorageVersion, UtxoCount};
+	use frame_support::storage::unhashed;
+
+	let versions = [
+		Releases::V1,
+		Releases::V2,
+		Releases::V3,
+		Releases::V4,
+		Releases::V5,
+		Releases::V6,
+		Releases::V7,
+		Releases::V8,
+		Releases::V9,
+		Releases::V10,
+		Releases::V11,
+	];
+	for version in versions.iter().copied() {
+		new_test_ext().execute_with(|| {
+			let db = <Test as frame_system::Config>::DbWeight::get();
+			split_genesis();
+			let utxos = UtxoModule::export_utxo_set();
+			let commitment = UtxoModule::utxo_commitment();
+
+			// rewrite the utxo set as it was stored at `version`, keyed by the identity hasher
+			// before v4 and uncounted before v3
+			for (outpoint, utxo) in utxos.iter() {
+				unhashed::kill(&UtxoStore::hashed_key_for(outpoint));
+				let key = match version < Releases::V4 {
+					true => v4::old_key(outpoint),
+					false => UtxoStore::hashed_key_for(outpoint),
+				};
+				unhashed::put_raw(&key, &encoded_at(utxo, version));
+			}
+			if version < Releases::V3 {
+				UtxoCount::kill();
+			}
+			StorageVersion::put(version);
+
+			let before = migrations::pre_upgrade().unwrap();
+			assert_eq!(before, (2, GENESIS_UTXO_VALUE), "{:?}", version);
+
+			// the other outpoint maps are scanned and every output is translated once, on top of
+			// rehashing and counting the utxo set where the version calls for it
+			let mut weight = db.reads_writes(7, 4);
+			if version < Releases::V4 {
+				weight += db.reads_writes(2, 4);
+			}
+			if version < Releases::V3 {
+				weight += db.reads_writes(2, 1);
+			}
+			assert_eq!(migrations::migrate::<Test>(), weight, "{:?}", version);
+			assert_eq!(UtxoModule::storage_version(), Releases::V12);
+			assert_ok!(migrations::post_upgrade(before));
+			assert_eq!(UtxoModule::export_utxo_set(), utxos, "{:?}", version);
+			assert_eq!(UtxoModule::utxo_commitment(), commitment, "{:?}", version);
+			assert_eq!(UtxoModule::utxo_count(), 2, "{:?}", version);
+			assert_ok!(UtxoModule::try_state(System::block_number()));
+		});
+	}
+}
+
+#[test]
+fn outpoint_maps_resolve_after_rehashing_their_keys() {
+	use crate::{migrations::{self, v12}, CoinbaseUtxos, Releases, StorageVersion};
+	use frame_support::storage::unhashed;
+
+	new_test_ext().execute_with(|| {
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		assert_ok!(UtxoModule::freeze(Origin::root(), alice_outpoint));
+		CoinbaseUtxos::insert(bob_outpoint, ());
+		let created_at = UtxoModule::utxo_created_at(alice_outpoint);
+		let frozen_at = UtxoModule::frozen_at(alice_outpoint);
+		assert!(created_at.is_some() && frozen_at.is_some());
+
+		// store the three maps under the identity hasher
+		for (prefix, new_key) in v12::maps::<Test>().iter() {
+			for outpoint in [alice_outpoint, bob_outpoint].iter() {
+				if let Some(value) = unhashed::get_raw(&new_key(*outpoint)) {
+					unhashed::kill(&new_key(*outpoint));
+					unhashed::put_raw(&v12::old_key(*prefix, outpoint), &value);
+				}
+			}
+		}
+		StorageVersion::put(Releases::V11);
+		assert_eq!(UtxoModule::frozen_at(alice_outpoint), None);
+
+		let before = v12::pre_upgrade::<Test>().unwrap();
+		assert_eq!(before, 4);
+
+		// two creation blocks, a reward and a freeze are moved
+		assert_eq!(migrations::migrate::<Test>(), db.reads_writes(5, 9));
+		assert_eq!(UtxoModule::storage_version(), Releases::V12);
+		assert_ok!(v12::post_upgrade::<Test>(before));
+
+		assert_eq!(UtxoModule::utxo_created_at(alice_outpoint), created_at);
+		assert!(UtxoModule::utxo_created_at(bob_outpoint).is_some());
+		assert_eq!(UtxoModule::frozen_at(alice_outpoint), frozen_at);
+		assert!(CoinbaseUtxos::contains_key(bob_outpoint));
+		assert_eq!(v12::pre_upgrade::<Test>(), Err("storage is not at v11"));
+	});
+}
+
+#[test]
+fn utxos_are_translated_from_an_old_layout() {
+	use crate::{migrations::{self, v1::OldTransactionOutput}, Releases};
+
+	new_test_ext().execute_with(|| {
+		let commitment = UtxoModule::utxo_commitment();
+		let old = OldTransactionOutput { value: GENESIS_UTXO_VALUE, pubkey: pubkey_of(&alice()) };
+		frame_support::storage::unhashed::put(&UtxoStore::hashed_key_for(genesis_outpoint()), &old);
+		crate::UtxoCommitment::kill();
+
+		let weight = migrations::translate_from::<Test>(Releases::V0);
+
+		assert_eq!(weight, <Test as frame_system::Config>::DbWeight::get().reads_writes(2, 2));
+		assert_eq!(UtxoStore::get(genesis_outpoint()), Some(genesis_utxo()));
+		assert_eq!(UtxoModule::utxo_commitment(), commitment);
+	});
+}
+
+// The ledger checks after corrupting a healthy state with `corrupt`
+fn try_state_after(corrupt: impl FnOnce()) -> Result<(), &'static str> {
+	new_test_ext().execute_with(|| {
+		let (alice_outpoint, _) = split_genesis();
+		assert_ok!(UtxoModule::freeze(Origin::root(), alice_outpoint));
+		next_block();
+		UtxoModule::try_state(System::block_number())?;
+
+		corrupt();
+		UtxoModule::try_state(System::block_number())
+	})
+}
+
+#[test]
+fn try_state_detects_a_corrupted_ledger() {
+	assert_eq!(try_state_after(|| ()), Ok(()));
+
+	assert_eq!(
+		try_state_after(|| UtxoStore::mutate(genesis_outpoint(), |_| Some(output(0, &alice())))),
+		Err("utxo set holds an empty output"),
+	);
+	assert_eq!(
+		try_state_after(|| OwnedUtxos::remove_prefix(pubkey_of(&bob()))),
+		Err("utxo is missing from the owner index"),
+	);
+	assert_eq!(
+		try_state_after(|| OwnedUtxos::insert(pubkey_of(&bob()), genesis_outpoint(), ())),
+		Err("owner index holds an outpoint the owner doesn't own"),
+	);
+	assert_eq!(
+		try_state_after(|| crate::TotalSupply::mutate(|total| *total += 1)),
+		Err("total supply doesn't match the utxo set"),
+	);
+	assert_eq!(
+		try_state_after(|| crate::UtxoCommitment::put(H256::zero())),
+		Err("commitment doesn't match the utxo set"),
+	);
+	assert_eq!(
+		try_state_after(|| crate::UtxoCount::mutate(|count| *count += 1)),
+		Err("utxo count doesn't match the utxo set"),
+	);
+	assert_eq!(
+		try_state_after(|| crate::FrozenUtxos::<Test>::insert(genesis_outpoint(), 1)),
+		Err("frozen outpoint is not in the utxo set"),
+	);
+}
+
+#[test]
+fn outpoints_for_matches_the_inserted_keys() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(30, &bob()), output(20, &bob())],
+			change_to: Some(pubkey_of(&alice())),
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		let expected = UtxoModule::outpoints_for(&tx);
+		assert_eq!(expected.len(), 3);
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(crate::CreatedThisBlock::get(), expected);
+		assert!(expected.iter().all(|key| UtxoStore::contains_key(key)));
+	});
+}
+
+#[test]
+fn is_applied_tells_how_many_outputs_are_left() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(30, &bob()), output(20, &bob())],
+			..Default::default()
+		}, &alice());
+		assert_eq!(UtxoModule::is_applied(&tx), AppliedStatus::NotApplied);
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		assert_eq!(UtxoModule::is_applied(&tx), AppliedStatus::FullyUnspent);
+
+		let spend_first = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 0))],
+			outputs: vec![output(29, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), spend_first));
+		assert_eq!(UtxoModule::is_applied(&tx), AppliedStatus::PartiallySpent);
+	});
+}
+
+#[test]
+fn conflicting_spends_are_mutually_exclusive() {
+	new_test_ext().execute_with(|| {
+		let to_bob = genesis_spend_to(10, &bob());
+		let to_validator = genesis_spend_to(20, &validator());
+		let validate = |tx: &Transaction| {
+			let call = crate::Call::<Test>::spend(tx.clone());
+			UtxoModule::validate_unsigned(TransactionSource::External, &call)
+		};
+
+		// both are valid on their own, but claim the same outpoint
+		let bobs = validate(&to_bob).unwrap();
+		let validators = validate(&to_validator).unwrap();
+		let spent = (b"spent", genesis_outpoint()).encode();
+		assert!(bobs.provides.contains(&spent) && validators.provides.contains(&spent));
+
+		assert_ok!(UtxoModule::spend(Origin::none(), to_validator));
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), to_bob.clone()).map_err(|e| e.error),
+			Error::<Test>::MissingInput,
+		);
+		assert_eq!(validate(&to_bob), InvalidTransaction::Stale.into());
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 0);
+	});
+}
+
+fn refundable_payment_to_bob() -> H256 {
+	refundable_payment(output(100, &bob()))
+}
+
+// the genesis output into `payment`, refundable to alice
+fn refundable_payment(payment: TransactionOutput) -> H256 {
+	let tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![TransactionOutput { refund_to: Some(pubkey_of(&alice())), ..payment }],
+		..Default::default()
+	}, &alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	outpoint_of(&tx, 0)
+}
+
+fn refund_of(payment: H256) -> H256 {
+	BlakeTwo256::hash_of(&(b"refund", payment))
+}
+
+#[test]
+fn refundable_payments_can_be_spent_normally() {
+	new_test_ext().execute_with(|| {
+		let payment = refundable_payment_to_bob();
+
+		let tx = signed(Transaction {
+			inputs: vec![input(payment)],
+			outputs: vec![output(100, &validator())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 100);
+		assert_noop!(UtxoModule::reject_payment(Origin::signed(BOB), payment), Error::<Test>::UnknownOutpoint);
+	});
+}
+
+#[test]
+fn rejected_payments_are_refunded() {
+	new_test_ext().execute_with(|| {
+		let payment = refundable_payment_to_bob();
+		assert_noop!(UtxoModule::reject_payment(Origin::signed(ALICE), payment), Error::<Test>::NotOwner);
+
+		assert_ok!(UtxoModule::reject_payment(Origin::signed(BOB), payment));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 0);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), 100);
+
+		// the refund can't bounce back again
+		let (refund, _) = OwnedUtxos::iter_prefix(pubkey_of(&alice())).next().unwrap();
+		assert_eq!(UtxoStore::get(refund), Some(output(100, &alice())));
+		assert_noop!(UtxoModule::reject_payment(Origin::signed(ALICE), refund), Error::<Test>::NotRefundable);
+		assert_eq!(UtxoModule::try_state(System::block_number()), Ok(()));
+	});
+}
+
+#[test]
+fn refunds_leave_the_fallback_of_the_recipient_behind() {
+	new_test_ext().execute_with(|| {
+		let fallback = crate::Fallback { pubkey: pubkey_of(&validator()), after: 1 };
+		let payment = refundable_payment(TransactionOutput {
+			fallback: Some(fallback),
+			..output(100, &bob())
+		});
+		assert_ok!(UtxoModule::reject_payment(Origin::signed(BOB), payment));
+		assert_eq!(UtxoStore::get(refund_of(payment)), Some(output(100, &alice())));
+
+		// the fallback key of bob may spend his outputs, not the refund to alice
+		let tx = Transaction {
+			inputs: vec![input(refund_of(payment))],
+			outputs: vec![output(100, &validator())],
+			..Default::default()
+		};
+		assert_eq!(
+			UtxoModule::validate_transaction(&signed(tx.clone(), &validator())).map(|_| ()),
+			Err("Signature must be valid"),
+		);
+		assert_ok!(UtxoModule::validate_transaction(&signed(tx, &alice())));
+	});
+}
+
+#[test]
+fn expiring_payments_are_refunded_without_expiry_until_they_expire() {
+	let expiring = || TransactionOutput { expiry: Some(2), ..output(100, &bob()) };
+
+	new_test_ext().execute_with(|| {
+		let payment = refundable_payment(expiring());
+		assert_ok!(UtxoModule::reject_payment(Origin::signed(BOB), payment));
+		assert_eq!(UtxoStore::get(refund_of(payment)).unwrap().expiry, None);
+	});
+
+	new_test_ext().execute_with(|| {
+		let payment = refundable_payment(expiring());
+		System::set_block_number(3);
+		assert_noop!(
+			UtxoModule::reject_payment(Origin::signed(BOB), payment),
+			Error::<Test>::UtxoExpired,
+		);
+	});
+}
+
+#[test]
+fn locked_payments_are_only_rejected_once_unlocked() {
+	new_test_ext().execute_with(|| {
+		let payment = refundable_payment(TransactionOutput {
+			unlocks_at: Some(3),
+			..output(100, &bob())
+		});
+		assert_noop!(
+			UtxoModule::reject_payment(Origin::signed(BOB), payment),
+			Error::<Test>::UtxoLocked,
+		);
+
+		System::set_block_number(3);
+		assert_ok!(UtxoModule::reject_payment(Origin::signed(BOB), payment));
+		assert_eq!(UtxoStore::get(refund_of(payment)).unwrap().unlocks_at, None);
+	});
+}
+
+#[test]
+fn vesting_payments_are_only_rejected_once_fully_vested() {
+	new_test_ext().execute_with(|| {
+		let payment = refundable_payment(TransactionOutput {
+			vesting: Some(crate::Vesting { start: 1, end: 5 }),
+			..output(100, &bob())
+		});
+		System::set_block_number(4);
+		assert_noop!(
+			UtxoModule::reject_payment(Origin::signed(BOB), payment),
+			Error::<Test>::UtxoLocked,
+		);
+
+		System::set_block_number(5);
+		assert_ok!(UtxoModule::reject_payment(Origin::signed(BOB), payment));
+		assert_eq!(UtxoStore::get(refund_of(payment)).unwrap().vesting, None);
+	});
+}
+
+#[test]
+fn escrowed_payments_can_not_be_rejected_by_the_owner_alone() {
+	new_test_ext().execute_with(|| {
+		let payment = refundable_payment(TransactionOutput {
+			cosigner: Some(pubkey_of(&validator())),
+			..output(100, &bob())
+		});
+
+		assert_noop!(
+			UtxoModule::reject_payment(Origin::signed(BOB), payment),
+			Error::<Test>::CosignerRequired,
+		);
+	});
+}
+
+#[test]
+fn spends_failing_before_signature_checks_are_refunded() {
+	new_test_ext().execute_with(|| {
+		let (alice_outpoint, bob_outpoint) = split_genesis();
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(alice_outpoint)
+			.add_input(bob_outpoint)
+			.add_output(pubkey_of(&validator()), 90)
+			.sign_input(0, &alice())
+			.sign_input(1, &bob())
+			.build();
+		let declared = crate::Call::<Test>::spend(tx.clone()).get_dispatch_info().weight;
+
+		// every signature was verified, so the declared weight stands
+		let applied = UtxoModule::spend(Origin::none(), tx.clone()).unwrap();
+		assert_eq!(applied.actual_weight, None);
+
+		// the replay fails before any signature is verified
+		let replayed = UtxoModule::spend(Origin::none(), tx.clone()).unwrap_err();
+		let lookups = <Test as frame_system::Config>::DbWeight::get().reads(2);
+		let actual = replayed.post_info.actual_weight.unwrap();
+		assert_eq!(actual, <() as WeightInfo>::spend(0, 1, 0) + lookups);
+		assert!(actual < declared);
+	});
+}
+
+#[test]
+fn replays_fail_as_already_applied_then_on_their_spent_inputs() {
+	new_test_ext().execute_with(|| {
+		let tx = genesis_spend_to(10, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		next_block();
+
+		// the outputs of the original are still there
+		assert!(UtxoModule::output_keys(&tx).iter().all(|key| UtxoStore::contains_key(key)));
+		let replay = Transaction::decode(&mut &tx.encode()[..]).unwrap();
+		assert_eq!(UtxoModule::validate_transaction(&replay), Err("AlreadyApplied"));
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), replay.clone()).map_err(|e| e.error),
+			Error::<Test>::AlreadyApplied,
+		);
+		let call = crate::Call::<Test>::spend(replay.clone());
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into(),
+		);
+
+		// once they are spent as well only the missing inputs are left to notice
+		let onwards = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 0))],
+			outputs: vec![output(80, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), onwards));
+		assert_eq!(UtxoModule::validate_transaction(&replay), Err("MissingInput"));
+	});
+}
+
+#[test]
+fn rekey_moves_an_output_to_a_new_key() {
+	new_test_ext().execute_with(|| {
+		let tx = UtxoModule::build_rekey(&genesis_outpoint(), pubkey_of(&bob())).unwrap();
+		assert_eq!(tx.outputs, vec![output(GENESIS_UTXO_VALUE - MinimumFee::get(), &bob())]);
+
+		// only the current owner can rekey
+		let bobs = signature_of(&tx, &bob());
+		assert!(UtxoModule::rekey(Origin::none(), genesis_outpoint(), bobs, pubkey_of(&bob())).is_err());
+
+		let alices = signature_of(&tx, &alice());
+		let call = crate::Call::<Test>::rekey(genesis_outpoint(), alices, pubkey_of(&bob()));
+		assert_ok!(UtxoModule::validate_unsigned(TransactionSource::External, &call));
+		assert_ok!(UtxoModule::rekey(Origin::none(), genesis_outpoint(), alices, pubkey_of(&bob())));
+
+		assert!(!UtxoStore::contains_key(genesis_outpoint()));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), 0);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), GENESIS_UTXO_VALUE - MinimumFee::get());
+		assert_eq!(UtxoModule::reward_total(), MinimumFee::get());
+	});
+}
+
+#[test]
+fn rekey_passes_on_a_plain_output() {
+	new_test_ext().execute_with(|| {
+		let fallback = crate::Fallback { pubkey: pubkey_of(&validator()), after: 1 };
+		UtxoStore::mutate(genesis_outpoint(), |utxo| {
+			let utxo = utxo.as_mut().unwrap();
+			utxo.refund_to = Some(pubkey_of(&bob()));
+			utxo.memo = b"note".to_vec();
+			utxo.unlocks_at = Some(0);
+			utxo.fallback = Some(fallback);
+		});
+
+		// none of the conditions of the old output carry over to the new key
+		let tx = UtxoModule::build_rekey(&genesis_outpoint(), pubkey_of(&bob())).unwrap();
+		assert_eq!(tx.outputs, vec![output(GENESIS_UTXO_VALUE - MinimumFee::get(), &bob())]);
+
+		UtxoStore::mutate(genesis_outpoint(), |utxo| {
+			utxo.as_mut().unwrap().cosigner = Some(pubkey_of(&validator()));
+		});
+		assert_noop!(
+			UtxoModule::rekey(Origin::none(), genesis_outpoint(), H512::zero(), pubkey_of(&bob())),
+			Error::<Test>::CosignerRequired,
+		);
+	});
+}
+
+#[test]
+fn rekey_moves_other_assets_whole() {
+	new_test_ext().execute_with(|| {
+		let asset_outpoint = issue_test_asset();
+
+		let tx = UtxoModule::build_rekey(&asset_outpoint, pubkey_of(&bob())).unwrap();
+		assert_eq!(tx.outputs, vec![asset_output(30, &bob())]);
+
+		let alices = signature_of(&tx, &alice());
+		assert_ok!(UtxoModule::rekey(Origin::none(), asset_outpoint, alices, pubkey_of(&bob())));
+
+		assert_eq!(UtxoStore::get(outpoint_of(&tx, 0)), Some(asset_output(30, &bob())));
+		assert_eq!(UtxoModule::reward_total(), 0);
+	});
+}
+
+#[test]
+fn a_transaction_can_pay_the_same_output_twice() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(10, &bob()), output(10, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		let keys = UtxoModule::output_keys(&tx);
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_ne!(keys[0], keys[1]);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 20);
+
+		// spending the same input twice is still rejected
+		let tx = signed(Transaction {
+			inputs: vec![input(keys[0]), input(keys[0])],
+			outputs: vec![output(10, &alice())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &bob());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx).map_err(|e| e.error),
+			"Each input must be used once"
+		);
+	});
+}
+
+#[test]
+fn transactions_cannot_grow_a_full_utxo_set() {
+	new_test_ext().execute_with(|| {
+		MaxUtxoSetSize::set(2);
+		assert_eq!(UtxoModule::utxo_count(), 1);
+
+		// grows the set to exactly the cap
+		let split = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(50, &bob()), output(49, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		let keys = UtxoModule::output_keys(&split);
+		assert_ok!(UtxoModule::spend(Origin::none(), split));
+		assert_eq!(UtxoModule::utxo_count(), 2);
+
+		let grow = signed(Transaction {
+			inputs: vec![input(keys[0])],
+			outputs: vec![output(25, &alice()), output(24, &alice())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &bob());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), grow).map_err(|e| e.error),
+			Error::<Test>::UtxoSetFull
+		);
+
+		// a full set can still be spent from and shrunk
+		MaxUtxoSetSize::set(1);
+		let keep = signed(Transaction {
+			inputs: vec![input(keys[0])],
+			outputs: vec![output(49, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &bob());
+		let kept = UtxoModule::output_keys(&keep)[0];
+		assert_ok!(UtxoModule::spend(Origin::none(), keep));
+		assert_eq!(UtxoModule::utxo_count(), 2);
+
+		let merge = signed(Transaction {
+			inputs: vec![input(kept), input(keys[1])],
+			outputs: vec![output(90, &alice())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), merge));
+		assert_eq!(UtxoModule::utxo_count(), 1);
+	});
+}
+
+// Records the messages logged under the pallet's target on the current thread
+struct TestLogger;
+
+thread_local! {
+	static LOGGED: std::cell::RefCell<Vec<String>> = Default::default();
+}
+
+impl log::Log for TestLogger {
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		metadata.target() == crate::LOG_TARGET
+	}
+
+	fn log(&self, record: &log::Record) {
+		if self.enabled(record.metadata()) {
+			LOGGED.with(|logged| logged.borrow_mut().push(record.args().to_string()));
+		}
+	}
+
+	fn flush(&self) {}
+}
+
+fn capture_logs(f: impl FnOnce()) -> Vec<String> {
+	// another test may have installed it already
+	let _ = log::set_logger(&TestLogger);
+	log::set_max_level(log::LevelFilter::Trace);
+	LOGGED.with(|logged| logged.borrow_mut().clear());
+
+	f();
+	LOGGED.with(|logged| logged.take())
+}
+
+#[test]
+fn rejections_are_logged_with_their_reason() {
+	let logged = capture_logs(|| new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(160, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+
+		assert!(UtxoModule::validate_transaction(&tx).is_err());
+	}));
+
+	assert!(logged.contains(&"asset 0 inputs 100 outputs 160".to_string()));
+	assert_eq!(logged.last().map(String::as_str), Some("transaction rejected: OutputsExceedInputs"));
+}
+
+#[test]
+fn the_utxo_set_can_be_read_in_pages() {
+	new_test_ext().execute_with(|| {
+		for index in 1..250u64 {
+			let key = BlakeTwo256::hash_of(&index);
+			crate::Module::<Test>::insert_utxo(key, output(index as u128, &bob()));
+		}
+		let entries: Vec<_> = UtxoModule::utxo_iter().collect();
+		assert_eq!(entries.len(), 250);
+
+		let mut pages = Vec::new();
+		let mut cursor = None;
+		loop {
+			let (page, next) = UtxoModule::utxos_paged(cursor, 100);
+			pages.push(page);
+			match next {
+				Some(next) => cursor = Some(next),
+				None => break,
+			}
+		}
+
+		assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), vec![100, 100, 50]);
+		assert_eq!(pages.concat(), entries);
+
+		// cursors from elsewhere in storage find nothing
+		assert_eq!(UtxoModule::utxos_paged(Some(b"elsewhere".to_vec()), 100), (Vec::new(), None));
+	});
+}
+
+#[test]
+fn txids_ignore_signatures() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(90, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+
+		let mut resigned = tx.clone();
+		resigned.inputs[0].sigscript = H512::repeat_byte(1);
+		resigned.inputs[0].witness = vec![1, 2, 3];
+		assert_eq!(crate::txid(&resigned), crate::txid(&tx));
+
+		let mut paying_more = tx.clone();
+		paying_more.outputs[0].value = 91;
+		assert_ne!(crate::txid(&paying_more), crate::txid(&tx));
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		let txid = crate::txid(&tx);
+		let expected = Event::pallet_utxo(crate::Event::TransactionSuccess(txid, 10, tx));
+		assert!(System::events().iter().any(|record| record.event == expected));
+	});
+}
+
+#[test]
+fn signatures_dont_change_output_keys() {
+	new_test_ext().execute_with(|| {
+		let unsigned = Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(50, &bob()), output(40, &alice())],
+			change_to: Some(pubkey_of(&alice())),
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		};
+		// sr25519 signatures are randomized, signing twice gives two different valid ones
+		let tx = signed(unsigned.clone(), &alice());
+		let resigned = signed(unsigned, &alice());
+		assert_ne!(tx.inputs[0].sigscript, resigned.inputs[0].sigscript);
+		assert_ok!(UtxoModule::validate_transaction(&tx));
+		assert_ok!(UtxoModule::validate_transaction(&resigned));
+
+		assert_eq!(UtxoModule::outpoints_for(&tx), UtxoModule::outpoints_for(&resigned));
+
+		let keys = UtxoModule::outpoints_for(&tx);
+		assert_ok!(UtxoModule::spend(Origin::none(), resigned));
+		assert!(keys.iter().all(|key| UtxoStore::contains_key(key)));
+	});
+}
+
+#[test]
+fn children_signed_ahead_survive_a_relayer_mutating_their_parent() {
+	new_test_ext().execute_with(|| {
+		let parent = genesis_spend_to(10, &bob());
+		// bob spends the output before the parent reaches a block
+		let child = signed(Transaction {
+			inputs: vec![input(outpoint_of(&parent, 0))],
+			outputs: vec![output(GENESIS_UTXO_VALUE - 20, &alice())],
+			..Default::default()
+		}, &bob());
+
+		// the witness isn't signed, a relayer can swap it without invalidating the parent
+		let mut relayed = parent.clone();
+		relayed.inputs[0].witness = vec![9; 8];
+		assert_ne!(relayed.encode(), parent.encode());
+		assert_ok!(UtxoModule::spend(Origin::none(), relayed));
+
+		assert_ok!(UtxoModule::spend(Origin::none(), child));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), GENESIS_UTXO_VALUE - 20);
+	});
+}
+
+#[test]
+fn a_snapshot_boots_a_chain_with_the_same_outpoints() {
+	use crate::snapshot::{self, UtxoSnapshot};
+
+	let (snapshot, bob_outpoint) = new_test_ext().execute_with(|| {
+		let (_, bob_outpoint) = split_genesis();
+		next_block();
+
+		(snapshot::export::<Test>(), bob_outpoint)
+	});
+	assert_eq!(snapshot.block, 2);
+	assert_eq!(snapshot.entries.len(), 2);
+	assert_eq!(snapshot.total_supply, GENESIS_UTXO_VALUE);
+
+	let json = serde_json::to_string(&snapshot).unwrap();
+	assert_eq!(serde_json::from_str::<UtxoSnapshot>(&json).unwrap(), snapshot);
+	assert_eq!(UtxoSnapshot::decode(&mut &snapshot.encode()[..]).unwrap(), snapshot);
+
+	ext_with_genesis(crate::GenesisConfig::from_snapshot(snapshot.clone())).execute_with(|| {
+		assert_eq!(UtxoModule::export_utxo_set(), snapshot.entries);
+		assert_eq!(UtxoModule::total_supply(), snapshot.total_supply);
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+
+		let tx = signed(Transaction {
+			inputs: vec![input(bob_outpoint)],
+			outputs: vec![output(40, &alice())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+// Split the genesis output and return a spend of bob's half, for the same block
+fn respend_of_split_genesis() -> Transaction {
+	let (_, bob_outpoint) = split_genesis();
+
+	signed(Transaction {
+		inputs: vec![input(bob_outpoint)],
+		outputs: vec![output(40, &alice())],
+		change_to: None,
+		valid_until: None,
+		burn: None,
+		aggregate_sig: None,
+	}, &bob())
+}
+
+#[test]
+fn outputs_can_be_respent_within_their_block_by_default() {
+	new_test_ext().execute_with(|| {
+		let respend = respend_of_split_genesis();
+
+		assert_ok!(UtxoModule::spend(Origin::none(), respend));
+	});
+}
+
+#[test]
+fn outputs_wait_a_block_when_same_block_spends_are_forbidden() {
+	new_test_ext().execute_with(|| {
+		SameBlockSpendForbidden::set(true);
+		// the genesis output is from an earlier block
+		let respend = respend_of_split_genesis();
+		let bob_key = pubkey_of(&bob());
+
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), respend.clone()).map_err(|e| e.error),
+			Error::<Test>::SameBlockSpend
+		);
+		assert_eq!(
+			UtxoModule::build_sweep(&bob_key, pubkey_of(&alice())).map_err(<&str>::from),
+			Err("NothingToSweep")
+		);
+
+		next_block();
+		assert_ok!(UtxoModule::build_sweep(&bob_key, pubkey_of(&alice())));
+		assert_ok!(UtxoModule::spend(Origin::none(), respend));
+	});
+}
+
+#[test]
+fn transactions_expire_after_valid_until() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(90, &bob())],
+			change_to: None,
+			valid_until: Some(2),
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		let call = crate::Call::<Test>::spend(tx.clone());
+
+		// the pool drops it once it expires, within `PoolLongevity`
+		let validity = UtxoModule::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(validity.longevity, 2);
+		next_block();
+		assert_ok!(UtxoModule::validate_transaction(&tx));
+
+		next_block();
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx).map_err(|e| e.error),
+			Error::<Test>::TransactionExpired
+		);
+
+		// without valid_until the pool keeps spends for `PoolLongevity` blocks
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(90, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		let call = crate::Call::<Test>::spend(tx);
+		let validity = UtxoModule::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(validity.longevity, 64);
+	});
+}
+
+#[test]
+fn burned_fees_leave_the_supply_and_authors_earn_the_subsidy() {
+	new_test_ext().execute_with(|| {
+		BurnFees::set(true);
+		BlockSubsidy::set(5);
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(90, &bob())],
+			change_to: None,
+			valid_until: None,
+			burn: None,
+			aggregate_sig: None,
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+
+		assert_eq!(UtxoModule::reward_total(), 0);
+		assert_eq!(UtxoModule::fees_burned(), 10);
+		assert_eq!(UtxoModule::total_supply(), GENESIS_UTXO_VALUE - 10);
+
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 5);
+		assert_eq!(UtxoModule::total_supply(), GENESIS_UTXO_VALUE - 10 + 5);
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+	});
+}
+
+fn payout_claim(payout: H256, signer: &sr25519::Pair) -> crate::PayoutClaim {
+	let block = System::block_number();
+	let signature = signer.sign(&crate::payout_payload(&payout, block));
+
+	crate::PayoutClaim { payout, signature: H512::from(signature.0) }
+}
+
+#[test]
+fn rewards_go_to_the_claimed_payout_key() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(5);
+		let payout = pubkey_of(&bob());
+		let claim = payout_claim(payout, &validator());
+
+		// the author puts the claim into the block through the inherent
+		let mut data = sp_inherents::InherentData::new();
+		data.put_data(crate::INHERENT_IDENTIFIER, &claim).unwrap();
+		let call = <UtxoModule as sp_inherents::ProvideInherent>::create_inherent(&data);
+		assert_eq!(call, Some(crate::Call::set_payout(claim.clone())));
+		assert!(<UtxoModule as sp_inherents::ProvideInherent>::create_inherent(
+			&sp_inherents::InherentData::new()
+		).is_none());
+
+		assert_ok!(UtxoModule::set_payout(Origin::none(), claim.clone()));
+		assert_noop!(UtxoModule::set_payout(Origin::none(), claim), "payout already set in this block");
+		next_block();
+
+		assert_eq!(UtxoModule::balance_of(&payout), 5);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 0);
+	});
+}
+
+#[test]
+fn rewards_fall_back_to_the_session_key() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(5);
+
+		// no claim
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 5);
+
+		// a claim someone else signed
+		let payout = pubkey_of(&bob());
+		assert_ok!(UtxoModule::set_payout(Origin::none(), payout_claim(payout, &bob())));
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&payout), 0);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 10);
+		assert!(System::events().iter().any(|record| record.event
+			== Event::pallet_utxo(crate::Event::PayoutClaimRejected(payout))));
+
+		// a claim signed for another block
+		let claim = payout_claim(payout, &validator());
+		next_block();
+		assert_ok!(UtxoModule::set_payout(Origin::none(), claim));
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&payout), 0);
+	});
+}
+
+// the signature of `authority` binding its rewards to `payout` at `nonce`
+fn payout_preference_signature(payout: &H256, nonce: u64, authority: &sr25519::Pair) -> H512 {
+	H512::from(authority.sign(&crate::payout_preference_payload(payout, nonce)).0)
+}
+
+#[test]
+fn rewards_go_to_the_standing_payout_preference() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(5);
+		let authority = pubkey_of(&validator());
+		let (cold, colder) = (pubkey_of(&bob()), H256::repeat_byte(3));
+
+		let signature = payout_preference_signature(&cold, 0, &validator());
+		let call = crate::Call::<Test>::set_payout_key(authority, cold, signature);
+		assert_ok!(UtxoModule::validate_unsigned(TransactionSource::External, &call));
+		assert_ok!(UtxoModule::set_payout_key(Origin::none(), authority, cold, signature));
+		assert_eq!(UtxoModule::payout_nonce(authority), 1);
+		next_block();
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&cold), 10);
+		assert_eq!(UtxoModule::balance_of(&authority), 0);
+
+		// a newer preference takes over
+		let signature = payout_preference_signature(&colder, 1, &validator());
+		assert_ok!(UtxoModule::set_payout_key(Origin::none(), authority, colder, signature));
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&colder), 5);
+		assert_eq!(UtxoModule::balance_of(&cold), 10);
+
+		// a per block claim still wins over the preference
+		assert_ok!(UtxoModule::set_payout(Origin::none(), payout_claim(cold, &validator())));
+		next_block();
+		assert_eq!(UtxoModule::balance_of(&cold), 15);
+	});
+}
+
+#[test]
+fn stale_payout_preferences_are_rejected() {
+	new_test_ext().execute_with(|| {
+		let authority = pubkey_of(&validator());
+		let cold = pubkey_of(&bob());
+		let first = payout_preference_signature(&cold, 0, &validator());
+		assert_ok!(UtxoModule::set_payout_key(Origin::none(), authority, cold, first));
+
+		let colder = H256::repeat_byte(3);
+		let signature = payout_preference_signature(&colder, 1, &validator());
+		assert_ok!(UtxoModule::set_payout_key(Origin::none(), authority, colder, signature));
+
+		// replaying the first preference would move the rewards back
+		assert_noop!(
+			UtxoModule::set_payout_key(Origin::none(), authority, cold, first),
+			Error::<Test>::InvalidPayoutSignature,
+		);
+		let call = crate::Call::<Test>::set_payout_key(authority, cold, first);
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::BadProof.into(),
+		);
+
+		// and nobody but the authority can set one
+		let signature = payout_preference_signature(&cold, 2, &bob());
+		assert_noop!(
+			UtxoModule::set_payout_key(Origin::none(), authority, cold, signature),
+			Error::<Test>::InvalidPayoutSignature,
+		);
+		assert_eq!(UtxoModule::payout_preference(authority), Some(colder));
+	});
+}
+
+#[test]
+fn rewards_go_to_the_author_found_by_any_engine() {
+	new_test_ext().execute_with(|| {
+		BlockSubsidy::set(5);
+		let author = pubkey_of(&bob());
+
+		// a digest of some other engine than Aura names bob as the author
+		System::deposit_log(DigestItem::PreRuntime(TEST_ENGINE_ID, bob().public().encode()));
+		next_block();
+
+		assert_eq!(UtxoModule::balance_of(&author), 5);
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 0);
+	});
+}
+
+fn restricted_ext(approved: &[&sr25519::Pair]) -> sp_io::TestExternalities {
+	RestrictedMode::set(true);
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: vec![genesis_utxo()],
+		approved_keys: approved.iter().map(|pair| pubkey_of(pair)).collect(),
+		..Default::default()
+	})
+}
+
+#[test]
+fn restricted_mode_only_pays_approved_keys() {
+	restricted_ext(&[&alice()]).execute_with(|| {
+		let tx = genesis_spend_to(5, &bob());
+		assert_eq!(
+			UtxoModule::validate_transaction(&tx),
+			Err(Error::<Test>::RecipientNotApproved.into()),
+		);
+
+		assert_noop!(
+			UtxoModule::approve_key(Origin::signed(ALICE), pubkey_of(&bob())),
+			sp_runtime::DispatchError::BadOrigin,
+		);
+		assert_ok!(UtxoModule::approve_key(Origin::root(), pubkey_of(&bob())));
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+#[test]
+fn restricted_mode_only_refunds_to_approved_keys() {
+	restricted_ext(&[&bob()]).execute_with(|| {
+		let refund_to = Some(pubkey_of(&alice()));
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![TransactionOutput { refund_to, ..output(100, &bob()) }],
+			..Default::default()
+		}, &alice());
+		assert_eq!(
+			UtxoModule::validate_transaction(&tx).map(|_| ()),
+			Err(Error::<Test>::RecipientNotApproved.into()),
+		);
+
+		// nor once the refund key was revoked since
+		assert_ok!(UtxoModule::approve_key(Origin::root(), pubkey_of(&alice())));
+		let payment = refundable_payment_to_bob();
+		assert_ok!(UtxoModule::revoke_key(Origin::root(), pubkey_of(&alice())));
+		assert_noop!(
+			UtxoModule::reject_payment(Origin::signed(BOB), payment),
+			Error::<Test>::RecipientNotApproved,
+		);
+	});
+}
+
+#[test]
+fn revoked_keys_can_still_spend_their_outputs() {
+	restricted_ext(&[&alice(), &bob()]).execute_with(|| {
+		assert_ok!(UtxoModule::revoke_key(Origin::root(), pubkey_of(&alice())));
+		assert_noop!(
+			UtxoModule::revoke_key(Origin::root(), pubkey_of(&alice())),
+			Error::<Test>::NotApproved,
+		);
+
+		// alice's value can leave to an approved key, but not come back
+		let tx = genesis_spend_to(5, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		let back = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 0))],
+			outputs: vec![output(90, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), back).map_err(|e| e.error),
+			Error::<Test>::RecipientNotApproved,
+		);
+	});
+}
+
+#[test]
+fn spends_are_rate_limited_per_window() {
+	new_test_ext().execute_with(|| {
+		MaxSpendsPerWindow::set(1);
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(genesis_outpoint())
+			.add_output(pubkey_of(&alice()), 50)
+			.add_output(pubkey_of(&alice()), 50)
+			.sign_with(&alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		assert_eq!(UtxoModule::spend_count(pubkey_of(&alice())), 1);
+
+		let again = |index| TxBuilder::new(GENESIS_HASH)
+			.add_input(outpoint_of(&tx, index))
+			.add_output(pubkey_of(&bob()), 50)
+			.sign_with(&alice());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), again(0)).map_err(|e| e.error),
+			Error::<Test>::RateLimited,
+		);
+
+		// the next window starts at block 10
+		while System::block_number() < 9 {
+			next_block();
+		}
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), again(0)).map_err(|e| e.error),
+			Error::<Test>::RateLimited,
+		);
+		next_block();
+		assert_eq!(UtxoModule::spend_count(pubkey_of(&alice())), 0);
+		assert_ok!(UtxoModule::spend(Origin::none(), again(0)));
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), again(1)).map_err(|e| e.error),
+			Error::<Test>::RateLimited,
+		);
+	});
+}
+
+#[test]
+fn clearing_spend_counts_weighs_a_write_per_key() {
+	new_test_ext().execute_with(|| {
+		let (_, bob_outpoint) = split_genesis();
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(bob_outpoint)
+			.add_output(pubkey_of(&validator()), 50)
+			.sign_with(&bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(crate::SpendCountEntries::get(), 2);
+		assert_eq!(UtxoModule::try_state(System::block_number()), Ok(()));
+
+		// the next window starts at block 10
+		let db = <Test as frame_system::Config>::DbWeight::get();
+		assert_eq!(UtxoModule::on_initialize(9), db.writes(6));
+		assert_eq!(UtxoModule::on_initialize(10), db.writes(6) + db.reads_writes(1, 3));
+		assert_eq!(crate::SpendCountEntries::get(), 0);
+		assert_eq!(UtxoModule::spend_count(pubkey_of(&bob())), 0);
+	});
+}
+
+#[test]
+fn multi_owner_spends_count_against_every_owner() {
+	let bobs = |value| TransactionOutput { value, pubkey: pubkey_of(&bob()), ..Default::default() };
+	ext_with_genesis(crate::GenesisConfig {
+		genesis_utxos: vec![genesis_utxo(), bobs(50), bobs(40)],
+		..Default::default()
+	}).execute_with(|| {
+		MaxSpendsPerWindow::set(1);
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(genesis_outpoint())
+			.add_input(BlakeTwo256::hash_of(&bobs(50)))
+			.add_output(pubkey_of(&validator()), 145)
+			.sign_input(0, &alice())
+			.sign_input(1, &bob())
+			.build();
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+
+		// bob used up the window even though alice contributed too
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(BlakeTwo256::hash_of(&bobs(40)))
+			.add_output(pubkey_of(&validator()), 35)
+			.sign_with(&bob());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx).map_err(|e| e.error),
+			Error::<Test>::RateLimited,
+		);
+	});
+}
+
+#[test]
+fn transactions_are_indexed_by_block_until_retention_ends() {
+	new_test_ext().execute_with(|| {
+		let first = TxBuilder::new(GENESIS_HASH)
+			.add_input(genesis_outpoint())
+			.add_output(pubkey_of(&alice()), 50)
+			.add_output(pubkey_of(&alice()), 45)
+			.sign_with(&alice());
+		let spend_to_bob = |index, value| TxBuilder::new(GENESIS_HASH)
+			.add_input(outpoint_of(&first, index))
+			.add_output(pubkey_of(&bob()), value)
+			.sign_with(&alice());
+		let txs = vec![first.clone(), spend_to_bob(0, 49), spend_to_bob(1, 44)];
+
+		for tx in txs.iter() {
+			assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		}
+		let txids: Vec<_> = txs.iter().map(crate::txid).collect();
+		assert_eq!(UtxoModule::transactions_at(1), txids);
+
+		// kept for `TxIndexRetention` blocks
+		next_block();
+		next_block();
+		assert_eq!(UtxoModule::transactions_at(1), txids);
+		assert!(UtxoModule::transactions_at(2).is_empty());
+
+		next_block();
+		assert!(UtxoModule::transactions_at(1).is_empty());
+	});
+}
+
+#[test]
+fn fee_estimates_grow_with_the_shape_of_the_transaction() {
+	assert!(UtxoModule::estimate_fee(2, 2) > UtxoModule::estimate_fee(1, 1));
+	assert!(UtxoModule::estimate_fee(1, 1) > 0);
+}
+
+#[test]
+fn fee_estimates_price_the_whole_weight_of_a_minimal_transaction() {
+	let tx = Transaction {
+		inputs: vec![TransactionInput::default()],
+		outputs: vec![TransactionOutput::default()],
+		..Default::default()
+	};
+	let weight = crate::spend_weight::<()>(&tx) as u128;
+	assert!(weight > <() as WeightInfo>::spend(1, 1, 0) as u128);
+	assert_eq!(crate::minimal_spend_weight::<()>(1, 1) as u128, weight);
+
+	// prices above a unit of value per unit of weight
+	WeightPrice::set(FixedU128::saturating_from_integer(3));
+	assert_eq!(UtxoModule::estimate_fee(1, 1), 3 * weight);
+
+	// and below it, rounded up
+	WeightPrice::set(FixedU128::saturating_from_rational(1, 7));
+	assert_eq!(UtxoModule::estimate_fee(1, 1), (weight + 6) / 7);
+}
+
+#[test]
+fn minimal_sizes_match_the_encoding_of_the_transaction() {
+	for (inputs, outputs) in [(0, 0), (1, 1), (3, 2), (64, 70)].iter().copied() {
+		let tx = Transaction {
+			inputs: vec![TransactionInput::default(); inputs as usize],
+			outputs: vec![TransactionOutput::default(); outputs as usize],
+			..Default::default()
+		};
+		assert_eq!(crate::minimal_tx_size(inputs, outputs), tx.encoded_size() as u64);
+	}
+
+	// no transaction is built, any count is priced
+	assert!(UtxoModule::estimate_fee(u32::max_value(), u32::max_value()) > 0);
+}
+
+#[test]
+fn sighash_bytes_match_encoding_a_stripped_copy() {
+	let tx = Transaction {
+		inputs: vec![
+			TransactionInput {
+				outpoint: H256::repeat_byte(1),
+				sigscript: H512::repeat_byte(2),
+				witness: vec![3; 40],
+			},
+			input(H256::repeat_byte(4)),
+		],
+		outputs: vec![output(5, &alice()), output(6, &bob())],
+		change_to: Some(pubkey_of(&alice())),
+		valid_until: Some(7),
+		burn: Some(9),
+		aggregate_sig: Some((pubkey_of(&alice()), H512::repeat_byte(8))),
+	};
+
+	let mut stripped = tx.clone();
+	for input in stripped.inputs.iter_mut() {
+		input.sigscript = H512::zero();
+		input.witness.clear();
+	}
+	stripped.aggregate_sig = None;
+
+	assert_eq!(crate::sighash_bytes(&tx), stripped.encode());
+	assert_eq!(crate::sighash_bytes(&Transaction::default()), Transaction::default().encode());
+}
+
+// Alice's genesis value moved to an output only spendable to pay bob 90
+fn escrow_to_bob() -> H256 {
+	let template = vec![output(90, &bob())];
+	let locked = TransactionOutput {
+		covenant: Some(crate::covenant_hash(&template)),
+		..output(95, &alice())
+	};
+	let tx = TxBuilder::new(GENESIS_HASH)
+		.add_input(genesis_outpoint())
+		.push_output(locked)
+		.sign_with(&alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	outpoint_of(&tx, 0)
+}
+
+#[test]
+fn covenant_outputs_pay_the_committed_outputs() {
+	new_test_ext().execute_with(|| {
+		let escrow = escrow_to_bob();
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(escrow)
+			.add_output(pubkey_of(&bob()), 90)
+			.sign_with(&alice());
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&bob())), 90);
+	});
+}
+
+#[test]
+fn covenant_outputs_reject_other_outputs() {
+	new_test_ext().execute_with(|| {
+		let escrow = escrow_to_bob();
+		let spend_to = |outputs: Vec<TransactionOutput>| {
+			let mut builder = TxBuilder::new(GENESIS_HASH);
+			builder.add_input(escrow);
+			for output in outputs {
+				builder.push_output(output);
+			}
+			builder.sign_with(&alice())
+		};
+
+		for outputs in vec![
+			vec![output(90, &alice())],
+			vec![output(89, &bob())],
+			vec![output(45, &bob()), output(45, &bob())],
+		] {
+			assert_noop!(
+				UtxoModule::spend(Origin::none(), spend_to(outputs)).map_err(|e| e.error),
+				Error::<Test>::CovenantViolated,
+			);
+		}
+	});
+}
+
+#[test]
+fn duplicate_outpoints_are_found_whatever_their_signatures() {
+	let signed_input = |outpoint: u8, sig: u8| TransactionInput {
+		sigscript: H512::repeat_byte(sig),
+		..input(H256::repeat_byte(outpoint))
+	};
+
+	assert_eq!(crate::duplicate_outpoint(&[]), None);
+	assert_eq!(crate::duplicate_outpoint(&[signed_input(1, 1)]), None);
+	assert_eq!(crate::duplicate_outpoint(&[signed_input(1, 1), signed_input(2, 1)]), None);
+	assert_eq!(
+		crate::duplicate_outpoint(&[signed_input(1, 1), signed_input(1, 2)]),
+		Some(H256::repeat_byte(1)),
+	);
+
+	let mut inputs: Vec<_> = (0..64).map(|index| signed_input(index, index)).collect();
+	assert_eq!(crate::duplicate_outpoint(&inputs), None);
+	inputs[40].outpoint = H256::repeat_byte(7);
+	assert_eq!(crate::duplicate_outpoint(&inputs), Some(H256::repeat_byte(7)));
+}
+
+// outputs holding each amount of each asset, owned by nobody in particular
+fn outputs_of(values: &[(AssetId, u128)]) -> Vec<TransactionOutput> {
+	values
+		.iter()
+		.map(|&(asset_id, value)| TransactionOutput { asset_id, value, ..Default::default() })
+		.collect()
+}
+
+// the total value `outputs` hold of `asset_id`, `None` if it overflows
+fn checked_total(outputs: &[TransactionOutput], asset_id: AssetId) -> Option<u128> {
+	outputs
+		.iter()
+		.filter(|output| output.asset_id == asset_id)
+		.try_fold(0u128, |total, output| total.checked_add(output.value))
+}
+
+// mostly small values so that balances come out even, with the odd huge one to overflow sums
+fn a_value() -> impl Strategy<Value = u128> {
+	prop_oneof![4 => 0u128..100, 1 => any::<u128>()]
+}
+
+proptest! {
+	#[test]
+	fn asset_balances_conserve_every_asset(
+		spent in vec((0u32..3, a_value()), 0..6),
+		outputs in vec((0u32..3, a_value()), 0..6),
+	) {
+		let (spent, outputs) = (outputs_of(&spent), outputs_of(&outputs));
+		let funded = outputs
+			.iter()
+			.all(|output| spent.iter().any(|utxo| utxo.asset_id == output.asset_id));
+		let balanced = funded && (0..3).all(|asset_id| {
+			match (checked_total(&spent, asset_id), checked_total(&outputs, asset_id)) {
+				(Some(total_input), Some(total_output)) => total_output <= total_input
+					&& (asset_id == NATIVE_ASSET || total_output == total_input),
+				_ => false,
+			}
+		});
+
+		match UtxoModule::check_asset_balances(&spent, &outputs, 0) {
+			Ok(leftover) => {
+				prop_assert!(balanced);
+				let native_in = checked_total(&spent, NATIVE_ASSET).unwrap();
+				let native_out = checked_total(&outputs, NATIVE_ASSET).unwrap();
+				prop_assert_eq!(leftover, native_in - native_out);
+			},
+			Err(_) => prop_assert!(!balanced),
+		}
+	}
+
+	#[test]
+	fn duplicate_inputs_are_always_found(outpoints in vec(0u8..16, 0..20)) {
+		let inputs: Vec<_> = outpoints.iter().map(|byte| input(H256::repeat_byte(*byte))).collect();
+		let duplicated = outpoints
+			.iter()
+			.enumerate()
+			.any(|(index, outpoint)| outpoints[index + 1..].contains(outpoint));
+
+		prop_assert_eq!(UtxoModule::check_no_duplicate_inputs(&inputs).is_err(), duplicated);
+	}
+}
+
+#[test]
+fn asset_balances_regressions() {
+	let balances = |spent: &[(AssetId, u128)], outputs: &[(AssetId, u128)]| {
+		UtxoModule::check_asset_balances(&outputs_of(spent), &outputs_of(outputs), 0)
+			.map_err(<&str>::from)
+	};
+
+	assert_eq!(balances(&[(0, 10), (1, 5)], &[(0, 7), (1, 5)]), Ok(3));
+	// nothing spent, nothing created
+	assert_eq!(balances(&[], &[]), Ok(0));
+	assert_eq!(balances(&[(0, 10)], &[(1, 5)]), Err("AssetImbalance"));
+	assert_eq!(balances(&[(0, 10), (1, 5)], &[(0, 10), (1, 4)]), Err("NonNativeFee"));
+	assert_eq!(balances(&[(0, 10)], &[(0, 11)]), Err("OutputsExceedInputs"));
+	assert_eq!(balances(&[(1, u128::max_value()), (1, 1)], &[(1, 1)]), Err("ValueOverflow"));
+	// the other asset overflowing can't be hidden by a balanced native one
+	assert_eq!(
+		balances(&[(0, 1), (1, 1)], &[(0, 1), (1, u128::max_value()), (1, 1)]),
+		Err("ValueOverflow"),
+	);
+}
+
+#[test]
+fn outputs_are_stored_under_the_keys_validation_checked() {
+	new_test_ext().execute_with(|| {
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(genesis_outpoint())
+			.add_output(pubkey_of(&bob()), 40)
+			.add_output(pubkey_of(&bob()), 40)
+			.change_to(pubkey_of(&alice()))
+			.sign_with(&alice());
+
+		let validated = UtxoModule::validate_transaction(&tx).unwrap();
+		let (change_key, change) = validated.change.unwrap();
+		assert_eq!(change, GENESIS_UTXO_VALUE - 80 - AutoChangeFee::get());
+		assert_eq!(validated.reward, AutoChangeFee::get());
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		let mut keys = validated.output_keys.clone();
+		keys.push(change_key);
+		assert_eq!(crate::CreatedThisBlock::get(), keys);
+		assert_eq!(UtxoModule::outpoints_for(&tx), keys);
+		assert_eq!(UtxoStore::get(change_key).map(|utxo| utxo.value), Some(change));
+	});
+}
+
+#[test]
+fn transactions_are_bounded_by_max_tx_bytes() {
+	new_test_ext().execute_with(|| {
+		let tx = genesis_spend_to(10, &bob());
+		// 1 + 97 for the input, 1 + 60 for the output, 4 for `change_to`, `valid_until`, `burn`
+		// and `aggregate_sig`
+		assert_eq!(tx.encoded_size(), 163);
+
+		MaxTxBytes::set(162);
+		assert_eq!(
+			UtxoModule::validate_transaction(&tx),
+			Err(Error::<Test>::TransactionTooLarge.into()),
+		);
+
+		MaxTxBytes::set(163);
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+// split the genesis output into `GENESIS_UTXO_VALUE / 10` outputs of 10 owned by alice
+fn alice_dust() -> Vec<H256> {
+	let tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(10, &alice()); (GENESIS_UTXO_VALUE / 10) as usize],
+		..Default::default()
+	}, &alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+	next_block();
+
+	(0..tx.outputs.len() as u32).map(|index| outpoint_of(&tx, index)).collect()
+}
+
+#[test]
+fn consolidate_merges_dust_at_a_discount() {
+	new_test_ext().execute_with(|| {
+		let dust = alice_dust();
+		assert_eq!(dust.len(), 10);
+
+		let tx = signed(Transaction {
+			inputs: dust.iter().cloned().map(input).collect(),
+			outputs: vec![output(95, &alice())],
+			..Default::default()
+		}, &alice());
+
+		let spend = crate::Call::<Test>::spend(tx.clone()).get_dispatch_info().weight;
+		let consolidate = crate::Call::<Test>::consolidate(tx.clone()).get_dispatch_info().weight;
+		assert_eq!(consolidate, ConsolidationFeeMultiplier::get() * spend);
+
+		let call = crate::Call::<Test>::consolidate(tx.clone());
+		assert_ok!(UtxoModule::validate_unsigned(TransactionSource::External, &call));
+
+		assert_ok!(UtxoModule::consolidate(Origin::none(), tx.clone()));
+		assert!(dust.iter().all(|outpoint| !UtxoStore::contains_key(outpoint)));
+		assert_eq!(UtxoStore::get(outpoint_of(&tx, 0)), Some(output(95, &alice())));
+		assert_eq!(UtxoModule::reward_total(), 5);
+	});
+}
+
+#[test]
+fn consolidate_rejects_paying_another_key() {
+	new_test_ext().execute_with(|| {
+		let dust = alice_dust();
+		let tx = signed(Transaction {
+			inputs: dust[..3].iter().cloned().map(input).collect(),
+			outputs: vec![output(20, &alice()), output(10, &bob())],
+			..Default::default()
+		}, &alice());
+
+		assert_noop!(
+			UtxoModule::consolidate(Origin::none(), tx.clone()).map_err(|e| e.error),
+			Error::<Test>::NotSelfConsolidation,
+		);
+
+		// the same transaction goes through as a plain spend
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+#[test]
+fn consolidate_rejects_not_shrinking_the_utxo_set() {
+	new_test_ext().execute_with(|| {
+		let dust = alice_dust();
+		let tx = signed(Transaction {
+			inputs: dust[..2].iter().cloned().map(input).collect(),
+			outputs: vec![output(10, &alice()), output(10, &alice())],
+			..Default::default()
+		}, &alice());
+
+		assert_noop!(
+			UtxoModule::consolidate(Origin::none(), tx.clone()).map_err(|e| e.error),
+			Error::<Test>::ConsolidationNotShrinking,
+		);
+
+		let call = crate::Call::<Test>::consolidate(tx);
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Call.into(),
+		);
+	});
+}
+
+// an output of `value` for `pair` that can be spent up to and including block `expiry`
+fn expiring_output(value: u128, pair: &sr25519::Pair, expiry: u64) -> TransactionOutput {
+	TransactionOutput { expiry: Some(expiry), ..output(value, pair) }
+}
+
+#[test]
+fn outputs_are_unspendable_after_their_expiry() {
+	new_test_ext().execute_with(|| {
+		// created in block 1, both expire after block 3
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![expiring_output(60, &bob(), 3), expiring_output(40, &bob(), 3)],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		next_block();
+		next_block();
+
+		// expiry is checked against the current block, 3 is the last one they can be spent in
+		assert_eq!(System::block_number(), 3);
+		let before = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 0))],
+			outputs: vec![output(60, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), before));
+
+		next_block();
+		let after = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 1))],
+			outputs: vec![output(40, &alice())],
+			..Default::default()
+		}, &bob());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), after.clone()).map_err(|e| e.error),
+			Error::<Test>::UtxoExpired,
+		);
+
+		// without sweeping, the expired output stays in the set for good
+		assert!(UtxoStore::contains_key(outpoint_of(&tx, 1)));
+		let call = crate::Call::<Test>::spend(after);
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into(),
+		);
+	});
+}
+
+#[test]
+fn outputs_cannot_be_created_expired() {
+	new_test_ext().execute_with(|| {
+		next_block();
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![expiring_output(100, &bob(), 1)],
+			..Default::default()
+		}, &alice());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx).map_err(|e| e.error),
+			Error::<Test>::UtxoExpired,
+		);
+	});
+}
+
+fn memo_output(value: u128, pair: &sr25519::Pair, memo: &[u8]) -> TransactionOutput {
+	TransactionOutput { memo: memo.to_vec(), ..output(value, pair) }
+}
+
+#[test]
+fn memos_are_stored_and_announced_with_their_outputs() {
+	new_test_ext().execute_with(|| {
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![memo_output(40, &bob(), b"invoice 42"), output(60, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		let outpoint = outpoint_of(&tx, 0);
+		assert_eq!(UtxoStore::get(outpoint).unwrap().memo, b"invoice 42".to_vec());
+		assert!(UtxoStore::get(outpoint_of(&tx, 1)).unwrap().memo.is_empty());
+		let memos = System::events().into_iter().filter(|record| matches!(
+			record.event,
+			Event::pallet_utxo(crate::Event::OutputMemo(..)),
+		)).count();
+		assert_eq!(memos, 1);
+		assert!(System::events().into_iter().any(|record| record.event
+			== Event::pallet_utxo(crate::Event::OutputMemo(outpoint, b"invoice 42".to_vec()))));
+	});
+}
+
+#[test]
+fn memos_are_signed_and_bounded() {
+	new_test_ext().execute_with(|| {
+		// swapping the memo of a signed output breaks its signatures
+		let mut tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![memo_output(100, &bob(), b"for rent")],
+			..Default::default()
+		}, &alice());
+		tx.outputs[0].memo = b"for nothing".to_vec();
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx).map_err(|e| e.error),
+			"Signature must be valid",
+		);
+
+		let too_long = vec![7; MaxMemoBytes::get() as usize + 1];
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![memo_output(100, &bob(), &too_long)],
+			..Default::default()
+		}, &alice());
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx).map_err(|e| e.error),
+			Error::<Test>::MemoTooLarge,
+		);
+
+		let longest = &too_long[1..];
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![memo_output(100, &bob(), longest)],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+#[test]
+fn expired_outputs_are_swept_into_the_reward() {
+	new_test_ext().execute_with(|| {
+		SweepExpired::set(true);
+		let author = pubkey_of(&validator());
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![expiring_output(40, &bob(), 2), output(60, &alice())],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		// still spendable in block 2, swept at its end
+		next_block();
+		assert!(UtxoStore::contains_key(outpoint_of(&tx, 0)));
+		assert_eq!(UtxoModule::balance_of(&author), 0);
+
+		next_block();
+		assert!(!UtxoStore::contains_key(outpoint_of(&tx, 0)));
+		assert_eq!(UtxoModule::balance_of(&author), 40);
+		assert_eq!(UtxoModule::total_supply(), GENESIS_UTXO_VALUE);
+		assert!(System::events().into_iter().any(|record| record.event
+			== Event::pallet_utxo(crate::Event::OutputsExpired(1, 40))));
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+	});
+}
+
+#[test]
+fn a_single_bad_signature_fails_a_batch() {
+	batch_verify_ext().execute_with(|| {
+		BatchVerify::set(true);
+		let dust = alice_dust();
+		let tx = signed(Transaction {
+			inputs: dust.iter().cloned().map(input).collect(),
+			outputs: vec![output(95, &bob())],
+			..Default::default()
+		}, &alice());
+
+		let mut forged = tx.clone();
+		forged.inputs[6].sigscript = signature_of(&tx, &bob());
+		assert_eq!(UtxoModule::validate_transaction(&forged), Err("Signature must be valid"));
+		assert_eq!(UtxoModule::dry_run(&forged), Err(DryRunError {
+			input_index: Some(6),
+			reason: b"Signature must be valid".to_vec(),
+		}));
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+	});
+}
+
+#[test]
+fn vested_tranches_unlock_one_after_another() {
+	new_test_ext().execute_with(|| {
+		let bob_key = pubkey_of(&bob());
+		let schedule = vec![(3, 20), (5, 30), (7, 40)];
+		let unsigned = UtxoModule::build_vest(&genesis_outpoint(), bob_key, &schedule).unwrap();
+		let tx = signed(unsigned, &alice());
+		let tranches: Vec<_> = (0..3).map(|index| outpoint_of(&tx, index)).collect();
+
+		let call = crate::Call::<Test>::vest(tx.inputs[0].clone(), bob_key, schedule.clone());
+		assert_ok!(UtxoModule::validate_unsigned(TransactionSource::External, &call));
+		assert_ok!(UtxoModule::vest(Origin::none(), tx.inputs[0].clone(), bob_key, schedule));
+		assert_eq!(UtxoStore::get(tranches[1]).unwrap().unlocks_at, Some(5));
+
+		let claim = |index: usize, value| signed(Transaction {
+			inputs: vec![input(tranches[index])],
+			outputs: vec![output(value - 1, &alice())],
+			..Default::default()
+		}, &bob());
+		for (index, (unlocks_at, value)) in [(3, 20), (5, 30), (7, 40)].iter().copied().enumerate() {
+			while System::block_number() < unlocks_at {
+				assert_noop!(
+					UtxoModule::spend(Origin::none(), claim(index, value)).map_err(|e| e.error),
+					Error::<Test>::UtxoLocked,
+				);
+				next_block();
+			}
+			assert_ok!(UtxoModule::spend(Origin::none(), claim(index, value)));
+		}
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), 19 + 29 + 39);
+	});
+}
+
+#[test]
+fn vesting_schedules_must_fit_the_input() {
+	new_test_ext().execute_with(|| {
+		let vest = |schedule: &[(u64, u128)]| {
+			UtxoModule::build_vest(&genesis_outpoint(), pubkey_of(&bob()), schedule)
+				.map_err(<&str>::from)
+		};
+
+		assert_eq!(vest(&[]), Err("EmptySchedule"));
+		assert_eq!(vest(&[(3, 20), (5, 0)]), Err("ZeroValueOutput"));
+		// nothing left for the fee
+		assert_eq!(vest(&[(3, 50), (5, 50)]), Err("InsufficientFunds"));
+		assert_ok!(vest(&[(3, 50), (5, 49)]));
+
+		// a locked spend waits in the future rather than being thrown away
+		let tx = signed(vest(&[(3, 99)]).unwrap(), &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		let early = signed(Transaction {
+			inputs: vec![input(outpoint_of(&tx, 0))],
+			outputs: vec![output(98, &alice())],
+			..Default::default()
+		}, &bob());
+		let call = crate::Call::<Test>::spend(early);
+		assert_eq!(
+			UtxoModule::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Future.into(),
+		);
+	});
+}
+
+fn vesting_output(value: u128, pair: &sr25519::Pair, start: u64, end: u64) -> TransactionOutput {
+	TransactionOutput { vesting: Some(crate::Vesting { start, end }), ..output(value, pair) }
+}
+
+// 80 vesting to bob over blocks 10 to 20
+fn bob_vesting() -> H256 {
+	let tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![vesting_output(80, &bob(), 10, 20)],
+		..Default::default()
+	}, &alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	outpoint_of(&tx, 0)
+}
+
+#[test]
+fn vesting_outputs_unlock_linearly() {
+	new_test_ext().execute_with(|| {
+		let vesting = bob_vesting();
+		let take = |outputs| UtxoModule::validate_transaction(&signed(Transaction {
+			inputs: vec![input(vesting)],
+			outputs,
+			..Default::default()
+		}, &bob()));
+
+		// nothing vested yet, whatever the remainder
+		System::set_block_number(10);
+		assert_eq!(take(vec![output(1, &alice())]), Err("VestingRemainderMissing"));
+		assert_eq!(
+			take(vec![output(1, &alice()), vesting_output(79, &bob(), 10, 20)]),
+			Err("VestingRemainderMissing"),
+		);
+
+		// half of it at 15, the other half vests on from there
+		System::set_block_number(15);
+		assert_eq!(take(vec![output(79, &alice())]), Err("VestingRemainderMissing"));
+		assert_eq!(
+			take(vec![output(39, &alice()), vesting_output(40, &alice(), 15, 20)]),
+			Err("VestingRemainderMissing"),
+		);
+		assert_eq!(
+			take(vec![output(39, &alice()), vesting_output(40, &bob(), 10, 20)]),
+			Err("VestingRemainderMissing"),
+		);
+		let half = vec![output(39, &alice()), vesting_output(40, &bob(), 15, 20)];
+		assert_ok!(take(half));
+
+		// all of it at the end
+		System::set_block_number(20);
+		assert_ok!(take(vec![output(79, &alice())]));
+	});
+}
+
+#[test]
+fn vesting_remainders_keep_the_pace_of_the_original() {
+	new_test_ext().execute_with(|| {
+		let vesting = bob_vesting();
+		System::set_block_number(15);
+		let tx = signed(Transaction {
+			inputs: vec![input(vesting)],
+			outputs: vec![output(39, &alice()), vesting_output(40, &bob(), 15, 20)],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+		// spending the remainder right away frees nothing more
+		let remainder = outpoint_of(&tx, 1);
+		System::set_block_number(16);
+		let again = |value| signed(Transaction {
+			inputs: vec![input(remainder)],
+			outputs: vec![output(value, &alice()), vesting_output(40 - value - 1, &bob(), 16, 20)],
+			..Default::default()
+		}, &bob());
+		assert_eq!(UtxoModule::validate_transaction(&again(9)), Err("VestingRemainderMissing"));
+		assert_ok!(UtxoModule::spend(Origin::none(), again(7)));
+
+		let schedule = crate::Vesting { start: 10, end: 20 };
+		assert_eq!(schedule.vested(80, 15), 40);
+		assert_eq!(schedule.vested(80, 9), 0);
+		assert_eq!(schedule.vested(u128::max_value(), 15), u128::max_value() / 2);
+		assert_eq!(schedule.vested(u128::max_value(), 21), u128::max_value());
+		assert_eq!(
+			UtxoModule::validate_transaction(&signed(Transaction {
+				inputs: vec![input(outpoint_of(&again(7), 0))],
+				outputs: vec![vesting_output(1, &bob(), 20, 20)],
+				..Default::default()
+			}, &alice())),
+			Err("InvalidVesting"),
+		);
+	});
+}
+
+// 60 for alice which bob may spend from block 5 on, and 30 only alice may spend
+fn alice_with_bob_fallback() -> (H256, H256) {
+	let fallback = crate::Fallback { pubkey: pubkey_of(&bob()), after: 5 };
+	let tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![
+			TransactionOutput { fallback: Some(fallback), ..output(60, &alice()) },
+			output(30, &alice()),
+		],
+		..Default::default()
+	}, &alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	(outpoint_of(&tx, 0), outpoint_of(&tx, 1))
+}
+
+#[test]
+fn fallback_keys_may_spend_after_their_block() {
+	new_test_ext().execute_with(|| {
+		let (outpoint, _) = alice_with_bob_fallback();
+		let spend_by = |signer: &sr25519::Pair| signed(Transaction {
+			inputs: vec![input(outpoint)],
+			outputs: vec![output(50, &validator())],
+			..Default::default()
+		}, signer);
+
+		System::set_block_number(4);
+		assert_ok!(UtxoModule::validate_transaction(&spend_by(&alice())));
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), spend_by(&bob())).map_err(|e| e.error),
+			"Signature must be valid",
+		);
+		assert_eq!(
+			UtxoModule::dry_run(&spend_by(&bob())).map_err(|e| e.input_index),
+			Err(Some(0)),
+		);
+
+		System::set_block_number(5);
+		assert_ok!(UtxoModule::validate_transaction(&spend_by(&alice())));
+		assert_ok!(UtxoModule::spend(Origin::none(), spend_by(&bob())));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 50);
+	});
+}
+
+#[test]
+fn the_owner_keeps_spending_past_the_fallback_block() {
+	new_test_ext().execute_with(|| {
+		let (with_fallback, plain) = alice_with_bob_fallback();
+		System::set_block_number(50);
+
+		let both = Transaction {
+			inputs: vec![input(with_fallback), input(plain)],
+			outputs: vec![output(85, &alice())],
+			..Default::default()
+		};
+
+		// the fallback key can't sign for the input without one
+		assert_eq!(
+			UtxoModule::dry_run(&signed(both.clone(), &bob())).map_err(|e| e.input_index),
+			Err(Some(1)),
+		);
+
+		// checked apart from the input only the owner may sign, whose signature is batched
+		assert_ok!(UtxoModule::spend(Origin::none(), signed(both, &alice())));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&alice())), 85);
+	});
+}
+
+fn ownership_proof(outpoint: &H256, signer: &sr25519::Pair) -> H512 {
+	H512::from(signer.sign(&crate::ownership_payload(outpoint, &System::parent_hash())).0)
+}
+
+#[test]
+fn owners_prove_control_without_spending() {
+	new_test_ext().execute_with(|| {
+		System::set_parent_hash(H256::repeat_byte(9));
+		let proof = ownership_proof(&genesis_outpoint(), &alice());
+
+		assert_ok!(UtxoModule::prove_ownership(Origin::signed(ALICE), genesis_outpoint(), proof));
+		assert_eq!(UtxoStore::get(genesis_outpoint()), Some(genesis_utxo()));
+		assert!(System::events().into_iter().any(|record| record.event == Event::pallet_utxo(
+			crate::Event::OwnershipProven(genesis_outpoint(), pubkey_of(&alice())),
+		)));
+
+		// a proof only holds against the block it was made for
+		System::set_parent_hash(H256::repeat_byte(10));
+		assert_noop!(
+			UtxoModule::prove_ownership(Origin::signed(ALICE), genesis_outpoint(), proof),
+			Error::<Test>::InvalidOwnershipProof,
+		);
+	});
+}
+
+#[test]
+fn only_the_owner_can_prove_ownership() {
+	new_test_ext().execute_with(|| {
+		let proof = ownership_proof(&genesis_outpoint(), &bob());
+		assert_noop!(
+			UtxoModule::prove_ownership(Origin::signed(ALICE), genesis_outpoint(), proof),
+			Error::<Test>::InvalidOwnershipProof,
+		);
+
+		let unknown = H256::repeat_byte(1);
+		let proof = ownership_proof(&unknown, &alice());
+		assert_noop!(
+			UtxoModule::prove_ownership(Origin::signed(ALICE), unknown, proof),
+			Error::<Test>::UnknownOutpoint,
+		);
+	});
+}
+
+#[test]
+fn outputs_can_not_pay_the_zero_key() {
+	new_test_ext().execute_with(|| {
+		let to_nobody = TransactionOutput { pubkey: H256::zero(), ..output(90, &bob()) };
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![to_nobody],
+			..Default::default()
+		}, &alice());
+		assert_noop!(UtxoModule::spend(Origin::none(), tx), Error::<Test>::UnspendableRecipient);
+
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![output(50, &bob())],
+			change_to: Some(H256::zero()),
+			..Default::default()
+		}, &alice());
+		assert_noop!(UtxoModule::spend(Origin::none(), tx), Error::<Test>::UnspendableRecipient);
+
+		// nor can a rejection refund to it
+		let refund_to = Some(H256::zero());
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![TransactionOutput { refund_to, ..output(90, &bob()) }],
+			..Default::default()
+		}, &alice());
+		assert_noop!(UtxoModule::spend(Origin::none(), tx), Error::<Test>::UnspendableRecipient);
+
+		assert_noop!(
+			UtxoModule::transfer(Origin::signed(ALICE), H256::zero(), 10),
+			Error::<Test>::UnspendableRecipient,
+		);
+	});
+}
+
+#[test]
+fn burns_destroy_value_outside_of_any_output() {
+	new_test_ext().execute_with(|| {
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(genesis_outpoint())
+			.add_output(pubkey_of(&bob()), 60)
+			.burn(30)
+			.sign_with(&alice());
+		let validated = UtxoModule::validate_transaction(&tx).unwrap();
+		assert_eq!(validated.reward, 10);
+		assert_eq!(validated.output_keys.len(), 1);
+
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		assert_eq!(UtxoModule::total_supply(), 70);
+		assert_eq!(UtxoModule::value_burned(), 30);
+		assert_eq!(UtxoStore::iter().count(), 1);
+		assert!(System::events().into_iter().any(|record| record.event == Event::pallet_utxo(
+			crate::Event::ValueBurned(crate::txid(&tx), 30),
+		)));
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+
+		// burning is signed like the rest of the transaction
+		let mut tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(outpoint_of(&tx, 0))
+			.add_output(pubkey_of(&alice()), 50)
+			.burn(5)
+			.sign_with(&bob());
+		tx.burn = Some(10);
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx.clone()).map_err(|e| e.error),
+			"Signature must be valid",
+		);
+
+		// and can't take more than the outputs leave
+		tx.burn = Some(11);
+		let tx = signed(tx, &bob());
+		assert_noop!(UtxoModule::spend(Origin::none(), tx), Error::<Test>::OutputsExceedInputs);
+	});
+}
+
+// Reserve alice's genesis output until block `until` for the close paying bob 90
+fn reserve_genesis_for_bob(until: u64, signer: &sr25519::Pair) -> DispatchResult {
+	reserve_for_bob(genesis_outpoint(), until, signer)
+}
+
+// reserve `outpoint` for a channel close paying bob 90 until block `until`
+fn reserve_for_bob(outpoint: H256, until: u64, signer: &sr25519::Pair) -> DispatchResult {
+	let closing = crate::covenant_hash(&[output(90, &bob())]);
+	let payload = crate::reservation_payload(&outpoint, until, &closing, &GENESIS_HASH);
+	let sigscript = H512::from(signer.sign(&payload).0);
+
+	UtxoModule::reserve(Origin::signed(ALICE), outpoint, sigscript, until, closing)
+}
+
+#[test]
+fn reserved_outputs_are_neither_burned_nor_rejected() {
+	new_test_ext().execute_with(|| {
+		let minted = mint_test_unique();
+		assert_ok!(reserve_for_bob(minted, 3, &alice()));
+		assert_noop!(
+			UtxoModule::burn_unique(Origin::signed(ALICE), minted),
+			Error::<Test>::UtxoReserved,
+		);
+
+		let payment = refundable_payment_to_bob();
+		assert_ok!(reserve_for_bob(payment, 3, &bob()));
+		assert_noop!(
+			UtxoModule::reject_payment(Origin::signed(BOB), payment),
+			Error::<Test>::UtxoReserved,
+		);
+
+		// until the reservations lapse
+		System::set_block_number(4);
+		assert_ok!(UtxoModule::burn_unique(Origin::signed(ALICE), minted));
+		assert_ok!(UtxoModule::reject_payment(Origin::signed(BOB), payment));
+	});
+}
+
+#[test]
+fn reserved_outputs_only_go_to_their_channel_close() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(reserve_genesis_for_bob(3, &alice()));
+		assert_eq!(
+			UtxoModule::reservation_of(genesis_outpoint()),
+			Some((3, crate::covenant_hash(&[output(90, &bob())]))),
+		);
+
+		let elsewhere = genesis_spend_to(10, &validator());
+		assert_noop!(UtxoModule::spend(Origin::none(), elsewhere), Error::<Test>::UtxoReserved);
+		// transfers leave reserved outputs alone
+		assert_noop!(
+			UtxoModule::transfer(Origin::signed(ALICE), pubkey_of(&bob()), 10),
+			Error::<Test>::InsufficientFunds,
+		);
+
+		System::set_block_number(3);
+		assert_ok!(UtxoModule::spend(Origin::none(), genesis_spend_to(10, &bob())));
+		assert_eq!(UtxoModule::reservation_of(genesis_outpoint()), None);
+	});
+}
+
+#[test]
+fn reservations_lapse_after_their_deadline() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(reserve_genesis_for_bob(2, &alice()));
+
+		System::set_block_number(3);
+		assert_ok!(UtxoModule::spend(Origin::none(), genesis_spend_to(10, &validator())));
+	});
+}
+
+#[test]
+fn reservations_need_the_owner_and_a_deadline_ahead() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(reserve_genesis_for_bob(3, &bob()), Error::<Test>::InvalidReservationSignature);
+		assert_noop!(reserve_genesis_for_bob(1, &alice()), Error::<Test>::ReservationLapsed);
+
+		assert_ok!(reserve_genesis_for_bob(3, &alice()));
+		assert_noop!(reserve_genesis_for_bob(4, &alice()), Error::<Test>::AlreadyReserved);
+
+		// once lapsed the output may be reserved again
+		System::set_block_number(4);
+		assert_ok!(reserve_genesis_for_bob(5, &alice()));
+	});
+}
+
+// 60 for alice in escrow with bob, both have to sign its spends
+fn alice_in_escrow_with_bob() -> H256 {
+	let tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![
+			TransactionOutput { cosigner: Some(pubkey_of(&bob())), ..output(60, &alice()) },
+		],
+		..Default::default()
+	}, &alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	outpoint_of(&tx, 0)
+}
+
+// `tx` with the signatures of `signers` in the witness of its first input, in that order
+fn cosigned(mut tx: Transaction, signers: &[&sr25519::Pair]) -> Transaction {
+	let payload = crate::signing_payload(&tx, &GENESIS_HASH);
+	tx.inputs[0].witness = signers
+		.iter()
+		.flat_map(|signer| signer.sign(&payload).0.to_vec())
+		.collect();
+
+	tx
+}
+
+#[test]
+fn escrows_need_both_signatures_in_either_order() {
+	new_test_ext().execute_with(|| {
+		let escrow = alice_in_escrow_with_bob();
+		let tx = Transaction {
+			inputs: vec![input(escrow)],
+			outputs: vec![output(50, &validator())],
+			..Default::default()
+		};
+
+		// the owner's signature alone doesn't do, wherever it is
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), signed(tx.clone(), &alice())).map_err(|e| e.error),
+			"Signature must be valid",
+		);
+		assert_eq!(
+			UtxoModule::validate_transaction(&cosigned(tx.clone(), &[&alice(), &alice()])),
+			Err("Signature must be valid"),
+		);
+		assert_eq!(
+			UtxoModule::validate_transaction(&cosigned(tx.clone(), &[&bob(), &bob()])),
+			Err("Signature must be valid"),
+		);
+		// nor is the very same signature twice
+		let mut repeated = cosigned(tx.clone(), &[&alice()]);
+		repeated.inputs[0].witness = repeated.inputs[0].witness.repeat(2);
+		assert_eq!(repeated.inputs[0].witness.len(), crate::COSIGNED_WITNESS_BYTES);
+		assert_eq!(UtxoModule::validate_transaction(&repeated), Err("Signature must be valid"));
+		assert_eq!(
+			UtxoModule::validate_transaction(&cosigned(tx.clone(), &[&alice(), &validator()])),
+			Err("Signature must be valid"),
+		);
+		assert_eq!(
+			UtxoModule::dry_run(&cosigned(tx.clone(), &[&alice()])).map_err(|e| e.input_index),
+			Err(Some(0)),
+		);
+
+		assert_ok!(UtxoModule::validate_transaction(&cosigned(tx.clone(), &[&bob(), &alice()])));
+		assert_ok!(UtxoModule::spend(Origin::none(), cosigned(tx, &[&alice(), &bob()])));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 50);
+	});
+}
+
+#[test]
+fn partial_transactions_collect_both_signatures_of_an_escrow() {
+	new_test_ext().execute_with(|| {
+		let escrow = alice_in_escrow_with_bob();
+		let tx = TxBuilder::new(GENESIS_HASH)
+			.add_input(escrow)
+			.add_output(pubkey_of(&validator()), 50)
+			.build();
+		let utxo = UtxoStore::get(escrow).unwrap();
+		let mut partial = PartialTransaction::new(tx, vec![utxo], GENESIS_HASH);
+
+		partial.sign(&alice());
+		assert_eq!(
+			partial.finalize(),
+			Err(IncompleteError { input: 0, pubkey: pubkey_of(&bob()) }),
+		);
+
+		partial.sign(&bob());
+		let tx = partial.finalize().unwrap();
+		assert_eq!(tx.inputs[0].witness.len(), crate::COSIGNED_WITNESS_BYTES);
+		assert_ok!(UtxoModule::spend(Origin::none(), tx));
+		assert_eq!(UtxoModule::balance_of(&pubkey_of(&validator())), 50);
+	});
+}
+
+#[test]
+fn cosigned_spends_weigh_a_second_signature() {
+	new_test_ext().execute_with(|| {
+		let escrow = alice_in_escrow_with_bob();
+		let tx = Transaction {
+			inputs: vec![input(escrow)],
+			outputs: vec![output(50, &validator())],
+			..Default::default()
+		};
+		let weight_of = |tx: Transaction| crate::Call::<Test>::spend(tx).get_dispatch_info().weight;
+
+		let plain = signed(tx.clone(), &alice());
+		let mut padded = plain.clone();
+		padded.inputs[0].witness = vec![0; crate::COSIGNED_WITNESS_BYTES - 1];
+		let escrowed = cosigned(tx, &[&alice(), &bob()]);
+		assert_eq!(
+			weight_of(escrowed.clone()) - weight_of(padded),
+			<() as WeightInfo>::cosigned(1) + <() as WeightInfo>::spend(1, 1, 1)
+				- <() as WeightInfo>::spend(1, 1, 0) + <() as WeightInfo>::spend_bytes(1),
+		);
+		assert!(weight_of(escrowed) > weight_of(plain));
+	});
+}
+
+#[test]
+fn transactions_validate_against_an_in_memory_utxo_set() {
+	use std::collections::BTreeMap;
+
+	let tx = genesis_spend_to(10, &bob());
+	let utxos = vec![(genesis_outpoint(), genesis_utxo())].into_iter().collect::<BTreeMap<_, _>>();
+
+	// no genesis, the only output there is is the one of the in-memory set
+	sp_io::TestExternalities::default().execute_with(|| {
+		assert_eq!(
+			UtxoModule::validate_transaction(&tx).map(|_| ()),
+			Err(Error::<Test>::MissingInput.into()),
+		);
+
+		let validated = UtxoModule::validate_transaction_against(&utxos, &tx).unwrap();
+		assert_eq!(validated.output_keys, UtxoModule::output_keys(&tx));
+		assert_eq!(validated.reward, 10);
+
+		// the outputs of an applied transaction are found in the set as well
+		let mut applied = utxos.clone();
+		applied.insert(validated.output_keys[0], output(GENESIS_UTXO_VALUE - 10, &bob()));
+		assert_eq!(
+			UtxoModule::validate_transaction_against(&applied, &tx).map(|_| ()),
+			Err(Error::<Test>::AlreadyApplied.into()),
+		);
+		assert!(UtxoStore::iter().next().is_none());
+	});
+}
+
+#[test]
+fn outpoint_filters_have_no_false_negatives_and_few_false_positives() {
+	let outpoints: Vec<_> = (0..1000u32).map(|i| BlakeTwo256::hash_of(&(b"held", i))).collect();
+	let filter = OutpointFilter::from_outpoints(&outpoints, 42);
+
+	assert!(outpoints.iter().all(|outpoint| filter.may_contain(outpoint)));
+
+	// about 1% expected
+	let false_positives = (0..1000u32)
+		.map(|i| BlakeTwo256::hash_of(&(b"absent", i)))
+		.filter(|outpoint| filter.may_contain(outpoint))
+		.count();
+	assert!(false_positives < 50, "{} false positives", false_positives);
+
+	// another seed, other bits
+	assert_ne!(filter, OutpointFilter::from_outpoints(&outpoints, 43));
+}
+
+// a set of outputs counting how often it was read
+struct CountingUtxos {
+	utxos: std::collections::BTreeMap<H256, TransactionOutput>,
+	reads: std::cell::Cell<u32>,
+}
+
+impl UtxoProvider for CountingUtxos {
+	fn utxo(&self, outpoint: &H256) -> Option<TransactionOutput> {
+		self.reads.set(self.reads.get() + 1);
+		self.utxos.utxo(outpoint)
+	}
+}
+
+#[test]
+fn filtered_lookups_only_read_outpoints_the_filter_may_hold() {
+	let tx = genesis_spend_to(10, &bob());
+	let counting = CountingUtxos {
+		utxos: vec![(genesis_outpoint(), genesis_utxo())].into_iter().collect(),
+		reads: Default::default(),
+	};
+	let filter = OutpointFilter::from_outpoints(&[genesis_outpoint()], 0);
+	let utxos = FilteredUtxos::new(&filter, &counting);
+
+	sp_io::TestExternalities::default().execute_with(|| {
+		let mut bogus = tx.clone();
+		bogus.inputs[0].outpoint = H256::repeat_byte(7);
+		assert_eq!(
+			UtxoModule::validate_transaction_against(&utxos, &signed(bogus, &alice())).map(|_| ()),
+			Err(Error::<Test>::MissingInput.into()),
+		);
+		assert_eq!(counting.reads.get(), 0);
+
+		// the genesis output is read, the outputs of the spend are not
+		assert_ok!(UtxoModule::validate_transaction_against(&utxos, &tx));
+		assert_eq!(counting.reads.get(), 1);
+	});
+}
+
+#[test]
+fn false_positives_of_outpoint_filters_fall_through_to_the_utxo_set() {
+	new_test_ext().execute_with(|| {
+		// far too small for what it holds, it may hold about anything
+		let mut filter = OutpointFilter::with_capacity(0, 0);
+		for i in 0..200u32 {
+			filter.insert(&BlakeTwo256::hash_of(&i));
+		}
+		let bogus = H256::repeat_byte(7);
+		assert!(filter.may_contain(&bogus));
+
+		let mut tx = genesis_spend_to(10, &bob());
+		tx.inputs[0].outpoint = bogus;
+		assert_eq!(FilteredUtxos::new(&filter, crate::OnChainUtxos).utxo(&bogus), None);
+		assert_eq!(
+			UtxoModule::validate_transaction_filtered(&filter, &signed(tx, &alice())).map(|_| ()),
+			Err(Error::<Test>::MissingInput.into()),
+		);
+	});
+}
+
+#[test]
+fn validation_against_a_filter_of_the_utxo_set_accepts_valid_spends() {
+	new_test_ext().execute_with(|| {
+		let outpoints: Vec<_> = UtxoStore::iter().map(|(outpoint, _)| outpoint).collect();
+		let filter = OutpointFilter::from_outpoints(&outpoints, 0);
+		let tx = genesis_spend_to(10, &bob());
+
+		let validated = UtxoModule::validate_transaction_filtered(&filter, &tx).unwrap();
+		assert_eq!(validated.reward, 10);
+
+		// a filter missing the genesis output turns the spend away
+		let stale = OutpointFilter::from_outpoints(&[], 0);
+		assert_eq!(
+			UtxoModule::validate_transaction_filtered(&stale, &tx).map(|_| ()),
+			Err(Error::<Test>::MissingInput.into()),
+		);
+	});
+}
+
+// alice's genesis output into 50 for bob and 30 for herself, each holding a deposit of 5
+fn split_genesis_with_deposits() -> Transaction {
+	OutputDeposit::set(5);
+	let tx = signed(Transaction {
+		inputs: vec![input(genesis_outpoint())],
+		outputs: vec![output(50, &bob()), output(30, &alice())],
+		..Default::default()
+	}, &alice());
+	assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+
+	tx
+}
+
+#[test]
+fn created_outputs_hold_a_deposit() {
+	new_test_ext().execute_with(|| {
+		let tx = split_genesis_with_deposits();
+		assert_eq!(UtxoModule::deposit_of(outpoint_of(&tx, 0)), 5);
+		assert_eq!(UtxoModule::deposit_of(outpoint_of(&tx, 1)), 5);
+		// the genesis output had no deposit to return
+		assert_eq!(UtxoModule::reward_total(), 100 - 80 - 10);
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+
+		// what is left has to cover the deposits
+		let bob_outpoint = outpoint_of(&tx, 0);
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), signed(Transaction {
+				inputs: vec![input(bob_outpoint)],
+				outputs: vec![output(30, &bob()), output(20, &validator())],
+				..Default::default()
+			}, &bob())).map_err(|e| e.error),
+			Error::<Test>::DepositNotCovered,
+		);
+
+		// the change output holds one too
+		let change = signed(Transaction {
+			inputs: vec![input(bob_outpoint)],
+			outputs: vec![output(40, &validator())],
+			change_to: Some(pubkey_of(&bob())),
+			..Default::default()
+		}, &bob());
+		let validated = UtxoModule::validate_transaction(&change).unwrap();
+		let (change_key, value) = validated.change.unwrap();
+		assert_eq!(value, 55 - 40 - 5 - AutoChangeFee::get() - 5);
+		assert_ok!(UtxoModule::spend(Origin::none(), change));
+		assert_eq!(UtxoModule::deposit_of(change_key), 5);
+
+		// reward outputs hold none
+		UtxoModule::on_finalize(System::block_number());
+		let rewards = crate::CoinbaseUtxos::iter_keys().collect::<Vec<_>>();
+		assert!(!rewards.is_empty());
+		assert!(rewards.iter().all(|outpoint| UtxoModule::deposit_of(outpoint) == 0));
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+	});
+}
+
+#[test]
+fn deposits_are_returned_to_the_spending_transaction() {
+	new_test_ext().execute_with(|| {
+		let bob_outpoint = outpoint_of(&split_genesis_with_deposits(), 0);
+		OutputDeposit::set(0);
+
+		// the 5 held by the output pay for more than its value
+		let tx = signed(Transaction {
+			inputs: vec![input(bob_outpoint)],
+			outputs: vec![output(54, &validator())],
+			..Default::default()
+		}, &bob());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		assert_eq!(UtxoModule::deposit_of(bob_outpoint), 0);
+		assert_eq!(UtxoModule::deposit_of(outpoint_of(&tx, 0)), 0);
+		assert_eq!(UtxoModule::reward_total(), 10 + 1);
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+	});
+}
+
+#[test]
+fn expired_outputs_pay_their_deposit_to_the_reward() {
+	new_test_ext().execute_with(|| {
+		SweepExpired::set(true);
+		OutputDeposit::set(5);
+		let tx = signed(Transaction {
+			inputs: vec![input(genesis_outpoint())],
+			outputs: vec![expiring_output(90, &bob(), 1)],
+			..Default::default()
+		}, &alice());
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+		assert_eq!(UtxoModule::reward_total(), 5);
+
+		UtxoModule::on_finalize(1);
+		assert_eq!(UtxoModule::deposit_of(outpoint_of(&tx, 0)), 0);
+		assert!(System::events().into_iter().any(|record| record.event == Event::pallet_utxo(
+			crate::Event::OutputsExpired(1, 95),
+		)));
+		assert_ok!(UtxoModule::try_state(System::block_number()));
+	});
+}
 
 #[test]
-fn it_works_for_default_value() {
+fn signatures_only_hold_on_the_chain_they_were_made_for() {
+	// the same genesis outputs on a chain with another genesis hash
+	let other_chain = H256::repeat_byte(7);
+	let mut mainnet = new_test_ext();
+	let mut testnet = new_test_ext();
+	testnet.execute_with(|| frame_system::BlockHash::<Test>::insert(0, other_chain));
+
+	let tx = genesis_spend_to(10, &bob());
+	let testnet_tx = TxBuilder::new(other_chain)
+		.add_input(genesis_outpoint())
+		.add_output(pubkey_of(&bob()), GENESIS_UTXO_VALUE - 10)
+		.sign_with(&alice());
+	// the chains agree on what the transaction is, just not on its signatures
+	assert_eq!(crate::txid(&tx), crate::txid(&testnet_tx));
+	assert_ne!(tx.inputs[0].sigscript, testnet_tx.inputs[0].sigscript);
+
+	mainnet.execute_with(|| {
+		assert_eq!(UtxoModule::genesis_hash(), GENESIS_HASH);
+		assert_eq!(
+			UtxoModule::validate_transaction(&testnet_tx),
+			Err("Signature must be valid"),
+		);
+		assert_ok!(UtxoModule::spend(Origin::none(), tx.clone()));
+	});
+
+	testnet.execute_with(|| {
+		assert_noop!(
+			UtxoModule::spend(Origin::none(), tx.clone()).map_err(|e| e.error),
+			"Signature must be valid",
+		);
+		assert_ok!(UtxoModule::spend(Origin::none(), testnet_tx));
+	});
+}
+
+// `value` minted to alice
+fn mint_to_alice(value: u128) -> H256 {
+	assert_ok!(UtxoModule::mint(Origin::root(), vec![output(value, &alice())]));
+
+	*UtxoModule::created_in_current_block().last().unwrap()
+}
+
+#[test]
+fn splits_share_the_value_with_the_remainder_first() {
 	new_test_ext().execute_with(|| {
-		// Dispatch a signed extrinsic.
-		assert_ok!(UtxoModule::do_something(Origin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(UtxoModule::something(), Some(42));
+		// 100 once the minimum fee is paid
+		let outpoint = mint_to_alice(101);
+		let bob_key = pubkey_of(&bob());
+		let tx = signed(UtxoModule::build_split(&outpoint, 3, bob_key).unwrap(), &alice());
+		let sigscript = tx.inputs[0].sigscript;
+
+		let call = crate::Call::<Test>::split(outpoint, sigscript, 3, bob_key);
+		assert_ok!(UtxoModule::validate_unsigned(TransactionSource::External, &call));
+		assert_ok!(UtxoModule::split(Origin::none(), outpoint, sigscript, 3, bob_key));
+
+		let values: Vec<_> = (0..3)
+			.map(|index| UtxoStore::get(outpoint_of(&tx, index)).map(|utxo| utxo.value))
+			.collect();
+		assert_eq!(values, vec![Some(34), Some(33), Some(33)]);
+		assert_eq!(UtxoModule::reward_total(), MinimumFee::get());
 	});
 }
 
 #[test]
-fn correct_error_for_none_value() {
+fn splits_need_outputs_worth_spending() {
 	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
+		let outpoint = mint_to_alice(101);
+		let split = |outpoint: &H256, n| {
+			UtxoModule::build_split(outpoint, n, pubkey_of(&bob())).map_err(<&str>::from)
+		};
+
+		assert_eq!(split(&outpoint, 0).map(|_| ()), Err("EmptySplit"));
+		let too_many = MaxOutputsPerBlock::get() + 1;
+		assert_eq!(split(&outpoint, too_many).map(|_| ()), Err("TooManyOutputs"));
+
+		// 4 once the minimum fee is paid, a third of it is the minimum fee of spending it
+		let small = mint_to_alice(5);
+		assert_eq!(split(&small, 3).map(|_| ()), Err("SplitIntoDust"));
+		assert_ok!(split(&small, 2));
+
+		// a signature over another split doesn't do
+		let tx = signed(split(&outpoint, 2).unwrap(), &alice());
 		assert_noop!(
-			UtxoModule::cause_error(Origin::signed(1)),
-			Error::<Test>::NoneValue
+			UtxoModule::split(Origin::none(), outpoint, tx.inputs[0].sigscript, 4, pubkey_of(&bob()))
+				.map_err(|e| e.error),
+			"Signature must be valid",
 		);
 	});
 }
+
+#[test]
+fn block_filters_match_what_the_block_paid_and_spent() {
+	new_test_ext().execute_with(|| {
+		// the genesis outputs aren't part of the block
+		UtxoModule::on_initialize(1);
+		System::set_parent_hash(H256::repeat_byte(3));
+		assert_ok!(UtxoModule::spend(Origin::none(), genesis_spend_to(10, &bob())));
+		next_block();
+
+		let filter = block_filter(&System::digest()).unwrap();
+		let reward = UtxoStore::get(UtxoModule::last_reward_outpoint().unwrap()).unwrap();
+		let key = H256::repeat_byte(3);
+		// bob and the author paid, and the spent genesis output
+		assert_eq!(filter.n, 3);
+		assert!(filter.matches(key.as_ref(), &pubkey_of(&bob())));
+		assert!(filter.matches(key.as_ref(), &reward.pubkey));
+		assert!(filter.matches(key.as_ref(), &genesis_outpoint()));
+		assert!(!filter.matches(key.as_ref(), &H256::repeat_byte(8)));
+
+		// light clients key the filter by the parent hash in the header
+		let header = |parent_hash| {
+			Header::new(1, H256::zero(), H256::zero(), parent_hash, System::digest())
+		};
+		assert!(header_may_concern(&header(key), &[H256::repeat_byte(8), pubkey_of(&bob())]));
+		assert!(!header_may_concern(&header(key), &[H256::repeat_byte(8)]));
+		assert!(header_may_concern(&Header::new_from_number(1), &[H256::repeat_byte(8)]));
+	});
+}
+
+#[test]
+fn block_filters_rarely_match_items_of_other_blocks() {
+	let items: Vec<_> = (0..100u32).map(|i| BlakeTwo256::hash_of(&(b"paid", i))).collect();
+	let filter = BlockFilter::build(b"parent", &items);
+	assert!(items.iter().all(|item| filter.matches(b"parent", item)));
+
+	// about one in `M`, the odds of a single one among these are below 2%
+	let others: Vec<_> = (0..10_000u32).map(|i| BlakeTwo256::hash_of(&(b"other", i))).collect();
+	let matched = others.iter().filter(|item| filter.matches(b"parent", *item)).count();
+	assert!(matched <= 1, "{} false positives", matched);
+	assert!(!filter.matches_any(b"parent", &others[..1000]));
+
+	// the items of a block match another block's filter no better
+	let other_block = BlockFilter::build(b"other parent", &others);
+	assert!(!other_block.matches_any(b"other parent", &items));
+
+	// duplicates count once, an empty block matches nothing
+	let doubled = [items.clone(), items.clone()].concat();
+	assert_eq!(BlockFilter::build(b"parent", &doubled), filter);
+	assert!(!BlockFilter::build(b"parent", &[]).matches_any(b"parent", &items));
+}
@@ -0,0 +1,40 @@
+//! Storage proofs that an output is part of the UTXO set, against the state root of a block.
+//!
+//! Unlike the proofs of [`crate::merkle`], these need no commitment of their own: `UtxoStore` lives
+//! in the state trie, so the trie nodes on the path to an entry prove it against the state root
+//! every header already carries. They are generated from a state backend, outside the runtime.
+
+use codec::Decode;
+use sp_core::H256;
+use sp_runtime::traits::BlakeTwo256;
+use sp_state_machine::{prove_read, read_proof_check, Backend, StorageProof};
+
+use crate::{utxo_storage_key, TransactionOutput};
+
+/// The output stored under `outpoint` in `backend` and the proof of it against the root of
+/// `backend`. `None` if the outpoint isn't part of the UTXO set.
+pub fn prove_utxo<B: Backend<BlakeTwo256>>(
+	backend: B,
+	outpoint: &H256,
+) -> Option<(TransactionOutput, StorageProof)> {
+	let key = utxo_storage_key(outpoint);
+	let encoded = backend.storage(&key).ok()??;
+	let utxo = TransactionOutput::decode(&mut &encoded[..]).ok()?;
+	let proof = prove_read(backend, &[&key]).ok()?;
+
+	Some((utxo, proof))
+}
+
+/// The output `proof` proves to be stored under `outpoint` in the state with root `root`, `None`
+/// if the proof is invalid or doesn't cover an output under `outpoint`.
+pub fn check_utxo_proof(
+	root: H256,
+	outpoint: &H256,
+	proof: StorageProof,
+) -> Option<TransactionOutput> {
+	let key = utxo_storage_key(outpoint);
+	let mut values = read_proof_check::<BlakeTwo256, _>(root, proof, &[&key]).ok()?;
+	let encoded = values.remove(&key)??;
+
+	TransactionOutput::decode(&mut &encoded[..]).ok()
+}
@@ -0,0 +1,81 @@
+//! Weights for pallet_utxo.
+//!
+//! The values below are not benchmark output: no machine has run `benchmarking.rs` against them
+//! yet. The database parts count the reads and writes on the code path of `spend`, the compute
+//! parts are hand estimates. Until they are replaced by the output of this command on reference
+//! hardware, a chain should only go live with a `WeightInfo` of its own:
+//!
+//! ```sh
+//! cargo build --release --features runtime-benchmarks
+//! ./target/release/node-utxo benchmark --chain dev --execution wasm --wasm-execution compiled \
+//!     --pallet pallet_utxo --extrinsic '*' --steps 50 --repeat 20 \
+//!     --output pallets/utxo/src/weights.rs
+//! ```
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_utxo.
+pub trait WeightInfo {
+	/// `spend` of a transaction with `i` inputs, `o` outputs and `w` bytes of witnesses.
+	fn spend(i: u32, o: u32, w: u32) -> Weight;
+	/// The part of `spend` growing with the `b` bytes of the encoded transaction: decoding it and
+	/// hashing its signing payload for the txid, the signatures and the output keys.
+	fn spend_bytes(b: u32) -> Weight;
+	/// The part of `spend` for the `c` inputs signed by a cosigner too: up to two more signature
+	/// verifications each, to try both orders of the signatures.
+	fn cosigned(c: u32) -> Weight;
+}
+
+/// Weights for pallet_utxo using the runtime's database weights.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn spend(i: u32, o: u32, w: u32) -> Weight {
+		(25_000_000 as Weight)
+			// signature verification dominates every input
+			.saturating_add((92_000_000 as Weight).saturating_mul(i as Weight))
+			.saturating_add((18_000_000 as Weight).saturating_mul(o as Weight))
+			.saturating_add((10 as Weight).saturating_mul(w as Weight))
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().reads((3 as Weight).saturating_mul(i as Weight)))
+			.saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(o as Weight)))
+			.saturating_add(T::DbWeight::get().writes(5 as Weight))
+			.saturating_add(T::DbWeight::get().writes((4 as Weight).saturating_mul(i as Weight)))
+			.saturating_add(T::DbWeight::get().writes((4 as Weight).saturating_mul(o as Weight)))
+	}
+	fn spend_bytes(b: u32) -> Weight {
+		(0 as Weight)
+			.saturating_add((4_000 as Weight).saturating_mul(b as Weight))
+	}
+	fn cosigned(c: u32) -> Weight {
+		(0 as Weight)
+			.saturating_add((184_000_000 as Weight).saturating_mul(c as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn spend(i: u32, o: u32, w: u32) -> Weight {
+		(25_000_000 as Weight)
+			.saturating_add((92_000_000 as Weight).saturating_mul(i as Weight))
+			.saturating_add((18_000_000 as Weight).saturating_mul(o as Weight))
+			.saturating_add((10 as Weight).saturating_mul(w as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((3 as Weight).saturating_mul(i as Weight)))
+			.saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(o as Weight)))
+			.saturating_add(RocksDbWeight::get().writes(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((4 as Weight).saturating_mul(i as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((4 as Weight).saturating_mul(o as Weight)))
+	}
+	fn spend_bytes(b: u32) -> Weight {
+		(0 as Weight)
+			.saturating_add((4_000 as Weight).saturating_mul(b as Weight))
+	}
+	fn cosigned(c: u32) -> Weight {
+		(0 as Weight)
+			.saturating_add((184_000_000 as Weight).saturating_mul(c as Weight))
+	}
+}
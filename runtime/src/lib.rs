@@ -7,14 +7,14 @@
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
 use sp_std::prelude::*;
-use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
+use sp_core::{crypto::KeyTypeId, OpaqueMetadata, H256};
 use sp_runtime::{
-	ConsensusEngineId,
+	ConsensusEngineId, FixedPointNumber, FixedU128,
 	ApplyExtrinsicResult, generic, create_runtime_str, impl_opaque_keys, MultiSignature,
-	transaction_validity::{TransactionValidity, TransactionSource},
+	transaction_validity::{TransactionLongevity, TransactionValidity, TransactionSource},
 };
 use sp_runtime::traits::{
-	BlakeTwo256, Block as BlockT, AccountIdLookup, Verify, IdentifyAccount, NumberFor,
+	BlakeTwo256, Block as BlockT, AccountIdLookup, Convert, Verify, IdentifyAccount, NumberFor,
 };
 use sp_api::impl_runtime_apis;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -107,7 +107,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_version: 100,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	// 2: input signatures are made over the genesis hash of the chain as well
+	// 3: transactions carry an optional aggregate signature
+	// 4: transactions may burn native value
+	transaction_version: 4,
 };
 
 /// This determines the average expected block time that we are targeting.
@@ -266,10 +269,96 @@ impl pallet_sudo::Config for Runtime {
 	type Call = Call;
 }
 
+parameter_types! {
+	/// No treasury cut for now, all fees go to the block author.
+	pub const TreasuryShare: Perbill = Perbill::from_percent(0);
+	/// The sr25519 key of the `Alice` dev account, which holds sudo in the bundled chain specs.
+	/// A production chain has to set its own treasury key.
+	pub const TreasuryPubkey: H256 = H256([
+		0xd4, 0x35, 0x93, 0xc7, 0x15, 0xfd, 0xd3, 0x1c, 0x61, 0x14, 0x1a, 0xbd, 0x04, 0xa9, 0x9f,
+		0xd6, 0x82, 0x2c, 0x85, 0x58, 0x85, 0x4c, 0xcd, 0xe3, 0x9a, 0x56, 0x84, 0xe7, 0xa5, 0x6d,
+		0xa2, 0x7d,
+	]);
+	pub const MinimumFee: pallet_utxo::Value = 1;
+	pub const MaxTxInputs: u32 = 64;
+	pub const MaxWitnessBytes: u32 = 1024;
+	pub const MaxOutputsPerBlock: u32 = 4096;
+	pub const AutoChangeFee: pallet_utxo::Value = 1;
+	/// Block subsidies start at `InitialIssuance` and halve every `HalvingInterval` blocks.
+	pub const InitialIssuance: pallet_utxo::Value = 50;
+	pub const HalvingInterval: u32 = 210_000;
+	/// Everything the halving schedule will ever issue.
+	pub const MaxSupply: pallet_utxo::Value = 21_000_000;
+	pub const BaseFeeRate: pallet_utxo::Value = 0;
+	pub const TargetBlockFullness: Perbill = Perbill::from_percent(50);
+	pub const EmitRejections: bool = false;
+	pub const CoinbaseMaturity: BlockNumber = 100;
+	pub const SameBlockSpendForbidden: bool = false;
+	pub const PoolLongevity: TransactionLongevity = 600;
+	pub const BurnFees: bool = false;
+	pub const MaxUtxoSetSize: u32 = u32::max_value();
+	pub const RestrictedMode: bool = false;
+	pub const MaxSpendsPerWindow: u32 = u32::max_value();
+	pub const SpendWindow: BlockNumber = HOURS;
+	pub const TxIndexRetention: BlockNumber = 7 * DAYS;
+	/// Quotes a spend of one input to one output at around sixteen units of value.
+	pub WeightPrice: FixedU128 = FixedU128::saturating_from_rational(1, 100_000_000);
+	pub const MaxTxBytes: u32 = 64 * 1024;
+	/// Consolidations pay a quarter of what a spend of the same size does.
+	pub const ConsolidationFeeMultiplier: Perbill = Perbill::from_percent(25);
+	pub const SweepExpired: bool = true;
+	pub const FeeHistoryLength: u32 = 64;
+	pub const MaxMemoBytes: u32 = 256;
+	/// Every output holds back a unit of value until it is spent, so filling the set costs.
+	pub const OutputDeposit: pallet_utxo::Value = 1;
+}
+
+/// UTXOs of a signed origin are owned by the raw bytes of its account's public key.
+pub struct AccountIdToPubkey;
+
+impl Convert<AccountId, H256> for AccountIdToPubkey {
+	fn convert(account: AccountId) -> H256 {
+		H256::from(<[u8; 32]>::from(account))
+	}
+}
+
 /// Configure the pallet-template in pallets/template.
 impl pallet_utxo::Config for Runtime {
 	type Event = Event;
+	type AuthorityId = AuraId;
 	type FindAuthor = pallet_aura::AuraAuthorId<Self>;
+	type TreasuryShare = TreasuryShare;
+	type TreasuryPubkey = TreasuryPubkey;
+	type AccountPubkey = AccountIdToPubkey;
+	type MinimumFee = MinimumFee;
+	type MaxTxInputs = MaxTxInputs;
+	type SignatureVerifier = pallet_utxo::signature::Sr25519Verifier;
+	type MaxWitnessBytes = MaxWitnessBytes;
+	type MaxOutputsPerBlock = MaxOutputsPerBlock;
+	type AutoChangeFee = AutoChangeFee;
+	type Issuance = pallet_utxo::issuance::HalvingIssuance<InitialIssuance, HalvingInterval>;
+	type MaxSupply = MaxSupply;
+	type WeightInfo = pallet_utxo::weights::SubstrateWeight<Runtime>;
+	type BaseFeeRate = BaseFeeRate;
+	type TargetBlockFullness = TargetBlockFullness;
+	type EmitRejections = EmitRejections;
+	type CoinbaseMaturity = CoinbaseMaturity;
+	type SameBlockSpendForbidden = SameBlockSpendForbidden;
+	type PoolLongevity = PoolLongevity;
+	type BurnFees = BurnFees;
+	type MaxUtxoSetSize = MaxUtxoSetSize;
+	type RestrictedMode = RestrictedMode;
+	type MaxSpendsPerWindow = MaxSpendsPerWindow;
+	type SpendWindow = SpendWindow;
+	type TxIndexRetention = TxIndexRetention;
+	type WeightPrice = WeightPrice;
+	type MaxTxBytes = MaxTxBytes;
+	type ConsolidationFeeMultiplier = ConsolidationFeeMultiplier;
+	type SweepExpired = SweepExpired;
+	type FeeHistoryLength = FeeHistoryLength;
+	type MaxMemoBytes = MaxMemoBytes;
+	type OutputDeposit = OutputDeposit;
+	type AuthorityToOwner = pallet_utxo::AuthorityKeyOwner;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -288,7 +377,7 @@ construct_runtime!(
 		TransactionPayment: pallet_transaction_payment::{Module, Storage},
 		Sudo: pallet_sudo::{Module, Call, Config<T>, Storage, Event<T>},
 		// Include the custom logic from the pallet-template in the runtime.
-		UtxoModule: pallet_utxo::{Module, Call, Storage, Event},
+		UtxoModule: pallet_utxo::{Module, Call, Storage, Event, ValidateUnsigned, Inherent},
 	}
 );
 
@@ -455,6 +544,65 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_utxo_runtime_api::UtxoApi<Block, BlockNumber> for Runtime {
+		fn balance_of(pubkey: H256) -> pallet_utxo::Value {
+			UtxoModule::balance_of(&pubkey)
+		}
+
+		fn utxo_proof(outpoint: H256) -> Option<pallet_utxo::merkle::UtxoProof> {
+			UtxoModule::utxo_proof(&outpoint)
+		}
+
+		fn utxo_storage_key(outpoint: H256) -> Vec<u8> {
+			pallet_utxo::utxo_storage_key(&outpoint)
+		}
+
+		fn dry_run(
+			tx: pallet_utxo::Transaction,
+		) -> Result<pallet_utxo::DryRunResult, pallet_utxo::DryRunError> {
+			UtxoModule::dry_run(&tx)
+		}
+
+		fn confirmations(outpoint: H256, at: BlockNumber) -> Option<u32> {
+			UtxoModule::confirmations(&outpoint, at)
+		}
+
+		fn outpoints_for(tx: pallet_utxo::Transaction) -> Vec<H256> {
+			UtxoModule::outpoints_for(&tx)
+		}
+
+		fn is_applied(tx: pallet_utxo::Transaction) -> pallet_utxo::AppliedStatus {
+			UtxoModule::is_applied(&tx)
+		}
+
+		fn utxos_paged(
+			start_key: Option<Vec<u8>>,
+			limit: u32,
+		) -> (Vec<(H256, pallet_utxo::TransactionOutput)>, Option<Vec<u8>>) {
+			UtxoModule::utxos_paged(start_key, limit)
+		}
+
+		fn transactions_at(block: BlockNumber) -> Vec<H256> {
+			UtxoModule::transactions_at(block)
+		}
+
+		fn created_in_current_block() -> Vec<H256> {
+			UtxoModule::created_in_current_block()
+		}
+
+		fn estimate_fee(inputs: u32, outputs: u32) -> pallet_utxo::Value {
+			UtxoModule::estimate_fee(inputs, outputs)
+		}
+
+		fn estimate_fee_within(target_blocks: u32, tx_weight: u64) -> pallet_utxo::Value {
+			UtxoModule::estimate_fee_within(target_blocks, tx_weight)
+		}
+
+		fn reward_info() -> pallet_utxo::RewardInfo {
+			UtxoModule::reward_info()
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn dispatch_benchmark(